@@ -1,12 +1,21 @@
-use ic_stable_structures::{ memory_manager::{ MemoryManager, MemoryId }, DefaultMemoryImpl, StableBTreeMap };
+use ic_stable_structures::{ memory_manager::{ MemoryManager, MemoryId }, DefaultMemoryImpl, StableBTreeMap, StableCell };
+use candid::Principal;
 use lib::types::{
+	admin::Admin,
+	admin_audit::AdminAuditEntry,
 	circuit::Circuit,
 	circuit_key::CircuitKey,
 	connector::Connector,
 	connector_key::ConnectorKey,
+	connector_secrets::ConnectorSecrets,
+	secret::Secret,
+	secret_key::SecretKey,
+	share::Share,
+	share_key::ShareKey,
 	trace::Trace,
 	trace_key::TraceKey,
 	user::User,
+	workspace::Workspace,
 };
 use std::cell::RefCell;
 use ic_stable_structures::memory_manager::VirtualMemory;
@@ -15,6 +24,15 @@ static CIRCUITS_MEMORY_ID: MemoryId = MemoryId::new(2);
 static TRACES_MEMORY_ID: MemoryId = MemoryId::new(3);
 static USERS_MEMORY_ID: MemoryId = MemoryId::new(4);
 static CONNECTORS_MEMORY_ID: MemoryId = MemoryId::new(5);
+static NODE_CANISTER_WASM_MEMORY_ID: MemoryId = MemoryId::new(6);
+static SHARES_MEMORY_ID: MemoryId = MemoryId::new(7);
+static WORKSPACES_MEMORY_ID: MemoryId = MemoryId::new(8);
+static SEQUENCES_MEMORY_ID: MemoryId = MemoryId::new(9);
+static CONNECTOR_SECRETS_MEMORY_ID: MemoryId = MemoryId::new(10);
+static SECRETS_MEMORY_ID: MemoryId = MemoryId::new(11);
+static VAULT_MASTER_KEY_MEMORY_ID: MemoryId = MemoryId::new(12);
+static ADMINS_MEMORY_ID: MemoryId = MemoryId::new(13);
+static ADMIN_AUDIT_LOG_MEMORY_ID: MemoryId = MemoryId::new(14);
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -25,6 +43,11 @@ pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 pub type StorageRef<K, V> = RefCell<StableBTreeMap<K, V, Memory>>;
 type MemManagerStore = RefCell<MemoryManager<DefaultMemoryImpl>>;
 
+/// A reference to a `StableCell` that is wrapped in a `RefCell`.
+///# Generics
+/// * `T` - The value type of the `StableCell`.
+pub type CellRef<T> = RefCell<StableCell<T, Memory>>;
+
 thread_local! {
 	pub static MEMORY_MANAGER: MemManagerStore = RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
 
@@ -43,4 +66,49 @@ thread_local! {
 	pub static CONNECTORS: StorageRef<ConnectorKey, Connector> = RefCell::new(
 		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(CONNECTORS_MEMORY_ID)))
 	);
+
+	/// Secrets of connectors by connector ID
+	pub static CONNECTOR_SECRETS: StorageRef<u32, ConnectorSecrets> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(CONNECTOR_SECRETS_MEMORY_ID)))
+	);
+
+	pub static SECRETS: StorageRef<SecretKey, Secret> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(SECRETS_MEMORY_ID)))
+	);
+
+	pub static SHARES: StorageRef<ShareKey, Share> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(SHARES_MEMORY_ID)))
+	);
+
+	pub static WORKSPACES: StorageRef<u32, Workspace> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(WORKSPACES_MEMORY_ID)))
+	);
+
+	pub static ADMINS: StorageRef<Principal, Admin> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(ADMINS_MEMORY_ID)))
+	);
+
+	/// Changes to the admins by audit entry ID
+	pub static ADMIN_AUDIT_LOG: StorageRef<u32, AdminAuditEntry> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(ADMIN_AUDIT_LOG_MEMORY_ID)))
+	);
+
+	pub static SEQUENCES: StorageRef<String, u32> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(SEQUENCES_MEMORY_ID)))
+	);
+
+	/// Wasm module installed on newly created node canisters
+	pub static NODE_CANISTER_WASM: CellRef<Vec<u8>> = RefCell::new(
+		StableCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(NODE_CANISTER_WASM_MEMORY_ID)), vec![]).expect(
+			"Failed to initialize NODE_CANISTER_WASM"
+		)
+	);
+
+	/// Master key the keys encrypting the secrets of each owner are derived from, generated on first use.
+	/// Kept next to the ciphertexts, so the vault is obfuscation only (see `SecretsStore`)
+	pub static VAULT_MASTER_KEY: CellRef<Vec<u8>> = RefCell::new(
+		StableCell::init(MEMORY_MANAGER.with(|m| m.borrow().get(VAULT_MASTER_KEY_MEMORY_ID)), vec![]).expect(
+			"Failed to initialize VAULT_MASTER_KEY"
+		)
+	);
 }
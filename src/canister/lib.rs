@@ -1,9 +1,20 @@
 use candid::Principal;
-use ic_cdk::query;
+use ic_cdk::{ caller, init, post_upgrade, query };
+use lib::types::admin::CanisterInitArgs;
+use modules::{
+	admins::admins_store::AdminsStore,
+	connectors::connectors_store::ConnectorsStore,
+	sequences::sequences_store::SequencesStore,
+};
 
 pub mod canister_storage;
 
 pub mod modules {
+	pub mod admins {
+		pub mod admins_controller;
+		pub mod admins_store;
+	}
+
 	pub mod circuits {
 		pub mod circuits_controller;
 		pub mod circuits_store;
@@ -14,6 +25,30 @@ pub mod modules {
 		pub mod connectors_store;
 	}
 
+	pub mod node_canisters {
+		pub mod node_canisters_controller;
+		pub mod node_canisters_store;
+	}
+
+	pub mod nodes {
+		pub mod nodes_controller;
+		pub mod nodes_store;
+	}
+
+	pub mod secrets {
+		pub mod secrets_controller;
+		pub mod secrets_store;
+	}
+
+	pub mod sequences {
+		pub mod sequences_store;
+	}
+
+	pub mod shares {
+		pub mod shares_controller;
+		pub mod shares_store;
+	}
+
 	pub mod traces {
 		pub mod traces_controller;
 		pub mod traces_store;
@@ -23,17 +58,44 @@ pub mod modules {
 		pub mod users_controller;
 		pub mod users_store;
 	}
+
+	pub mod workspaces {
+		pub mod workspaces_controller;
+		pub mod workspaces_store;
+	}
+}
+
+#[init]
+fn init(args: CanisterInitArgs) {
+	AdminsStore::init(args.admins, caller());
+}
+
+#[post_upgrade]
+fn post_upgrade() {
+	SequencesStore::migrate();
+	ConnectorsStore::migrate();
+	AdminsStore::migrate();
 }
 
 // Hacky way to expose the candid interface to the outside world
 #[query(name = "__get_candid_interface_tmp_hack")]
 pub fn __export_did_tmp_() -> String {
 	use candid::export_service;
+	use lib::types::admin::*;
+	use lib::types::admin_audit::*;
 	use lib::types::api_error::*;
 	use lib::types::circuit::*;
+	use lib::types::circuit_revision::*;
+	use lib::types::circuit_validation::*;
+	use lib::types::node::*;
+	use lib::types::share::*;
 	use lib::types::trace::*;
 	use lib::types::user::*;
 	use lib::types::connector::*;
+	use lib::types::workspace::*;
+	use lib::types::secret::*;
+	use std::collections::HashMap;
+	use ic_cdk::api::management_canister::http_request::{ TransformArgs, HttpResponse };
 
 	export_service!();
 	__export_service()
@@ -0,0 +1,36 @@
+use candid::Principal;
+use ic_cdk::{ caller, query, update };
+use lib::{ types::{ admin::Admin, admin_audit::AdminAuditEntry, api_error::ApiError }, utils::validate::validate_anonymous };
+use super::admins_store::AdminsStore;
+
+#[query]
+fn get_admins() -> Result<Vec<Admin>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AdminsStore::get_admins(caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn get_admin_audit_log() -> Result<Vec<AdminAuditEntry>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AdminsStore::get_audit_log(caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn add_admin(principal: Principal) -> Result<Admin, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AdminsStore::add_admin(principal, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn remove_admin(principal: Principal) -> Result<Admin, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => AdminsStore::remove_admin(principal, caller_principal),
+		Err(err) => Err(err),
+	}
+}
@@ -0,0 +1,184 @@
+use candid::Principal;
+use ic_cdk::{ api::{ is_controller, time }, id };
+use lib::{
+	types::{ admin::Admin, admin_audit::{ AdminAction, AdminAuditEntry }, api_error::ApiError },
+	utils::{ sequence::ADMIN_AUDIT_SEQUENCE, validate::validate_anonymous },
+};
+use crate::{
+	canister_storage::{ ADMINS, ADMIN_AUDIT_LOG },
+	modules::sequences::sequences_store::SequencesStore,
+};
+
+/// Admin of deployments installed before admins were kept in stable memory.
+static LEGACY_ADMIN: &str = "tzq3i-wbpyf-lg3xw-whs7v-znvez-btcmr-dcmho-moqam-qmjhf-xnzgt-dae";
+
+pub struct AdminsStore;
+
+impl AdminsStore {
+	/// Register the initial admins. The installer becomes the admin if none are given.
+	///
+	/// # Arguments
+	/// - `admins` - Initial admins
+	/// - `caller_principal` - Principal installing the canister
+	pub fn init(admins: Vec<Principal>, caller_principal: Principal) {
+		let admins = match admins.is_empty() {
+			true => vec![caller_principal],
+			false => admins,
+		};
+
+		for principal in admins {
+			if validate_anonymous(&principal).is_ok() {
+				Self::insert_admin(principal, id());
+			}
+		}
+	}
+
+	/// Register the legacy admin if the registry is empty, so upgraded deployments keep their admin.
+	pub fn migrate() {
+		if ADMINS.with(|admins| admins.borrow().is_empty()) {
+			let legacy_admin = Principal::from_text(LEGACY_ADMIN).expect("Invalid legacy admin principal");
+
+			Self::insert_admin(legacy_admin, id());
+		}
+	}
+
+	/// Validate admin.
+	///
+	/// # Arguments
+	/// - `principal` - Principal
+	///
+	/// # Returns
+	/// - `Result<Principal, ApiError>` - Principal or ApiError
+	pub fn validate_admin(principal: &Principal) -> Result<Principal, ApiError> {
+		if !ADMINS.with(|admins| admins.borrow().contains_key(principal)) {
+			return Err(ApiError::Unauthorized("UNAUTHORIZED".to_string()));
+		}
+
+		Ok(*principal)
+	}
+
+	/// Get admins.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller, an admin or controller
+	///
+	/// # Returns
+	/// - `Vec<Admin>` - Admins
+	pub fn get_admins(caller_principal: Principal) -> Result<Vec<Admin>, ApiError> {
+		Self::validate_admin_or_controller(&caller_principal)?;
+
+		Ok(
+			ADMINS.with(|admins| {
+				admins
+					.borrow()
+					.iter()
+					.map(|(_, admin)| admin)
+					.collect()
+			})
+		)
+	}
+
+	/// Add admin.
+	///
+	/// # Arguments
+	/// - `principal` - Principal to make admin
+	/// - `caller_principal` - Principal of the caller, an admin or controller
+	///
+	/// # Returns
+	/// - `Admin` - Added admin
+	pub fn add_admin(principal: Principal, caller_principal: Principal) -> Result<Admin, ApiError> {
+		Self::validate_admin_or_controller(&caller_principal)?;
+		validate_anonymous(&principal)?;
+
+		if ADMINS.with(|admins| admins.borrow().contains_key(&principal)) {
+			return Err(ApiError::AlreadyExists("ADMIN ALREADY EXISTS".to_string()));
+		}
+
+		Ok(Self::insert_admin(principal, caller_principal))
+	}
+
+	/// Remove admin. The last admin cannot be removed.
+	///
+	/// # Arguments
+	/// - `principal` - Principal of the admin
+	/// - `caller_principal` - Principal of the caller, an admin or controller
+	///
+	/// # Returns
+	/// - `Admin` - Removed admin
+	pub fn remove_admin(principal: Principal, caller_principal: Principal) -> Result<Admin, ApiError> {
+		Self::validate_admin_or_controller(&caller_principal)?;
+
+		let admin = ADMINS.with(|admins| {
+			let mut admins = admins.borrow_mut();
+
+			if !admins.contains_key(&principal) {
+				return Err(ApiError::NotFound("ADMIN NOT FOUND".to_string()));
+			}
+
+			if admins.len() == 1 {
+				return Err(ApiError::BadRequest("CANNOT REMOVE THE LAST ADMIN".to_string()));
+			}
+
+			admins.remove(&principal).ok_or(ApiError::NotFound("ADMIN NOT FOUND".to_string()))
+		})?;
+
+		Self::audit(AdminAction::Removed, principal, caller_principal);
+
+		Ok(admin)
+	}
+
+	/// Get the audit log of admin changes, oldest first.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller, an admin or controller
+	///
+	/// # Returns
+	/// - `Vec<AdminAuditEntry>` - Audit log entries
+	pub fn get_audit_log(caller_principal: Principal) -> Result<Vec<AdminAuditEntry>, ApiError> {
+		Self::validate_admin_or_controller(&caller_principal)?;
+
+		Ok(
+			ADMIN_AUDIT_LOG.with(|audit_log| {
+				audit_log
+					.borrow()
+					.iter()
+					.map(|(_, entry)| entry)
+					.collect()
+			})
+		)
+	}
+
+	fn validate_admin_or_controller(principal: &Principal) -> Result<Principal, ApiError> {
+		match is_controller(principal) {
+			true => Ok(*principal),
+			false => Self::validate_admin(principal),
+		}
+	}
+
+	fn insert_admin(principal: Principal, changed_by: Principal) -> Admin {
+		let admin = Admin {
+			principal,
+			added_by: changed_by,
+			added_at: time(),
+		};
+
+		ADMINS.with(|admins| admins.borrow_mut().insert(principal, admin.clone()));
+		Self::audit(AdminAction::Added, principal, changed_by);
+
+		admin
+	}
+
+	fn audit(action: AdminAction, principal: Principal, changed_by: Principal) {
+		let id = SequencesStore::next_id(ADMIN_AUDIT_SEQUENCE);
+
+		ADMIN_AUDIT_LOG.with(|audit_log| {
+			audit_log.borrow_mut().insert(id, AdminAuditEntry {
+				id,
+				action,
+				principal,
+				changed_by,
+				created_at: time(),
+			})
+		});
+	}
+}
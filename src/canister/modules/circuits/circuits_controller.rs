@@ -1,6 +1,15 @@
 use candid::Principal;
 use ic_cdk::{ caller, query, update };
-use lib::{ types::{ api_error::ApiError, circuit::{ Circuit, PostCircuit } }, utils::validate::validate_anonymous };
+use lib::{
+	types::{
+		api_error::ApiError,
+		circuit::{ Circuit, PostCircuit },
+		circuit_validation::CircuitValidation,
+		node::Node,
+		share::UserCircuit,
+	},
+	utils::validate::validate_anonymous,
+};
 use super::circuits_store::CircuitsStore;
 
 #[query]
@@ -12,13 +21,21 @@ fn get_circuit(circuit_id: u32) -> Result<Circuit, ApiError> {
 }
 
 #[query]
-fn get_user_circuits() -> Result<Vec<Circuit>, ApiError> {
+fn get_user_circuits() -> Result<Vec<UserCircuit>, ApiError> {
 	match validate_anonymous(&caller()) {
 		Ok(caller_principal) => Ok(CircuitsStore::get_user_circuits(caller_principal)),
 		Err(err) => Err(err),
 	}
 }
 
+#[query]
+fn get_workspace_circuits(workspace_id: u32) -> Result<Vec<UserCircuit>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => CircuitsStore::get_workspace_circuits(workspace_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
 #[query]
 fn get_node_canister_id(circuit_id: u32) -> Result<Principal, ApiError> {
 	match validate_anonymous(&caller()) {
@@ -27,26 +44,40 @@ fn get_node_canister_id(circuit_id: u32) -> Result<Principal, ApiError> {
 	}
 }
 
+#[query]
+fn get_node_canister_circuit() -> Result<Circuit, ApiError> {
+	// Called by node canisters, the node canister itself is authorized by the circuit it runs
+	CircuitsStore::get_node_canister_circuit(caller()).map(|(_, circuit)| circuit)
+}
+
 #[update]
-fn enable_circuit(circuit_id: u32) -> Result<Circuit, ApiError> {
+async fn enable_circuit(circuit_id: u32) -> Result<Circuit, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => CircuitsStore::toggle_circuit(circuit_id, true, caller_principal),
+		Ok(caller_principal) => CircuitsStore::toggle_circuit(circuit_id, true, caller_principal).await,
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
-fn disable_circuit(circuit_id: u32) -> Result<Circuit, ApiError> {
+async fn disable_circuit(circuit_id: u32) -> Result<Circuit, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => CircuitsStore::toggle_circuit(circuit_id, false, caller_principal),
+		Ok(caller_principal) => CircuitsStore::toggle_circuit(circuit_id, false, caller_principal).await,
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
-fn add_circuit(data: PostCircuit) -> Result<Circuit, ApiError> {
+async fn validate_circuit(circuit_id: u32) -> Result<CircuitValidation, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => Ok(CircuitsStore::add_circuit(data, caller_principal)),
+		Ok(caller_principal) => CircuitsStore::validate_circuit(circuit_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn add_circuit(data: PostCircuit) -> Result<Circuit, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => CircuitsStore::add_circuit(data, caller_principal).await,
 		Err(err) => Err(err),
 	}
 }
@@ -58,3 +89,49 @@ fn edit_circuit(circuit_id: u32, data: PostCircuit) -> Result<Circuit, ApiError>
 		Err(err) => Err(err),
 	}
 }
+
+#[update]
+async fn move_circuit(circuit_id: u32, workspace_id: Option<u32>) -> Result<Circuit, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => CircuitsStore::move_circuit(circuit_id, workspace_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn export_circuit(circuit_id: u32) -> Result<String, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => CircuitsStore::export_circuit(circuit_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn import_circuit(document: String) -> Result<Circuit, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => CircuitsStore::import_circuit(document, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn duplicate_circuit(circuit_id: u32, name: String) -> Result<Circuit, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => CircuitsStore::duplicate_circuit(circuit_id, name, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn copy_nodes(
+	source_circuit_id: u32,
+	node_ids: Vec<u32>,
+	target_circuit_id: u32,
+	position: Option<u32>
+) -> Result<Vec<Node>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) =>
+			CircuitsStore::copy_nodes(source_circuit_id, node_ids, target_circuit_id, position, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
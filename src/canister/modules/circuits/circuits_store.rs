@@ -1,7 +1,29 @@
+use std::collections::HashMap;
 use candid::Principal;
-use ic_cdk::api::time;
-use lib::types::{ api_error::ApiError, circuit::{ Circuit, PostCircuit }, circuit_key::CircuitKey };
-use crate::canister_storage::CIRCUITS;
+use ic_cdk::{ api::time, id };
+use lib::{
+	types::{
+		api_error::ApiError,
+		circuit::{ Circuit, PostCircuit },
+		circuit_export::{ CircuitExport, ConnectorExport },
+		circuit_key::CircuitKey,
+		circuit_validation::CircuitValidation,
+		node::{ Node, PostNode },
+		share::{ Role, ShareResource, UserCircuit, UserConnector },
+	},
+	utils::{ sequence::CIRCUITS_SEQUENCE, validate::validate_role },
+};
+use crate::{
+	canister_storage::CIRCUITS,
+	modules::{
+		connectors::connectors_store::ConnectorsStore,
+		node_canisters::node_canisters_store::NodeCanistersStore,
+		sequences::sequences_store::SequencesStore,
+		shares::shares_store::SharesStore,
+		traces::traces_store::TracesStore,
+		workspaces::workspaces_store::WorkspacesStore,
+	},
+};
 
 pub struct CircuitsStore;
 
@@ -30,37 +52,109 @@ impl CircuitsStore {
 	/// # Returns
 	/// - `Circuit` - Circuit
 	pub fn get_circuit(circuit_id: u32, caller_principal: Principal) -> Result<Circuit, ApiError> {
-		CIRCUITS.with(|circuits| {
-			let circuits = circuits.borrow();
+		let (_, circuit) = Self::get_authorized_circuit(circuit_id, caller_principal, Role::Viewer)?;
 
-			// Find circuit by CircuitKey. If not found throw error
-			let circuit_key = CircuitKey { id: circuit_id, owner: caller_principal.to_string() };
-			let circuit = circuits.get(&circuit_key);
+		Ok(circuit)
+	}
 
-			if circuit.is_none() {
-				return Err(ApiError::NotFound("NOT FOUND".to_string()));
-			}
+	/// Get circuits owned by or shared with the principal.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<UserCircuit>` - Circuits with the caller's role
+	pub fn get_user_circuits(caller_principal: Principal) -> Vec<UserCircuit> {
+		CIRCUITS.with(|circuits| {
+			let circuits = circuits.borrow();
 
-			Ok(circuit.unwrap().clone())
+			circuits
+				.iter()
+				.filter_map(|(key, circuit)| {
+					Self::resolve_role(&key, &circuit, caller_principal).map(|role| UserCircuit { circuit, role })
+				})
+				.collect::<Vec<UserCircuit>>()
 		})
 	}
 
-	/// Get circuits by principal.
+	/// Get circuits owned by a workspace.
 	///
 	/// # Arguments
+	/// - `workspace_id` - Workspace ID
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
-	/// - `Vec<Circuit>` - Circuits
-	pub fn get_user_circuits(caller_principal: Principal) -> Vec<Circuit> {
+	/// - `Vec<UserCircuit>` - Circuits with the caller's role
+	pub fn get_workspace_circuits(
+		workspace_id: u32,
+		caller_principal: Principal
+	) -> Result<Vec<UserCircuit>, ApiError> {
+		WorkspacesStore::get_authorized_workspace(workspace_id, caller_principal, Role::Viewer)?;
+
 		CIRCUITS.with(|circuits| {
 			let circuits = circuits.borrow();
 
+			let workspace_circuits = circuits
+				.iter()
+				.filter(|(_, circuit)| circuit.workspace_id == Some(workspace_id))
+				.filter_map(|(key, circuit)| {
+					Self::resolve_role(&key, &circuit, caller_principal).map(|role| UserCircuit { circuit, role })
+				})
+				.collect::<Vec<UserCircuit>>();
+
+			Ok(workspace_circuits)
+		})
+	}
+
+	/// Get the role of a principal on a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `principal` - Principal
+	///
+	/// # Returns
+	/// - `Role` - Role of the principal
+	pub fn get_circuit_role(circuit_id: u32, principal: Principal) -> Result<Role, ApiError> {
+		let (circuit_key, circuit) = Self::find_circuit(circuit_id)?;
+
+		// Circuits without access are reported as not found, so their existence is not leaked
+		Self::resolve_role(&circuit_key, &circuit, principal).ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+	}
+
+	/// Get circuit by id together with its key, if the caller has at least the required role.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	/// - `required_role` - Minimum role required
+	///
+	/// # Returns
+	/// - `(CircuitKey, Circuit)` - Circuit key and circuit
+	pub fn get_authorized_circuit(
+		circuit_id: u32,
+		caller_principal: Principal,
+		required_role: Role
+	) -> Result<(CircuitKey, Circuit), ApiError> {
+		let role = Self::get_circuit_role(circuit_id, caller_principal)?;
+		validate_role(&role, required_role)?;
+
+		Self::find_circuit(circuit_id)
+	}
+
+	/// Get the circuit running on a node canister.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	///
+	/// # Returns
+	/// - `(CircuitKey, Circuit)` - Circuit key and circuit
+	pub fn get_node_canister_circuit(node_canister_id: Principal) -> Result<(CircuitKey, Circuit), ApiError> {
+		CIRCUITS.with(|circuits| {
 			circuits
+				.borrow()
 				.iter()
-				.filter(|(key, _)| caller_principal.to_string() == key.owner)
-				.map(|(_, circuit)| circuit.clone())
-				.collect::<Vec<Circuit>>()
+				.find(|(_, circuit)| circuit.node_canister_id == node_canister_id)
+				.ok_or(ApiError::Unauthorized("UNAUTHORIZED".to_string()))
 		})
 	}
 
@@ -78,7 +172,12 @@ impl CircuitsStore {
 		Ok(circuit.node_canister_id)
 	}
 
-	/// Add circuit.
+	/// Add circuit and create its node canister.
+	///
+	/// Breaking change: circuits used to be added without a node canister. Every circuit now gets its own
+	/// node canister, so circuits cannot be added (or imported or duplicated) until an admin has uploaded the
+	/// node canister wasm with `set_node_canister_wasm`. Until then a `BadRequest("NODE CANISTER WASM NOT SET")`
+	/// error is returned and nothing is created.
 	///
 	/// # Arguments
 	/// - `data` - Circuit data
@@ -86,20 +185,23 @@ impl CircuitsStore {
 	///
 	/// # Returns
 	/// - `Circuit` - Added circuit
-	pub fn add_circuit(data: PostCircuit, caller_principal: Principal) -> Circuit {
+	pub async fn add_circuit(data: PostCircuit, caller_principal: Principal) -> Result<Circuit, ApiError> {
+		if !NodeCanistersStore::has_wasm() {
+			return Err(ApiError::BadRequest("NODE CANISTER WASM NOT SET".to_string()));
+		}
+
+		let node_canister_id = NodeCanistersStore::create_node_canister(caller_principal).await?;
+
 		CIRCUITS.with(|circuits| {
 			let mut circuits = circuits.borrow_mut();
 
-			let circuit_id =
-				circuits
-					.last_key_value()
-					.map(|(key, _)| key.id)
-					.unwrap_or(0) + 1;
+			let circuit_id = SequencesStore::next_id(CIRCUITS_SEQUENCE);
 
 			let new_circuit = Circuit {
 				id: circuit_id,
 				user_id: caller_principal,
-				node_canister_id: Principal::anonymous(),
+				node_canister_id,
+				workspace_id: None,
 				name: data.name,
 				description: data.description,
 				is_favorite: false,
@@ -113,10 +215,193 @@ impl CircuitsStore {
 			// Add new circuit
 			circuits.insert(CircuitKey { id: circuit_id, owner: caller_principal.to_string() }, new_circuit.clone());
 
-			new_circuit
+			Ok(new_circuit)
 		})
+	}
+
+	/// Export circuit as a versioned JSON document. Secrets are stripped.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `String` - Stringified JSON export document
+	pub async fn export_circuit(circuit_id: u32, caller_principal: Principal) -> Result<String, ApiError> {
+		let circuit = Self::get_circuit(circuit_id, caller_principal)?;
+		let nodes = NodeCanistersStore::get_circuit_nodes(circuit.node_canister_id, circuit_id).await?;
+
+		let mut connector_ids = nodes
+			.iter()
+			.filter_map(|node| node.node_type.connector_id())
+			.collect::<Vec<u32>>();
+		connector_ids.sort();
+		connector_ids.dedup();
+
+		let mut connectors = vec![];
+		for connector_id in connector_ids {
+			let (_, connector) = ConnectorsStore::get_authorized_connector(
+				connector_id,
+				caller_principal,
+				Role::Viewer
+			)?;
+
+			connectors.push(ConnectorExport { id: connector_id, name: connector.name });
+		}
+
+		Ok(CircuitExport::new(&circuit, nodes, connectors).to_json())
+	}
+
+	/// Import circuit from a JSON export document into the caller's account. Nothing is kept if the nodes cannot be
+	/// added.
+	///
+	/// # Arguments
+	/// - `document` - Stringified JSON export document
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Circuit` - Imported circuit
+	pub async fn import_circuit(document: String, caller_principal: Principal) -> Result<Circuit, ApiError> {
+		let export = CircuitExport::from_json(&document).map_err(|errors| ApiError::BadRequest(errors.join(", ")))?;
+
+		// Every referenced connector must exist in the caller's account, matched by name. Connectors only shared
+		// with the caller as a viewer are skipped, as nodes using them would run with the credentials of another user
+		let user_connectors = ConnectorsStore::get_user_connectors(caller_principal)
+			.into_iter()
+			.filter(|user_connector| user_connector.role >= Role::Editor)
+			.collect::<Vec<UserConnector>>();
+
+		let mut connector_ids = HashMap::new();
+		let mut missing_connectors = vec![];
+
+		for export_connector in export.connectors.iter() {
+			let user_connector = user_connectors
+				.iter()
+				.find(|user_connector| user_connector.connector.name == export_connector.name);
+
+			match user_connector {
+				Some(user_connector) => {
+					connector_ids.insert(export_connector.id, user_connector.connector.id);
+				}
+				None => missing_connectors.push(format!("CONNECTOR NOT FOUND: {}", export_connector.name)),
+			}
+		}
+
+		if !missing_connectors.is_empty() {
+			return Err(ApiError::BadRequest(missing_connectors.join(", ")));
+		}
+
+		let circuit = Self::add_circuit(
+			PostCircuit {
+				name: export.circuit.name.clone(),
+				description: export.circuit.description.clone(),
+			},
+			caller_principal
+		).await?;
+
+		let added_nodes = NodeCanistersStore::add_nodes(
+			circuit.node_canister_id,
+			circuit.id,
+			None,
+			export.post_nodes(&connector_ids)
+		).await;
+
+		// Do not leave an empty circuit and its node canister behind
+		if let Err(err) = added_nodes {
+			CIRCUITS.with(|circuits| {
+				circuits.borrow_mut().remove(&(CircuitKey { id: circuit.id, owner: caller_principal.to_string() }));
+			});
 
-		// TODO: add a new node canister, update "node_canister_id" in Circuit struct, make user the owner and add as a controller
+			// The error of the import is returned, a node canister failing to be deleted only costs its cycles
+			let _ = NodeCanistersStore::delete_node_canister(circuit.node_canister_id).await;
+
+			return Err(err);
+		}
+
+		Ok(circuit)
+	}
+
+	/// Duplicate circuit and all its nodes into a new circuit with a fresh node canister.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID of the circuit to duplicate
+	/// - `name` - Name of the new circuit
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Circuit` - Duplicated circuit
+	pub async fn duplicate_circuit(
+		circuit_id: u32,
+		name: String,
+		caller_principal: Principal
+	) -> Result<Circuit, ApiError> {
+		let source_circuit = Self::get_circuit(circuit_id, caller_principal)?;
+
+		let mut source_nodes = NodeCanistersStore::get_circuit_nodes(
+			source_circuit.node_canister_id,
+			circuit_id
+		).await?;
+		source_nodes.sort_by_key(|node| node.order);
+
+		let circuit = Self::add_circuit(
+			PostCircuit {
+				name,
+				description: source_circuit.description,
+			},
+			caller_principal
+		).await?;
+
+		let post_nodes = source_nodes.into_iter().map(PostNode::from).collect::<Vec<PostNode>>();
+		NodeCanistersStore::add_nodes(circuit.node_canister_id, circuit.id, None, post_nodes).await?;
+
+		Ok(circuit)
+	}
+
+	/// Copy nodes from one circuit to another, also across node canisters.
+	///
+	/// # Arguments
+	/// - `source_circuit_id` - Circuit ID to copy the nodes from
+	/// - `node_ids` - IDs of the nodes to copy
+	/// - `target_circuit_id` - Circuit ID to copy the nodes to
+	/// - `position` - Order of the first copied node in the target circuit. The nodes are appended if not provided
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Copied nodes
+	pub async fn copy_nodes(
+		source_circuit_id: u32,
+		node_ids: Vec<u32>,
+		target_circuit_id: u32,
+		position: Option<u32>,
+		caller_principal: Principal
+	) -> Result<Vec<Node>, ApiError> {
+		let source_circuit = Self::get_circuit(source_circuit_id, caller_principal)?;
+		let (_, target_circuit) = Self::get_authorized_circuit(target_circuit_id, caller_principal, Role::Editor)?;
+
+		let mut source_nodes = NodeCanistersStore::get_circuit_nodes(
+			source_circuit.node_canister_id,
+			source_circuit_id
+		).await?;
+		source_nodes.sort_by_key(|node| node.order);
+
+		let missing_node_ids = node_ids
+			.iter()
+			.filter(|node_id| !source_nodes.iter().any(|node| node.id == **node_id))
+			.map(|node_id| node_id.to_string())
+			.collect::<Vec<String>>();
+
+		if !missing_node_ids.is_empty() {
+			return Err(ApiError::NotFound(format!("NODES NOT FOUND: {}", missing_node_ids.join(", "))));
+		}
+
+		// Keep the order of the source circuit
+		let post_nodes = source_nodes
+			.into_iter()
+			.filter(|node| node_ids.contains(&node.id))
+			.map(PostNode::from)
+			.collect::<Vec<PostNode>>();
+
+		NodeCanistersStore::add_nodes(target_circuit.node_canister_id, target_circuit_id, position, post_nodes).await
 	}
 
 	/// Edit circuit.
@@ -129,19 +414,11 @@ impl CircuitsStore {
 	/// # Returns
 	/// - `Circuit` - Edited circuit
 	pub fn edit_circuit(circuit_id: u32, data: PostCircuit, caller_principal: Principal) -> Result<Circuit, ApiError> {
+		let (circuit_key, mut circuit) = Self::get_authorized_circuit(circuit_id, caller_principal, Role::Editor)?;
+
 		CIRCUITS.with(|circuits| {
 			let mut circuits = circuits.borrow_mut();
 
-			// Find mutable circuit by CircuitKey. If not found throw error
-			let circuit_key = CircuitKey { id: circuit_id, owner: caller_principal.to_string() };
-			let circuit = circuits.get(&circuit_key);
-
-			if circuit.is_none() {
-				return Err(ApiError::NotFound("NOT FOUND".to_string()));
-			}
-
-			let mut circuit = circuit.unwrap().clone();
-
 			// Mutate values
 			circuit.name = data.name;
 			circuit.description = data.description;
@@ -154,27 +431,107 @@ impl CircuitsStore {
 		})
 	}
 
-	/// Toggle enable/disable circuit.
+	/// Move circuit between personal and workspace ownership.
 	///
 	/// # Arguments
 	/// - `circuit_id` - Circuit ID
+	/// - `workspace_id` - Workspace ID to move the circuit to. The caller becomes the owner if not provided
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
-	/// - `Circuit` - Enabled circuit
-	pub fn toggle_circuit(circuit_id: u32, enabled: bool, caller_principal: Principal) -> Result<Circuit, ApiError> {
+	/// - `Circuit` - Moved circuit
+	pub async fn move_circuit(
+		circuit_id: u32,
+		workspace_id: Option<u32>,
+		caller_principal: Principal
+	) -> Result<Circuit, ApiError> {
+		let (circuit_key, mut circuit) = Self::find_circuit(circuit_id)?;
+
+		if circuit.workspace_id == workspace_id {
+			return Err(ApiError::BadRequest("ALREADY OWNED BY TARGET".to_string()));
+		}
+
+		// Leaving the current ownership requires ownership of the circuit, or admin rights in its workspace
+		match circuit.workspace_id {
+			Some(current_workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(current_workspace_id, caller_principal, Role::Admin)?;
+			}
+			None => {
+				Self::get_authorized_circuit(circuit_id, caller_principal, Role::Owner)?;
+			}
+		}
+
+		// Workspace circuits are managed through this canister, so it becomes the owner of the node canister
+		let (owner, node_canister_owner) = match workspace_id {
+			Some(workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(workspace_id, caller_principal, Role::Editor)?;
+				(WorkspacesStore::key_owner(workspace_id), id())
+			}
+			None => (caller_principal.to_string(), caller_principal),
+		};
+
+		NodeCanistersStore::transfer_ownership(circuit.node_canister_id, node_canister_owner).await?;
+
+		// Mutate values
+		circuit.workspace_id = workspace_id;
+		circuit.updated_at = time();
+
 		CIRCUITS.with(|circuits| {
 			let mut circuits = circuits.borrow_mut();
 
-			// Find mutable circuit by CircuitKey. If not found throw error
-			let circuit_key = CircuitKey { id: circuit_id, owner: caller_principal.to_string() };
-			let circuit = circuits.get(&circuit_key);
+			// Re-key the circuit to its new owner
+			circuits.remove(&circuit_key);
+			circuits.insert(CircuitKey { id: circuit_id, owner: owner.clone() }, circuit.clone());
+		});
 
-			if circuit.is_none() {
-				return Err(ApiError::NotFound("NOT FOUND".to_string()));
-			}
+		TracesStore::move_circuit_traces(circuit_id, &circuit_key.owner, &owner);
+
+		Ok(circuit)
+	}
+
+	/// Validate the structure of the draft of a circuit, e.g. before publishing it.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitValidation` - Errors and warnings
+	pub async fn validate_circuit(circuit_id: u32, caller_principal: Principal) -> Result<CircuitValidation, ApiError> {
+		let circuit = Self::get_circuit(circuit_id, caller_principal)?;
+		let nodes = NodeCanistersStore::get_circuit_nodes(circuit.node_canister_id, circuit_id).await?;
+
+		Ok(CircuitValidation::validate(circuit_id, &nodes))
+	}
+
+	/// Toggle enable/disable circuit. Executions run the published revision, so a circuit can only be enabled once
+	/// a revision without blocking validation errors is published.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `enabled` - Enabled or disabled
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Circuit` - Enabled circuit
+	pub async fn toggle_circuit(
+		circuit_id: u32,
+		enabled: bool,
+		caller_principal: Principal
+	) -> Result<Circuit, ApiError> {
+		let (_, circuit) = Self::get_authorized_circuit(circuit_id, caller_principal, Role::Editor)?;
+
+		if enabled {
+			let revision = NodeCanistersStore::get_published_revision(circuit.node_canister_id, circuit_id).await?;
+
+			CircuitValidation::validate(circuit_id, &revision.nodes).ensure_valid()?;
+		}
 
-			let mut circuit = circuit.unwrap().clone();
+		// Get the circuit again, it may have changed while validating
+		let (circuit_key, mut circuit) = Self::get_authorized_circuit(circuit_id, caller_principal, Role::Editor)?;
+
+		CIRCUITS.with(|circuits| {
+			let mut circuits = circuits.borrow_mut();
 
 			// Mutate values
 			circuit.is_enabled = enabled;
@@ -186,4 +543,31 @@ impl CircuitsStore {
 			Ok(circuit.clone())
 		})
 	}
+
+	fn find_circuit(circuit_id: u32) -> Result<(CircuitKey, Circuit), ApiError> {
+		CIRCUITS.with(|circuits| {
+			let circuits = circuits.borrow();
+
+			// Keys are ordered by ID first, so the circuit is the first key from the lowest key with this ID
+			circuits
+				.range(CircuitKey { id: circuit_id, owner: String::default() }..)
+				.next()
+				.filter(|(key, _)| key.id == circuit_id)
+				.ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+		})
+	}
+
+	fn resolve_role(circuit_key: &CircuitKey, circuit: &Circuit, principal: Principal) -> Option<Role> {
+		if circuit_key.owner == principal.to_string() {
+			return Some(Role::Owner);
+		}
+
+		// The highest of the workspace role and the shared role applies
+		let workspace_role = circuit.workspace_id.and_then(|workspace_id| {
+			WorkspacesStore::get_member_role(workspace_id, principal)
+		});
+		let shared_role = SharesStore::get_role(ShareResource::Circuit, circuit_key.id, principal);
+
+		workspace_role.max(shared_role)
+	}
 }
@@ -1,30 +1,127 @@
-use ic_cdk::{ caller, query, update };
+use ic_cdk::{ api::management_canister::http_request::{ HttpResponse, TransformArgs }, caller, query, update };
 use lib::{
-	types::{ api_error::ApiError, connector::{ Connector, PostConnector } },
-	utils::validate::validate_anonymous,
+	types::{
+		api_error::ApiError,
+		connector::{ Connector, ConnectorTestResult, ConnectorUsage, JwtPreview, PostConnector },
+		share::UserConnector,
+	},
+	utils::{
+		candid_interface,
+		http_outcall::transform_status_and_body,
+		validate::validate_anonymous,
+	},
 };
 use super::connectors_store::ConnectorsStore;
 
 #[query]
-fn get_user_connectors() -> Result<Vec<Connector>, ApiError> {
+fn get_user_connectors() -> Result<Vec<UserConnector>, ApiError> {
 	match validate_anonymous(&caller()) {
 		Ok(caller_principal) => Ok(ConnectorsStore::get_user_connectors(caller_principal)),
 		Err(err) => Err(err),
 	}
 }
 
+#[query]
+fn get_workspace_connectors(workspace_id: u32) -> Result<Vec<UserConnector>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::get_workspace_connectors(workspace_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn get_connector(connector_id: u32) -> Result<Connector, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::get_connector(connector_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn get_connector_usage(connector_id: u32) -> Result<Vec<ConnectorUsage>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::get_connector_usage(connector_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+// An update, so the secrets go through consensus instead of an uncertified query response of a single replica
+#[update]
+fn get_node_canister_connector(connector_id: u32) -> Result<Connector, ApiError> {
+	// Called by node canisters, the node canister itself is authorized by the circuit it runs
+	ConnectorsStore::get_node_canister_connector(connector_id, caller())
+}
+
+#[update]
+fn preview_jwt(connector_id: u32, sample_data: String) -> Result<JwtPreview, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::preview_jwt(connector_id, sample_data, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn transform(raw: TransformArgs) -> HttpResponse {
+	transform_status_and_body(raw)
+}
+
+#[query]
+fn transform_candid_metadata(raw: TransformArgs) -> HttpResponse {
+	candid_interface::transform_candid_metadata(raw)
+}
+
+#[update]
+async fn test_connector(connector_id: u32) -> Result<ConnectorTestResult, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::test_connector(connector_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn add_connector(data: PostConnector) -> Result<Connector, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::add_connector(data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn edit_connector(connector_id: u32, data: PostConnector) -> Result<Connector, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::edit_connector(connector_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn refresh_canister_interface(connector_id: u32) -> Result<Connector, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::refresh_canister_interface(connector_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn move_connector(connector_id: u32, workspace_id: Option<u32>) -> Result<Connector, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConnectorsStore::move_connector(connector_id, workspace_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
 #[update]
-fn add_connector(data: PostConnector) -> Result<Connector, ApiError> {
+fn duplicate_connector(connector_id: u32, name: String) -> Result<Connector, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => Ok(ConnectorsStore::add_connector(data, caller_principal)),
+		Ok(caller_principal) => ConnectorsStore::duplicate_connector(connector_id, name, caller_principal),
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
-fn edit_connector(connector_id: u32, data: PostConnector) -> Result<Connector, ApiError> {
+async fn delete_connector(connector_id: u32, cascade: bool) -> Result<Connector, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => ConnectorsStore::edit_connector(connector_id, data, caller_principal),
+		Ok(caller_principal) => ConnectorsStore::delete_connector(connector_id, cascade, caller_principal).await,
 		Err(err) => Err(err),
 	}
 }
@@ -1,31 +1,455 @@
 use candid::Principal;
-use ic_cdk::api::time;
-use lib::types::{ api_error::ApiError, connector::{ Connector, PostConnector }, connector_key::ConnectorKey };
-use crate::canister_storage::CONNECTORS;
+use ic_cdk::{ api::time, call };
+use lib::{
+	types::{
+		api_error::ApiError,
+		circuit::Circuit,
+		circuit_key::CircuitKey,
+		connector::{
+			Authentication,
+			CandidInterfaceSource,
+			CanisterConnector,
+			Connector,
+			ConnectorTestResult,
+			ConnectorType,
+			ConnectorUsage,
+			JwtPreview,
+			PostConnector,
+		},
+		connector_key::ConnectorKey,
+		connector_secrets::ConnectorSecrets,
+		headers::Headers,
+		node::{ HttpRequestMethod, LookupCanister, Node, NodeType, Output },
+		share::{ Role, ShareResource, UserConnector },
+	},
+	utils::{
+		candid_interface::{ candid_methods, fetch_candid_metadata, CANDID_INTERFACE_QUERY },
+		http_outcall::{ http_outcall, response_status },
+		jwt::{ decode_jwt, mint_jwt },
+		oauth2::fetch_oauth2_token,
+		request_signing::{ apply_authentication, apply_token },
+		sequence::CONNECTORS_SEQUENCE,
+		validate::validate_role,
+		vault::{ render_secrets, secret_references },
+	},
+};
+use serde_json::Value;
+use crate::{
+	canister_storage::{ CIRCUITS, CONNECTORS, CONNECTOR_SECRETS },
+	modules::{
+		circuits::circuits_store::CircuitsStore,
+		node_canisters::node_canisters_store::NodeCanistersStore,
+		secrets::secrets_store::SecretsStore,
+		sequences::sequences_store::SequencesStore,
+		shares::shares_store::SharesStore,
+		workspaces::workspaces_store::WorkspacesStore,
+	},
+};
+
+// Cycles attached to the test request, and to the token request of OAuth2 connectors
+static TEST_CONNECTION_CYCLES: u128 = 30_000_000_000;
+
+// Cycles attached to the request reading the Candid metadata of a canister
+static CANDID_METADATA_CYCLES: u128 = 30_000_000_000;
+
+// Number of characters of the response body kept in the test result
+static TEST_BODY_EXCERPT_LENGTH: usize = 512;
 
 pub struct ConnectorsStore;
 
 impl ConnectorsStore {
-	/// Get connectors by principal.
+	/// Get connectors owned by or shared with the principal.
 	///
 	/// # Arguments
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
-	/// - `Vec<Connector>` - Connectors
-	pub fn get_user_connectors(caller_principal: Principal) -> Vec<Connector> {
+	/// - `Vec<UserConnector>` - Connectors with the caller's role
+	pub fn get_user_connectors(caller_principal: Principal) -> Vec<UserConnector> {
 		CONNECTORS.with(|connectors| {
 			let connecetors = connectors.borrow();
 
 			connecetors
 				.iter()
-				.filter(|(key, _)| caller_principal.to_string() == key.owner)
-				.map(|(_, connector)| connector.clone())
-				.collect::<Vec<Connector>>()
+				.filter_map(|(key, connector)| {
+					Self::resolve_role(&key, &connector, caller_principal).map(|role| UserConnector {
+						connector: Self::mask_secrets(connector),
+						role,
+					})
+				})
+				.collect::<Vec<UserConnector>>()
+		})
+	}
+
+	/// Get connectors owned by a workspace.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<UserConnector>` - Connectors with the caller's role
+	pub fn get_workspace_connectors(
+		workspace_id: u32,
+		caller_principal: Principal
+	) -> Result<Vec<UserConnector>, ApiError> {
+		WorkspacesStore::get_authorized_workspace(workspace_id, caller_principal, Role::Viewer)?;
+
+		CONNECTORS.with(|connectors| {
+			let connectors = connectors.borrow();
+
+			let workspace_connectors = connectors
+				.iter()
+				.filter(|(_, connector)| connector.workspace_id == Some(workspace_id))
+				.filter_map(|(key, connector)| {
+					Self::resolve_role(&key, &connector, caller_principal).map(|role| UserConnector {
+						connector: Self::mask_secrets(connector),
+						role,
+					})
+				})
+				.collect::<Vec<UserConnector>>();
+
+			Ok(workspace_connectors)
 		})
 	}
 
-	/// Add connector.
+	/// Get the role of a principal on a connector.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `principal` - Principal
+	///
+	/// # Returns
+	/// - `Role` - Role of the principal
+	pub fn get_connector_role(connector_id: u32, principal: Principal) -> Result<Role, ApiError> {
+		let (connector_key, connector) = Self::find_connector(connector_id)?;
+
+		// Connectors without access are reported as not found, so their existence is not leaked
+		Self::resolve_role(&connector_key, &connector, principal).ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+	}
+
+	/// Get connector by id together with its key, if the caller has at least the required role.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `caller_principal` - Principal of the caller
+	/// - `required_role` - Minimum role required
+	///
+	/// # Returns
+	/// - `(ConnectorKey, Connector)` - Connector key and connector
+	pub fn get_authorized_connector(
+		connector_id: u32,
+		caller_principal: Principal,
+		required_role: Role
+	) -> Result<(ConnectorKey, Connector), ApiError> {
+		let role = Self::get_connector_role(connector_id, caller_principal)?;
+		validate_role(&role, required_role)?;
+
+		Self::find_connector(connector_id)
+	}
+
+	/// Get connector for a node canister, with its secrets. The owner of the circuit running on the node canister
+	/// must be allowed to edit the connector, or the connector must belong to the workspace of the circuit.
+	/// Node canisters are only controlled by this canister, so the secrets cannot be read from their memory.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `node_canister_id` - Canister ID of the calling node canister
+	///
+	/// # Returns
+	/// - `Connector` - Connector
+	pub fn get_node_canister_connector(connector_id: u32, node_canister_id: Principal) -> Result<Connector, ApiError> {
+		let (circuit_key, circuit) = CircuitsStore::get_node_canister_circuit(node_canister_id)?;
+		let (connector_key, connector) = Self::find_connector(connector_id)?;
+
+		match Self::can_use_connector(&circuit_key, &circuit, &connector_key, &connector) {
+			true => Self::resolve_connector(&connector_key, connector),
+			false => Err(ApiError::NotFound("NOT FOUND".to_string())),
+		}
+	}
+
+	/// Get connector by id.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Connector` - Connector
+	pub fn get_connector(connector_id: u32, caller_principal: Principal) -> Result<Connector, ApiError> {
+		let (_, connector) = Self::get_authorized_connector(connector_id, caller_principal, Role::Viewer)?;
+
+		Ok(Self::mask_secrets(connector))
+	}
+
+	/// Get the nodes referencing a connector. Only circuits that can use the connector are searched, their nodes
+	/// are fetched from the node canisters.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<ConnectorUsage>` - Circuits and nodes referencing the connector
+	pub async fn get_connector_usage(
+		connector_id: u32,
+		caller_principal: Principal
+	) -> Result<Vec<ConnectorUsage>, ApiError> {
+		Self::get_authorized_connector(connector_id, caller_principal, Role::Viewer)?;
+
+		let usage = Self::find_connector_usage(connector_id)
+			.await?
+			.iter()
+			.map(|(circuit, node)| ConnectorUsage { circuit_id: circuit.id, node_id: node.id })
+			.collect();
+
+		Ok(usage)
+	}
+
+	/// Delete connector. Connectors referenced by nodes are only deleted when cascading, the nodes then call the
+	/// URL of the connector directly, without its headers and authentication, or the canister of the connector
+	/// without its Candid interface.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `cascade` - Whether to detach the connector from the nodes referencing it
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Connector` - Deleted connector
+	pub async fn delete_connector(
+		connector_id: u32,
+		cascade: bool,
+		caller_principal: Principal
+	) -> Result<Connector, ApiError> {
+		let (_, connector) = Self::find_connector(connector_id)?;
+
+		// Deletion requires ownership of the connector, or admin rights in its workspace
+		match connector.workspace_id {
+			Some(workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(workspace_id, caller_principal, Role::Admin)?;
+			}
+			None => {
+				Self::get_authorized_connector(connector_id, caller_principal, Role::Owner)?;
+			}
+		}
+
+		let usage = Self::find_connector_usage(connector_id).await?;
+
+		if !usage.is_empty() && !cascade {
+			return Err(ApiError::BadRequest(format!("CONNECTOR IS USED BY {} NODES", usage.len())));
+		}
+
+		for (circuit, node) in usage {
+			let mut node_type = node.node_type;
+
+			match &mut node_type {
+				NodeType::HttpRequest(http_request) | NodeType::LookupHttpRequest(http_request) => {
+					let path = http_request.connector
+						.take()
+						.map(|connector| connector.path)
+						.unwrap_or_default();
+
+					if let ConnectorType::Http(http_connector) = &connector.connector_type {
+						http_request.url = http_connector.url(&path)?;
+					}
+				}
+				| NodeType::LookupCanister(LookupCanister { canister, connector_id, .. })
+				| NodeType::Output(Output { canister, connector_id, .. }) => {
+					connector_id.take();
+
+					if let ConnectorType::CanisterInterface(canister_connector) = &connector.connector_type {
+						*canister = canister_connector.canister_id;
+					}
+				}
+				NodeType::Canister(_) => {}
+			}
+
+			NodeCanistersStore::edit_node(circuit.node_canister_id, node.id, node_type).await?;
+		}
+
+		// The connector may have been deleted while the nodes were detached
+		let (connector_key, connector) = Self::find_connector(connector_id)?;
+
+		CONNECTORS.with(|connectors| {
+			connectors.borrow_mut().remove(&connector_key);
+		});
+
+		Self::store_secrets(connector_id, ConnectorSecrets::default());
+		SharesStore::remove_shares(ShareResource::Connector, connector_id);
+
+		Ok(Self::mask_secrets(connector))
+	}
+
+	/// Duplicate connector with its secrets, e.g. to create a variant per environment. The duplicate keeps the
+	/// workspace of the connector. Like deletion, duplication requires ownership of the connector, or admin rights
+	/// in its workspace, as the duplicate holds the secrets of the connector.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `name` - Name of the duplicate
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Connector` - Duplicate connector
+	pub fn duplicate_connector(
+		connector_id: u32,
+		name: String,
+		caller_principal: Principal
+	) -> Result<Connector, ApiError> {
+		let (connector_key, connector) = Self::find_connector(connector_id)?;
+
+		Self::validate_ownership(&connector, caller_principal)?;
+
+		let duplicate_id = SequencesStore::next_id(CONNECTORS_SEQUENCE);
+
+		let duplicate = Connector {
+			id: duplicate_id,
+			user_id: caller_principal,
+			workspace_id: connector.workspace_id,
+			name,
+			connector_type: connector.connector_type,
+			last_test_result: None,
+			secrets: None,
+			created_at: time(),
+			updated_at: time(),
+		};
+
+		Self::store_secrets(duplicate_id, Self::get_secrets(connector_id));
+
+		CONNECTORS.with(|connectors| {
+			connectors
+				.borrow_mut()
+				.insert(ConnectorKey { id: duplicate_id, owner: connector_key.owner }, duplicate.clone());
+		});
+
+		Ok(Self::mask_secrets(duplicate))
+	}
+
+	/// Mint a token with the JWT authentication of a connector and decode it, to debug the configuration.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `sample_data` - Stringified JSON used to render the payload. The sample data of the connector if empty
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `JwtPreview` - Token with a masked signature and its decoded header and claims
+	pub fn preview_jwt(
+		connector_id: u32,
+		sample_data: String,
+		caller_principal: Principal
+	) -> Result<JwtPreview, ApiError> {
+		let (connector_key, connector) = Self::get_authorized_connector(connector_id, caller_principal, Role::Editor)?;
+
+		let jwt_config = match Self::resolve_connector(&connector_key, connector)?.connector_type {
+			ConnectorType::Http(http_connector) =>
+				match http_connector.authentication {
+					Authentication::JWT(jwt_config) => jwt_config,
+					_ => {
+						return Err(ApiError::BadRequest("CONNECTOR DOES NOT USE JWT AUTHENTICATION".to_string()));
+					}
+				}
+			_ => {
+				return Err(ApiError::BadRequest("CONNECTOR IS NOT AN HTTP CONNECTOR".to_string()));
+			}
+		};
+
+		let sample_data = match sample_data.trim() {
+			"" => jwt_config.sample_data.clone(),
+			_ => sample_data,
+		};
+
+		let data: Value = serde_json
+			::from_str(&sample_data)
+			.map_err(|err| ApiError::BadRequest(format!("INVALID SAMPLE DATA: {err}")))?;
+
+		decode_jwt(&mint_jwt(&jwt_config, &data, time() / 1_000_000_000)?)
+	}
+
+	/// Run the test connection of a connector with its authentication and store the result on the connector.
+	/// Failing requests are reported in the result, not as an error.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `ConnectorTestResult` - Result of the test
+	pub async fn test_connector(
+		connector_id: u32,
+		caller_principal: Principal
+	) -> Result<ConnectorTestResult, ApiError> {
+		let (connector_key, connector) = Self::get_authorized_connector(connector_id, caller_principal, Role::Editor)?;
+
+		let http_connector = match Self::resolve_connector(&connector_key, connector)?.connector_type {
+			ConnectorType::Http(http_connector) => http_connector,
+			_ => {
+				return Err(ApiError::BadRequest("CONNECTOR IS NOT AN HTTP CONNECTOR".to_string()));
+			}
+		};
+
+		let Some(test_connection) = http_connector.test_connection.clone() else {
+			return Err(ApiError::BadRequest("CONNECTOR HAS NO TEST CONNECTION".to_string()));
+		};
+
+		let started_at = time();
+
+		let url = http_connector.url(&test_connection.relative_url)?;
+
+		let response = match
+			Self::authenticate_test_request(
+				&http_connector.authentication,
+				&test_connection.method,
+				url,
+				http_connector.headers.clone()
+			).await
+		{
+			Ok((url, headers)) =>
+				http_outcall(url, &test_connection.method, headers, None, TEST_CONNECTION_CYCLES).await,
+			Err(err) => Err(err),
+		};
+
+		let latency_ms = (time() - started_at) / 1_000_000;
+
+		let test_result = match response {
+			Ok(response) => {
+				let status = response_status(&response);
+				let body = String::from_utf8_lossy(&response.body).to_string();
+				let (passed, error) = test_connection.evaluate(status, &body);
+
+				ConnectorTestResult {
+					passed,
+					status,
+					latency_ms,
+					body_excerpt: body.chars().take(TEST_BODY_EXCERPT_LENGTH).collect(),
+					error,
+					tested_at: time(),
+				}
+			}
+			Err(err) =>
+				ConnectorTestResult {
+					passed: false,
+					status: 0,
+					latency_ms,
+					body_excerpt: String::default(),
+					error: Some(format!("{err:?}")),
+					tested_at: time(),
+				},
+		};
+
+		// The connector may have been edited or moved while the request was in flight
+		let (connector_key, mut connector) = Self::find_connector(connector_id)?;
+		connector.last_test_result = Some(test_result.clone());
+
+		CONNECTORS.with(|connectors| {
+			connectors.borrow_mut().insert(connector_key, connector);
+		});
+
+		Ok(test_result)
+	}
+
+	/// Add connector. The Candid interface of canister connectors is fetched from the canister.
 	///
 	/// # Arguments
 	/// - `data` - Connector data
@@ -33,25 +457,30 @@ impl ConnectorsStore {
 	///
 	/// # Returns
 	/// - `Connector` - Added connector
-	pub fn add_connector(data: PostConnector, caller_principal: Principal) -> Connector {
-		CONNECTORS.with(|connectors| {
+	pub async fn add_connector(data: PostConnector, caller_principal: Principal) -> Result<Connector, ApiError> {
+		let connector_type = Self::prepare_connector_type(data.connector_type, None).await?;
+
+		let new_connector = CONNECTORS.with(|connectors| {
 			let mut connectors = connectors.borrow_mut();
 
-			let connector_id =
-				connectors
-					.last_key_value()
-					.map(|(key, _)| key.id)
-					.unwrap_or(0) + 1;
+			let connector_id = SequencesStore::next_id(CONNECTORS_SEQUENCE);
 
-			let new_connector = Connector {
+			let mut new_connector = Connector {
 				id: connector_id,
 				user_id: caller_principal,
+				workspace_id: None,
 				name: data.name,
-				connector_type: data.connector_type,
+				connector_type,
+				last_test_result: None,
+				secrets: None,
 				created_at: time(),
 				updated_at: time(),
 			};
 
+			if let Some(authentication) = new_connector.authentication_mut() {
+				Self::store_secrets(connector_id, ConnectorSecrets::take(authentication));
+			}
+
 			// Add new connector
 			connectors.insert(
 				ConnectorKey { id: connector_id, owner: caller_principal.to_string() },
@@ -59,10 +488,14 @@ impl ConnectorsStore {
 			);
 
 			new_connector
-		})
+		});
+
+		Ok(Self::mask_secrets(new_connector))
 	}
 
-	/// Edit connector.
+	/// Edit connector. Changing the hosts the connector sends its secrets to, the base URL or the OAuth2 token URL,
+	/// requires ownership of the connector, or admin rights in its workspace. Canister connectors keep their Candid
+	/// interface unless the canister changes, the interface of a new canister is fetched from it.
 	///
 	/// # Arguments
 	/// - `connector_id` - Connector ID
@@ -71,33 +504,476 @@ impl ConnectorsStore {
 	///
 	/// # Returns
 	/// - `Connector` - Edited connector
-	pub fn edit_connector(
+	pub async fn edit_connector(
 		connector_id: u32,
 		data: PostConnector,
 		caller_principal: Principal
 	) -> Result<Connector, ApiError> {
-		CONNECTORS.with(|connectors| {
-			let mut connectors = connectors.borrow_mut();
+		let (_, connector) = Self::get_authorized_connector(connector_id, caller_principal, Role::Editor)?;
 
-			// Find mutable connector by ConnectorKey. If not found throw error
-			let connector_key = ConnectorKey { id: connector_id, owner: caller_principal.to_string() };
-			let connector = connectors.get(&connector_key);
+		let connector_type = Self::prepare_connector_type(
+			data.connector_type,
+			Some(&connector.connector_type)
+		).await?;
 
-			if connector.is_none() {
-				return Err(ApiError::NotFound("NOT FOUND".to_string()));
-			}
+		// The connector and the caller's role may have changed while the interface was fetched
+		let (connector_key, mut connector) = Self::get_authorized_connector(
+			connector_id,
+			caller_principal,
+			Role::Editor
+		)?;
 
-			let mut connector = connector.unwrap().clone();
+		if Self::secret_targets(&connector_type) != Self::secret_targets(&connector.connector_type) {
+			Self::validate_ownership(&connector, caller_principal)?;
+		}
+
+		CONNECTORS.with(|connectors| {
+			let mut connectors = connectors.borrow_mut();
 
 			// Mutate values
 			connector.name = data.name;
-			connector.connector_type = data.connector_type;
+			connector.connector_type = connector_type;
 			connector.updated_at = time();
 
+			let current_secrets = Self::get_secrets(connector_id);
+
+			match connector.authentication_mut() {
+				Some(authentication) => {
+					Self::store_secrets(connector_id, ConnectorSecrets::merge(authentication, &current_secrets));
+				}
+				None => Self::store_secrets(connector_id, ConnectorSecrets::default()),
+			}
+
 			// Add new connector or overwrite existing one
 			connectors.insert(connector_key, connector.clone());
 
-			Ok(connector.clone())
+			Ok(Self::mask_secrets(connector))
+		})
+	}
+
+	/// Refresh the Candid interface of a canister connector. The interface is fetched from the canister, it is never
+	/// taken from the client.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Connector` - Connector with the refreshed interface
+	pub async fn refresh_canister_interface(
+		connector_id: u32,
+		caller_principal: Principal
+	) -> Result<Connector, ApiError> {
+		let (_, connector) = Self::get_authorized_connector(connector_id, caller_principal, Role::Editor)?;
+
+		let canister_id = match connector.connector_type {
+			ConnectorType::CanisterInterface(canister_connector) => canister_connector.canister_id,
+			_ => {
+				return Err(ApiError::BadRequest("CONNECTOR IS NOT A CANISTER CONNECTOR".to_string()));
+			}
+		};
+
+		let canister_connector = Self::fetch_canister_interface(canister_id).await?;
+
+		// The connector may have been edited while the interface was fetched
+		let (connector_key, mut connector) = Self::find_connector(connector_id)?;
+
+		connector.connector_type = ConnectorType::CanisterInterface(canister_connector);
+		connector.updated_at = time();
+
+		CONNECTORS.with(|connectors| {
+			connectors.borrow_mut().insert(connector_key, connector.clone());
+		});
+
+		Ok(Self::mask_secrets(connector))
+	}
+
+	/// Move connector between personal and workspace ownership.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `workspace_id` - Workspace ID to move the connector to. The caller becomes the owner if not provided
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Connector` - Moved connector
+	pub fn move_connector(
+		connector_id: u32,
+		workspace_id: Option<u32>,
+		caller_principal: Principal
+	) -> Result<Connector, ApiError> {
+		let (connector_key, mut connector) = Self::find_connector(connector_id)?;
+
+		if connector.workspace_id == workspace_id {
+			return Err(ApiError::BadRequest("ALREADY OWNED BY TARGET".to_string()));
+		}
+
+		// Leaving the current ownership requires ownership of the connector, or admin rights in its workspace
+		match connector.workspace_id {
+			Some(current_workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(current_workspace_id, caller_principal, Role::Admin)?;
+			}
+			None => {
+				Self::get_authorized_connector(connector_id, caller_principal, Role::Owner)?;
+			}
+		}
+
+		let owner = match workspace_id {
+			Some(workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(workspace_id, caller_principal, Role::Editor)?;
+				WorkspacesStore::key_owner(workspace_id)
+			}
+			None => caller_principal.to_string(),
+		};
+
+		// Mutate values
+		connector.workspace_id = workspace_id;
+		connector.updated_at = time();
+
+		CONNECTORS.with(|connectors| {
+			let mut connectors = connectors.borrow_mut();
+
+			// Re-key the connector to its new owner
+			connectors.remove(&connector_key);
+			connectors.insert(ConnectorKey { id: connector_id, owner }, connector.clone());
+		});
+
+		Ok(Self::mask_secrets(connector))
+	}
+
+	/// Get the connectors of an owner referencing a secret of the vault.
+	///
+	/// # Arguments
+	/// - `owner` - Owner of the secret
+	/// - `name` - Name of the secret
+	///
+	/// # Returns
+	/// - `Vec<u32>` - Connector IDs
+	pub fn get_secret_usage(owner: &str, name: &str) -> Vec<u32> {
+		let connectors = CONNECTORS.with(|connectors| {
+			connectors
+				.borrow()
+				.iter()
+				.filter(|(key, _)| key.owner == owner)
+				.map(|(_, connector)| connector)
+				.collect::<Vec<Connector>>()
+		});
+
+		connectors
+			.into_iter()
+			.map(Self::restore_secrets)
+			.filter_map(|connector| match connector.connector_type {
+				ConnectorType::Http(mut http_connector) => {
+					let is_used = http_connector
+						.templates_mut()
+						.iter()
+						.any(|template| secret_references(template).iter().any(|reference| reference == name));
+
+					is_used.then_some(connector.id)
+				}
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Migrate connectors stored by previous versions: secrets are moved out of the connectors, and canister
+	/// connectors holding a canister ID as text become structured canister connectors.
+	pub fn migrate() {
+		Self::migrate_secrets();
+		Self::migrate_canister_connectors();
+	}
+
+	fn migrate_secrets() {
+		let connectors = CONNECTORS.with(|connectors| {
+			connectors
+				.borrow()
+				.iter()
+				.collect::<Vec<(ConnectorKey, Connector)>>()
+		});
+
+		for (connector_key, mut connector) in connectors {
+			let Some(authentication) = connector.authentication_mut() else {
+				continue;
+			};
+
+			let secrets = ConnectorSecrets::take(authentication);
+
+			if secrets.values.is_empty() {
+				continue;
+			}
+
+			Self::store_secrets(connector_key.id, secrets);
+
+			CONNECTORS.with(|connectors| {
+				connectors.borrow_mut().insert(connector_key, connector);
+			});
+		}
+	}
+
+	fn migrate_canister_connectors() {
+		let connectors = CONNECTORS.with(|connectors| {
+			connectors
+				.borrow()
+				.iter()
+				.filter(|(_, connector)| matches!(connector.connector_type, ConnectorType::Canister(_)))
+				.collect::<Vec<(ConnectorKey, Connector)>>()
+		});
+
+		for (connector_key, mut connector) in connectors {
+			let ConnectorType::Canister(canister_id) = &connector.connector_type else {
+				continue;
+			};
+
+			// Canister IDs that do not parse are kept as they are. Interfaces cannot be fetched during an upgrade,
+			// the migrated connectors have an empty one until it is refreshed
+			let Ok(canister_id) = Principal::from_text(canister_id.trim()) else {
+				continue;
+			};
+
+			connector.connector_type = ConnectorType::CanisterInterface(CanisterConnector {
+				canister_id,
+				candid: String::default(),
+				candid_source: CandidInterfaceSource::Manual,
+				methods: vec![],
+				fetched_at: None,
+			});
+
+			CONNECTORS.with(|connectors| {
+				connectors.borrow_mut().insert(connector_key, connector);
+			});
+		}
+	}
+
+	/// Validate a posted connector type. Canister IDs given as text become canister connectors. The Candid interface
+	/// of canister connectors is never taken from the client: it is kept if the canister is unchanged, and fetched
+	/// from the canister otherwise.
+	async fn prepare_connector_type(
+		connector_type: ConnectorType,
+		current: Option<&ConnectorType>
+	) -> Result<ConnectorType, ApiError> {
+		let canister_id = match connector_type {
+			ConnectorType::Http(http_connector) => {
+				return Ok(ConnectorType::Http(http_connector));
+			}
+			ConnectorType::Canister(canister_id) => {
+				Principal::from_text(canister_id.trim()).map_err(|_|
+					ApiError::BadRequest("INVALID CANISTER ID".to_string())
+				)?
+			}
+			ConnectorType::CanisterInterface(canister_connector) => canister_connector.canister_id,
+		};
+
+		// Interfaces entered by users before they were fetched are replaced
+		if let Some(ConnectorType::CanisterInterface(current)) = current {
+			if current.canister_id == canister_id && current.candid_source != CandidInterfaceSource::Manual {
+				return Ok(ConnectorType::CanisterInterface(current.clone()));
+			}
+		}
+
+		Ok(ConnectorType::CanisterInterface(Self::fetch_canister_interface(canister_id).await?))
+	}
+
+	/// Fetch the Candid interface of a canister from its `candid:service` metadata, or from the
+	/// `__get_candid_interface_tmp_hack` query if the canister has no such metadata.
+	async fn fetch_canister_interface(canister_id: Principal) -> Result<CanisterConnector, ApiError> {
+		let (candid, candid_source) = match fetch_candid_metadata(canister_id, CANDID_METADATA_CYCLES).await {
+			Ok(candid) => (candid, CandidInterfaceSource::Metadata),
+			Err(_) => {
+				let (candid,): (String,) = call(canister_id, CANDID_INTERFACE_QUERY, ()).await.map_err(|(_, message)| {
+					ApiError::InterCanister(format!("FAILED TO FETCH CANDID INTERFACE: {message}"))
+				})?;
+
+				(candid, CandidInterfaceSource::InterfaceQuery)
+			}
+		};
+
+		let methods = candid_methods(&candid)?;
+
+		Ok(CanisterConnector {
+			canister_id,
+			candid,
+			candid_source,
+			methods,
+			fetched_at: Some(time()),
 		})
 	}
+
+	async fn authenticate_test_request(
+		authentication: &Authentication,
+		method: &HttpRequestMethod,
+		url: String,
+		headers: Headers
+	) -> Result<(String, Headers), ApiError> {
+		match authentication {
+			Authentication::OAuth2(oauth2_config) => {
+				let refresh_token = oauth2_config.refresh_token.clone();
+				let token = fetch_oauth2_token(oauth2_config, refresh_token, TEST_CONNECTION_CYCLES).await?;
+
+				Ok(apply_token(&token.access_token, &oauth2_config.location, url, headers))
+			}
+			_ => apply_authentication(authentication, method, url, headers, None, None, time() / 1_000_000_000),
+		}
+	}
+
+	/// Validate that the principal owns a connector, or is an admin of the workspace owning it.
+	fn validate_ownership(connector: &Connector, principal: Principal) -> Result<(), ApiError> {
+		match connector.workspace_id {
+			Some(workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(workspace_id, principal, Role::Admin)?;
+			}
+			None => {
+				Self::get_authorized_connector(connector.id, principal, Role::Owner)?;
+			}
+		}
+
+		Ok(())
+	}
+
+	/// URLs the secrets of a connector are sent to: the base URL, and the token URL of OAuth2 connectors.
+	fn secret_targets(connector_type: &ConnectorType) -> Vec<&str> {
+		match connector_type {
+			ConnectorType::Http(http_connector) => {
+				let mut targets = vec![http_connector.base_url.trim()];
+
+				if let Authentication::OAuth2(oauth2_config) = &http_connector.authentication {
+					targets.push(oauth2_config.token_url.trim());
+				}
+
+				targets
+			}
+			_ => vec![],
+		}
+	}
+
+	fn get_secrets(connector_id: u32) -> ConnectorSecrets {
+		CONNECTOR_SECRETS.with(|secrets| secrets.borrow().get(&connector_id).unwrap_or_default())
+	}
+
+	fn store_secrets(connector_id: u32, connector_secrets: ConnectorSecrets) {
+		CONNECTOR_SECRETS.with(|secrets| {
+			let mut secrets = secrets.borrow_mut();
+
+			match connector_secrets.values.is_empty() {
+				true => secrets.remove(&connector_id),
+				false => secrets.insert(connector_id, connector_secrets),
+			};
+		})
+	}
+
+	fn mask_secrets(mut connector: Connector) -> Connector {
+		let secrets = Self::get_secrets(connector.id);
+
+		connector.secrets = connector.authentication_mut().map(|authentication| secrets.mask(authentication));
+		connector
+	}
+
+	/// Restore the secrets of a connector and render the secrets of the vault it references.
+	fn resolve_connector(connector_key: &ConnectorKey, connector: Connector) -> Result<Connector, ApiError> {
+		let mut connector = Self::restore_secrets(connector);
+
+		if let ConnectorType::Http(http_connector) = &mut connector.connector_type {
+			let mut templates = http_connector.templates_mut();
+
+			let mut names = templates
+				.iter()
+				.flat_map(|template| secret_references(template))
+				.collect::<Vec<String>>();
+			names.sort();
+			names.dedup();
+
+			let secrets = SecretsStore::reveal_secrets(&connector_key.owner, &names)?;
+
+			for template in templates.iter_mut() {
+				**template = render_secrets(template, &secrets);
+			}
+		}
+
+		Ok(connector)
+	}
+
+	fn restore_secrets(mut connector: Connector) -> Connector {
+		let secrets = Self::get_secrets(connector.id);
+
+		if let Some(authentication) = connector.authentication_mut() {
+			secrets.restore(authentication);
+		}
+
+		connector
+	}
+
+	async fn find_connector_usage(connector_id: u32) -> Result<Vec<(Circuit, Node)>, ApiError> {
+		let (connector_key, connector) = Self::find_connector(connector_id)?;
+
+		let circuits = CIRCUITS.with(|circuits| {
+			circuits
+				.borrow()
+				.iter()
+				.filter(|(circuit_key, circuit)| {
+					Self::can_use_connector(circuit_key, circuit, &connector_key, &connector)
+				})
+				.map(|(_, circuit)| circuit)
+				.collect::<Vec<Circuit>>()
+		});
+
+		let mut usage = vec![];
+
+		for circuit in circuits {
+			let nodes = NodeCanistersStore::get_circuit_nodes(circuit.node_canister_id, circuit.id).await?;
+
+			usage.extend(
+				nodes
+					.into_iter()
+					.filter(|node| node.node_type.connector_id() == Some(connector_id))
+					.map(|node| (circuit.clone(), node))
+			);
+		}
+
+		Ok(usage)
+	}
+
+	/// Whether a circuit can use a connector: the owner of the circuit is at least an editor of the connector, or
+	/// the connector belongs to the workspace of the circuit. Viewers cannot use a connector, its secrets would be
+	/// sent with the requests of their circuits.
+	fn can_use_connector(
+		circuit_key: &CircuitKey,
+		circuit: &Circuit,
+		connector_key: &ConnectorKey,
+		connector: &Connector
+	) -> bool {
+		if circuit.workspace_id.is_some() && connector.workspace_id == circuit.workspace_id {
+			return true;
+		}
+
+		Principal::from_text(&circuit_key.owner)
+			.ok()
+			.and_then(|owner| Self::resolve_role(connector_key, connector, owner))
+			.is_some_and(|role| role >= Role::Editor)
+	}
+
+	fn find_connector(connector_id: u32) -> Result<(ConnectorKey, Connector), ApiError> {
+		CONNECTORS.with(|connectors| {
+			let connectors = connectors.borrow();
+
+			// Keys are ordered by ID first, so the connector is the first key from the lowest key with this ID
+			connectors
+				.range(ConnectorKey { id: connector_id, owner: String::default() }..)
+				.next()
+				.filter(|(key, _)| key.id == connector_id)
+				.ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+		})
+	}
+
+	fn resolve_role(connector_key: &ConnectorKey, connector: &Connector, principal: Principal) -> Option<Role> {
+		if connector_key.owner == principal.to_string() {
+			return Some(Role::Owner);
+		}
+
+		// The highest of the workspace role and the shared role applies
+		let workspace_role = connector.workspace_id.and_then(|workspace_id| {
+			WorkspacesStore::get_member_role(workspace_id, principal)
+		});
+		let shared_role = SharesStore::get_role(ShareResource::Connector, connector_key.id, principal);
+
+		workspace_role.max(shared_role)
+	}
 }
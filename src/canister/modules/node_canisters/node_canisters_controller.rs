@@ -0,0 +1,29 @@
+use candid::Principal;
+use ic_cdk::{ caller, update };
+use lib::types::api_error::ApiError;
+use crate::modules::admins::admins_store::AdminsStore;
+use super::node_canisters_store::NodeCanistersStore;
+
+#[update]
+fn set_node_canister_wasm(wasm: Vec<u8>) -> Result<(), ApiError> {
+	match AdminsStore::validate_admin(&caller()) {
+		Ok(_) => NodeCanistersStore::set_wasm(wasm),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn revoke_node_canister_owner_controllers() -> Result<Vec<Principal>, ApiError> {
+	match AdminsStore::validate_admin(&caller()) {
+		Ok(_) => NodeCanistersStore::revoke_owner_controllers().await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn upgrade_node_canisters() -> Result<Vec<Principal>, ApiError> {
+	match AdminsStore::validate_admin(&caller()) {
+		Ok(_) => NodeCanistersStore::upgrade_node_canisters().await,
+		Err(err) => Err(err),
+	}
+}
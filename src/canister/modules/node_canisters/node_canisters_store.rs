@@ -0,0 +1,282 @@
+use candid::{ Encode, Principal };
+use ic_cdk::{
+	api::management_canister::main::{
+		create_canister,
+		delete_canister,
+		install_code,
+		stop_canister,
+		update_settings,
+		CanisterIdRecord,
+		CanisterInstallMode,
+		CanisterSettings,
+		CreateCanisterArgument,
+		InstallCodeArgument,
+		UpdateSettingsArgument,
+	},
+	call,
+	id,
+};
+use lib::types::{
+	api_error::ApiError,
+	circuit_revision::CircuitRevision,
+	node::{ Node, NodeType, PostNode },
+	node_canister_config::NodeCanisterConfig,
+};
+use crate::{
+	canister_storage::{ CIRCUITS, NODE_CANISTER_WASM },
+	modules::circuits::circuits_store::CircuitsStore,
+};
+
+/// Cycles attached to the creation of a node canister.
+static NODE_CANISTER_CYCLES: u128 = 1_000_000_000_000;
+
+pub struct NodeCanistersStore;
+
+impl NodeCanistersStore {
+	/// Set the wasm module installed on newly created node canisters.
+	///
+	/// # Arguments
+	/// - `wasm` - Wasm module of the nodes canister
+	pub fn set_wasm(wasm: Vec<u8>) -> Result<(), ApiError> {
+		NODE_CANISTER_WASM.with(|node_canister_wasm| {
+			node_canister_wasm
+				.borrow_mut()
+				.set(wasm)
+				.map(|_| ())
+				.map_err(|_| ApiError::BadRequest("WASM TOO LARGE".to_string()))
+		})
+	}
+
+	/// Whether the wasm module installed on newly created node canisters has been set.
+	pub fn has_wasm() -> bool {
+		NODE_CANISTER_WASM.with(|node_canister_wasm| !node_canister_wasm.borrow().get().is_empty())
+	}
+
+	/// Create a new node canister. Only this canister controls it: node canisters hold the secrets of the
+	/// connectors their circuits use, so their owner must not be able to read or replace their code and memory.
+	///
+	/// # Arguments
+	/// - `owner` - Principal of the owner of the node canister
+	///
+	/// # Returns
+	/// - `Principal` - Canister ID of the new node canister
+	pub async fn create_node_canister(owner: Principal) -> Result<Principal, ApiError> {
+		let wasm_module = NODE_CANISTER_WASM.with(|node_canister_wasm| node_canister_wasm.borrow().get().clone());
+
+		if wasm_module.is_empty() {
+			return Err(ApiError::BadRequest("NODE CANISTER WASM NOT SET".to_string()));
+		}
+
+		let settings = CanisterSettings {
+			controllers: Some(vec![id()]),
+			..Default::default()
+		};
+
+		let (canister_record,) = create_canister(
+			CreateCanisterArgument { settings: Some(settings) },
+			NODE_CANISTER_CYCLES
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		let config = NodeCanisterConfig {
+			owner,
+			main_canister: id(),
+		};
+		let arg = Encode!(&config).map_err(|err| ApiError::InterCanister(err.to_string()))?;
+
+		install_code(InstallCodeArgument {
+			mode: CanisterInstallMode::Install,
+			canister_id: canister_record.canister_id,
+			wasm_module,
+			arg,
+		}).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		Ok(canister_record.canister_id)
+	}
+
+	/// Stop and delete a node canister. Its remaining cycles are lost.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	pub async fn delete_node_canister(node_canister_id: Principal) -> Result<(), ApiError> {
+		stop_canister(CanisterIdRecord { canister_id: node_canister_id }).await.map_err(|(_, message)|
+			ApiError::InterCanister(message)
+		)?;
+
+		delete_canister(CanisterIdRecord { canister_id: node_canister_id }).await.map_err(|(_, message)|
+			ApiError::InterCanister(message)
+		)
+	}
+
+	/// Upgrade the node canisters of all circuits to the stored wasm module. The configuration of each node canister
+	/// is passed as upgrade argument, so node canisters installed without one get it.
+	///
+	/// # Returns
+	/// - `Vec<Principal>` - Canister IDs of the upgraded node canisters
+	pub async fn upgrade_node_canisters() -> Result<Vec<Principal>, ApiError> {
+		let wasm_module = NODE_CANISTER_WASM.with(|node_canister_wasm| node_canister_wasm.borrow().get().clone());
+
+		if wasm_module.is_empty() {
+			return Err(ApiError::BadRequest("NODE CANISTER WASM NOT SET".to_string()));
+		}
+
+		let node_canisters = CIRCUITS.with(|circuits| {
+			circuits
+				.borrow()
+				.iter()
+				.filter(|(_, circuit)| circuit.node_canister_id != Principal::anonymous())
+				.map(|(key, circuit)| {
+					// Workspace circuits are owned by this canister
+					let owner = match circuit.workspace_id {
+						Some(_) => id(),
+						None => Principal::from_text(&key.owner).unwrap_or(circuit.user_id),
+					};
+
+					(circuit.node_canister_id, owner)
+				})
+				.collect::<Vec<(Principal, Principal)>>()
+		});
+
+		let mut upgraded_node_canister_ids = vec![];
+
+		for (node_canister_id, owner) in node_canisters {
+			let config = Some(NodeCanisterConfig { owner, main_canister: id() });
+			let arg = Encode!(&config).map_err(|err| ApiError::InterCanister(err.to_string()))?;
+
+			install_code(InstallCodeArgument {
+				mode: CanisterInstallMode::Upgrade,
+				canister_id: node_canister_id,
+				wasm_module: wasm_module.clone(),
+				arg,
+			}).await.map_err(|(_, message)| ApiError::InterCanister(format!("{node_canister_id}: {message}")))?;
+
+			upgraded_node_canister_ids.push(node_canister_id);
+		}
+
+		Ok(upgraded_node_canister_ids)
+	}
+
+	/// Make this canister the only controller of the node canisters of all circuits. Node canisters used to be
+	/// created with their owner as a controller.
+	///
+	/// # Returns
+	/// - `Vec<Principal>` - Canister IDs of the updated node canisters
+	pub async fn revoke_owner_controllers() -> Result<Vec<Principal>, ApiError> {
+		let mut node_canister_ids = CircuitsStore::get_circuits()
+			.into_iter()
+			.map(|circuit| circuit.node_canister_id)
+			.filter(|node_canister_id| *node_canister_id != Principal::anonymous())
+			.collect::<Vec<Principal>>();
+		node_canister_ids.sort();
+		node_canister_ids.dedup();
+
+		for node_canister_id in node_canister_ids.iter() {
+			update_settings(UpdateSettingsArgument {
+				canister_id: *node_canister_id,
+				settings: CanisterSettings {
+					controllers: Some(vec![id()]),
+					..Default::default()
+				},
+			}).await.map_err(|(_, message)| ApiError::InterCanister(format!("{node_canister_id}: {message}")))?;
+		}
+
+		Ok(node_canister_ids)
+	}
+
+	/// Get the nodes of a circuit from its node canister.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	/// - `circuit_id` - Circuit ID
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Nodes
+	pub async fn get_circuit_nodes(node_canister_id: Principal, circuit_id: u32) -> Result<Vec<Node>, ApiError> {
+		let (result,): (Result<(Principal, Vec<Node>), ApiError>,) = call(
+			node_canister_id,
+			"get_circuit_nodes",
+			(circuit_id,)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result.map(|(_, nodes)| nodes)
+	}
+
+	/// Get the published revision of a circuit from its node canister, the revision executions run.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	/// - `circuit_id` - Circuit ID
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Published revision
+	pub async fn get_published_revision(
+		node_canister_id: Principal,
+		circuit_id: u32
+	) -> Result<CircuitRevision, ApiError> {
+		let (result,): (Result<CircuitRevision, ApiError>,) = call(
+			node_canister_id,
+			"get_published_revision",
+			(circuit_id,)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Add nodes to a circuit in its node canister.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	/// - `circuit_id` - Circuit ID
+	/// - `position` - Order of the first added node. The nodes are appended if not provided
+	/// - `data` - Nodes data, in execution order
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Added nodes
+	pub async fn add_nodes(
+		node_canister_id: Principal,
+		circuit_id: u32,
+		position: Option<u32>,
+		data: Vec<PostNode>
+	) -> Result<Vec<Node>, ApiError> {
+		let (result,): (Result<Vec<Node>, ApiError>,) = call(
+			node_canister_id,
+			"add_nodes",
+			(circuit_id, position, data)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Edit a node in its node canister.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	/// - `node_id` - Node ID
+	/// - `data` - Node type
+	///
+	/// # Returns
+	/// - `Node` - Edited node
+	pub async fn edit_node(node_canister_id: Principal, node_id: u32, data: NodeType) -> Result<Node, ApiError> {
+		let (result,): (Result<Node, ApiError>,) = call(
+			node_canister_id,
+			"edit_node",
+			(node_id, data)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Transfer the ownership of a node canister.
+	///
+	/// # Arguments
+	/// - `node_canister_id` - Canister ID of the node canister
+	/// - `new_owner` - Principal of the new owner
+	pub async fn transfer_ownership(node_canister_id: Principal, new_owner: Principal) -> Result<(), ApiError> {
+		let (result,): (Result<NodeCanisterConfig, ApiError>,) = call(
+			node_canister_id,
+			"transfer_ownership",
+			(new_owner,)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result.map(|_| ())
+	}
+}
@@ -0,0 +1,159 @@
+use ic_cdk::{ caller, update };
+use lib::{
+	types::{
+		api_error::ApiError,
+		circuit_revision::{ CircuitRevision, NodeChange },
+		node::{ LookupCanisterPreview, LookupHttpRequestPreview, Node, NodeType, PostPin },
+	},
+	utils::validate::validate_anonymous,
+};
+use super::nodes_store::NodesStore;
+
+#[update]
+async fn get_circuit_nodes(circuit_id: u32) -> Result<Vec<Node>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::get_circuit_nodes(circuit_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn add_node(circuit_id: u32, data: NodeType) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::add_node(circuit_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn edit_node(circuit_id: u32, node_id: u32, data: NodeType) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::edit_node(circuit_id, node_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn delete_node(circuit_id: u32, node_id: u32) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::delete_node(circuit_id, node_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn edit_order(circuit_id: u32, node_id: u32, order: u32) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::edit_order(circuit_id, node_id, order, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn reorder_nodes(circuit_id: u32, node_ids: Vec<u32>) -> Result<Vec<Node>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::reorder_nodes(circuit_id, node_ids, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn enable_node(circuit_id: u32, node_id: u32) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::toggle_node(circuit_id, node_id, true, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn disable_node(circuit_id: u32, node_id: u32) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::toggle_node(circuit_id, node_id, false, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn add_pin(circuit_id: u32, node_id: u32, data: PostPin) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::add_pin(circuit_id, node_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn edit_pin(circuit_id: u32, node_id: u32, pin_id: u32, data: PostPin) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::edit_pin(circuit_id, node_id, pin_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn delete_pin(circuit_id: u32, node_id: u32, pin_id: u32) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::delete_pin(circuit_id, node_id, pin_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn reorder_pins(circuit_id: u32, node_id: u32, pin_ids: Vec<u32>) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::reorder_pins(circuit_id, node_id, pin_ids, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn preview_lookup_canister(circuit_id: u32, data: LookupCanisterPreview) -> Result<String, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::preview_lookup_canister(circuit_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn preview_lookup_http_request(circuit_id: u32, data: LookupHttpRequestPreview) -> Result<String, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::preview_lookup_http_request(circuit_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn get_circuit_revisions(circuit_id: u32) -> Result<Vec<CircuitRevision>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::get_circuit_revisions(circuit_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn diff_revisions(
+	circuit_id: u32,
+	from_revision: u32,
+	to_revision: Option<u32>
+) -> Result<Vec<NodeChange>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) =>
+			NodesStore::diff_revisions(circuit_id, from_revision, to_revision, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn publish_circuit(circuit_id: u32) -> Result<CircuitRevision, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::publish_circuit(circuit_id, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn rollback_to(circuit_id: u32, revision: u32) -> Result<CircuitRevision, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::rollback_to(circuit_id, revision, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
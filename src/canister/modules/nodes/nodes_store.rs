@@ -0,0 +1,363 @@
+use candid::{ utils::ArgumentEncoder, CandidType, Principal };
+use ic_cdk::call;
+use lib::types::{
+	api_error::ApiError,
+	circuit_revision::{ CircuitRevision, NodeChange },
+	node::{ LookupCanisterPreview, LookupHttpRequestPreview, Node, NodeType, PostPin },
+	share::Role,
+};
+use serde::de::DeserializeOwned;
+use crate::modules::circuits::circuits_store::CircuitsStore;
+
+/// Node canisters only accept calls from their owner and this canister. Users a circuit is shared with, and members
+/// of the workspace owning it, manage its nodes and revisions through these methods, which check their role first.
+pub struct NodesStore;
+
+impl NodesStore {
+	/// Get the nodes of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Nodes
+	pub async fn get_circuit_nodes(circuit_id: u32, caller_principal: Principal) -> Result<Vec<Node>, ApiError> {
+		let (_, nodes): (Principal, Vec<Node>) = Self::call_node_canister(
+			circuit_id,
+			caller_principal,
+			Role::Viewer,
+			"get_circuit_nodes",
+			(circuit_id,)
+		).await?;
+
+		Ok(nodes)
+	}
+
+	/// Add a node to a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `data` - Node data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Added node
+	pub async fn add_node(circuit_id: u32, data: NodeType, caller_principal: Principal) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "add_node", (circuit_id, data)).await
+	}
+
+	/// Edit a node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `data` - Node data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Edited node
+	pub async fn edit_node(
+		circuit_id: u32,
+		node_id: u32,
+		data: NodeType,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "edit_node", (node_id, data)).await
+	}
+
+	/// Delete a node from a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Deleted node
+	pub async fn delete_node(circuit_id: u32, node_id: u32, caller_principal: Principal) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "delete_node", (node_id,)).await
+	}
+
+	/// Edit the order of a node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `order` - Node order
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub async fn edit_order(
+		circuit_id: u32,
+		node_id: u32,
+		order: u32,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "edit_order", (node_id, order)).await
+	}
+
+	/// Reorder all nodes of a circuit at once.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_ids` - IDs of all the circuit's nodes, in the new execution order
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Reordered nodes
+	pub async fn reorder_nodes(
+		circuit_id: u32,
+		node_ids: Vec<u32>,
+		caller_principal: Principal
+	) -> Result<Vec<Node>, ApiError> {
+		Self::call_node_canister(
+			circuit_id,
+			caller_principal,
+			Role::Editor,
+			"reorder_nodes",
+			(circuit_id, node_ids)
+		).await
+	}
+
+	/// Toggle enable/disable a node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `enabled` - Enabled or disabled
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub async fn toggle_node(
+		circuit_id: u32,
+		node_id: u32,
+		enabled: bool,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		let method = match enabled {
+			true => "enable_node",
+			false => "disable_node",
+		};
+
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, method, (node_id,)).await
+	}
+
+	/// Add pin to a node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `data` - Pin data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub async fn add_pin(
+		circuit_id: u32,
+		node_id: u32,
+		data: PostPin,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "add_pin", (node_id, data)).await
+	}
+
+	/// Edit pin of a node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `pin_id` - Pin ID
+	/// - `data` - Pin data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub async fn edit_pin(
+		circuit_id: u32,
+		node_id: u32,
+		pin_id: u32,
+		data: PostPin,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "edit_pin", (node_id, pin_id, data)).await
+	}
+
+	/// Delete pin from a node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `pin_id` - Pin ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub async fn delete_pin(
+		circuit_id: u32,
+		node_id: u32,
+		pin_id: u32,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "delete_pin", (node_id, pin_id)).await
+	}
+
+	/// Reorder all pins of a node of a circuit at once.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `pin_ids` - IDs of all the node's pins, in the new execution order
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub async fn reorder_pins(
+		circuit_id: u32,
+		node_id: u32,
+		pin_ids: Vec<u32>,
+		caller_principal: Principal
+	) -> Result<Node, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "reorder_pins", (node_id, pin_ids)).await
+	}
+
+	/// Preview a lookup canister node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `data` - Lookup data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `String` - Stringified JSON reply
+	pub async fn preview_lookup_canister(
+		circuit_id: u32,
+		data: LookupCanisterPreview,
+		caller_principal: Principal
+	) -> Result<String, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "preview_lookup_canister", (data,)).await
+	}
+
+	/// Preview a lookup HTTP request node of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `data` - Lookup data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `String` - Response body
+	pub async fn preview_lookup_http_request(
+		circuit_id: u32,
+		data: LookupHttpRequestPreview,
+		caller_principal: Principal
+	) -> Result<String, ApiError> {
+		Self::call_node_canister(
+			circuit_id,
+			caller_principal,
+			Role::Editor,
+			"preview_lookup_http_request",
+			(data,)
+		).await
+	}
+
+	/// Get all published revisions of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<CircuitRevision>` - Revisions, oldest first
+	pub async fn get_circuit_revisions(
+		circuit_id: u32,
+		caller_principal: Principal
+	) -> Result<Vec<CircuitRevision>, ApiError> {
+		Self::call_node_canister(
+			circuit_id,
+			caller_principal,
+			Role::Viewer,
+			"get_circuit_revisions",
+			(circuit_id,)
+		).await
+	}
+
+	/// Diff two revisions of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `from_revision` - Revision to compare from
+	/// - `to_revision` - Revision to compare to. The draft is used if not provided
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<NodeChange>` - Changes between the revisions
+	pub async fn diff_revisions(
+		circuit_id: u32,
+		from_revision: u32,
+		to_revision: Option<u32>,
+		caller_principal: Principal
+	) -> Result<Vec<NodeChange>, ApiError> {
+		Self::call_node_canister(
+			circuit_id,
+			caller_principal,
+			Role::Viewer,
+			"diff_revisions",
+			(circuit_id, from_revision, to_revision)
+		).await
+	}
+
+	/// Publish the draft of a circuit as a new revision.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Published revision
+	pub async fn publish_circuit(circuit_id: u32, caller_principal: Principal) -> Result<CircuitRevision, ApiError> {
+		Self::call_node_canister(circuit_id, caller_principal, Role::Editor, "publish_circuit", (circuit_id,)).await
+	}
+
+	/// Roll a circuit back to a published revision.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `revision` - Revision to roll back to
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Newly published revision
+	pub async fn rollback_to(
+		circuit_id: u32,
+		revision: u32,
+		caller_principal: Principal
+	) -> Result<CircuitRevision, ApiError> {
+		Self::call_node_canister(
+			circuit_id,
+			caller_principal,
+			Role::Editor,
+			"rollback_to",
+			(circuit_id, revision)
+		).await
+	}
+
+	/// Call a method of the node canister of a circuit, if the caller has at least the required role on the circuit.
+	async fn call_node_canister<T: ArgumentEncoder, R: CandidType + DeserializeOwned>(
+		circuit_id: u32,
+		caller_principal: Principal,
+		required_role: Role,
+		method: &str,
+		args: T
+	) -> Result<R, ApiError> {
+		let (_, circuit) = CircuitsStore::get_authorized_circuit(circuit_id, caller_principal, required_role)?;
+
+		let (result,): (Result<R, ApiError>,) = call(circuit.node_canister_id, method, args).await.map_err(
+			|(_, message)| ApiError::InterCanister(message)
+		)?;
+
+		result
+	}
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use ic_cdk::{ caller, query, update };
+use lib::{
+	types::{ api_error::ApiError, secret::{ PostSecret, SecretInfo, SecretUsage } },
+	utils::validate::validate_anonymous,
+};
+use super::secrets_store::SecretsStore;
+
+#[query]
+fn get_secrets(workspace_id: Option<u32>) -> Result<Vec<SecretInfo>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SecretsStore::get_secrets(workspace_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+// An update, so the secrets go through consensus instead of an uncertified query response of a single replica
+#[update]
+fn get_node_canister_secrets(names: Vec<String>) -> Result<HashMap<String, String>, ApiError> {
+	// Called by node canisters, the node canister itself is authorized by the circuit it runs
+	SecretsStore::get_node_canister_secrets(names, caller())
+}
+
+// Values are obfuscated, not encrypted with a key kept outside the canister: do not store secrets that must stay
+// confidential from the node providers of the subnet
+#[update]
+async fn add_secret(workspace_id: Option<u32>, data: PostSecret) -> Result<SecretInfo, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SecretsStore::add_secret(workspace_id, data, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn rotate_secret(workspace_id: Option<u32>, name: String, value: String) -> Result<SecretInfo, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SecretsStore::rotate_secret(workspace_id, name, value, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn delete_secret(workspace_id: Option<u32>, name: String) -> Result<SecretInfo, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SecretsStore::delete_secret(workspace_id, name, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn get_secret_usage(workspace_id: Option<u32>, name: String) -> Result<Vec<SecretUsage>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SecretsStore::get_secret_usage(workspace_id, name, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use candid::Principal;
+use ic_cdk::api::{ management_canister::main::raw_rand, time };
+use lib::{
+	types::{
+		api_error::ApiError,
+		secret::{ PostSecret, Secret, SecretInfo, SecretUsage, SecretVersion },
+		secret_key::SecretKey,
+		share::Role,
+	},
+	utils::vault::{
+		decrypt_secret,
+		derive_secret_key,
+		encrypt_secret,
+		is_valid_secret_name,
+		secret_references,
+		SECRET_NONCE_LENGTH,
+	},
+};
+use crate::{
+	canister_storage::{ CIRCUITS, SECRETS, VAULT_MASTER_KEY },
+	modules::{
+		circuits::circuits_store::CircuitsStore,
+		connectors::connectors_store::ConnectorsStore,
+		node_canisters::node_canisters_store::NodeCanistersStore,
+		workspaces::workspaces_store::WorkspacesStore,
+	},
+};
+
+// Number of versions kept per secret, older versions are dropped on rotation
+static SECRET_HISTORY_LENGTH: usize = 10;
+
+/// Vault of secrets referenced as `{{secret.NAME}}` by connectors and nodes.
+///
+/// The secrets are encrypted with keys derived from `VAULT_MASTER_KEY`, which is stored in this canister next to
+/// the ciphertexts. This is obfuscation only: the values are never returned to users and do not appear in plain
+/// text in stable memory, but anyone who can read the memory of this canister (e.g. the node providers of its
+/// subnet) can decrypt them. Moving the master key to vetKD is required before the vault can offer more.
+pub struct SecretsStore;
+
+impl SecretsStore {
+	/// Get the secrets of the caller or of a workspace, without their values.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID. The secrets of the caller if not provided
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<SecretInfo>` - Secrets
+	pub fn get_secrets(workspace_id: Option<u32>, caller_principal: Principal) -> Result<Vec<SecretInfo>, ApiError> {
+		let owner = Self::resolve_owner(workspace_id, caller_principal, Role::Viewer)?;
+
+		SECRETS.with(|secrets| {
+			let secrets = secrets.borrow();
+
+			let owner_secrets = secrets
+				.range(SecretKey { owner: owner.clone(), name: String::default() }..)
+				.take_while(|(key, _)| key.owner == owner)
+				.map(|(_, secret)| SecretInfo::from(&secret))
+				.collect::<Vec<SecretInfo>>();
+
+			Ok(owner_secrets)
+		})
+	}
+
+	/// Add a secret.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID. The secret belongs to the caller if not provided
+	/// - `data` - Secret data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `SecretInfo` - Added secret
+	pub async fn add_secret(
+		workspace_id: Option<u32>,
+		data: PostSecret,
+		caller_principal: Principal
+	) -> Result<SecretInfo, ApiError> {
+		let owner = Self::resolve_owner(workspace_id, caller_principal, Role::Editor)?;
+
+		if !is_valid_secret_name(&data.name) {
+			return Err(ApiError::BadRequest("INVALID SECRET NAME".to_string()));
+		}
+
+		let key = SecretKey { owner: owner.clone(), name: data.name.clone() };
+
+		if SECRETS.with(|secrets| secrets.borrow().contains_key(&key)) {
+			return Err(ApiError::AlreadyExists("SECRET ALREADY EXISTS".to_string()));
+		}
+
+		let version = Self::encrypt_version(&owner, &data.name, &data.value, 1, caller_principal).await?;
+
+		let secret = Secret {
+			name: data.name,
+			workspace_id,
+			versions: vec![version],
+			created_at: time(),
+			updated_at: time(),
+		};
+
+		// The name may have been taken while the secret was encrypted
+		SECRETS.with(|secrets| {
+			let mut secrets = secrets.borrow_mut();
+
+			if secrets.contains_key(&key) {
+				return Err(ApiError::AlreadyExists("SECRET ALREADY EXISTS".to_string()));
+			}
+
+			secrets.insert(key, secret.clone());
+
+			Ok(SecretInfo::from(&secret))
+		})
+	}
+
+	/// Rotate a secret. The new value becomes the current version, previous versions are kept in its history.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID. A secret of the caller if not provided
+	/// - `name` - Name of the secret
+	/// - `value` - New value
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `SecretInfo` - Rotated secret
+	pub async fn rotate_secret(
+		workspace_id: Option<u32>,
+		name: String,
+		value: String,
+		caller_principal: Principal
+	) -> Result<SecretInfo, ApiError> {
+		let owner = Self::resolve_owner(workspace_id, caller_principal, Role::Editor)?;
+		let key = SecretKey { owner: owner.clone(), name: name.clone() };
+
+		// Version numbers keep increasing when old versions are dropped from the history
+		let next_version = Self::find_secret(&key)?
+			.current()
+			.map(|version| version.version + 1)
+			.unwrap_or(1);
+
+		let version = Self::encrypt_version(&owner, &name, &value, next_version, caller_principal).await?;
+
+		// The secret may have been rotated or deleted while the new version was encrypted
+		let mut secret = Self::find_secret(&key)?;
+
+		if secret.current().map(|version| version.version + 1).unwrap_or(1) != next_version {
+			return Err(ApiError::BadRequest("SECRET WAS ROTATED CONCURRENTLY".to_string()));
+		}
+
+		secret.versions.push(version);
+
+		if secret.versions.len() > SECRET_HISTORY_LENGTH {
+			secret.versions.drain(..secret.versions.len() - SECRET_HISTORY_LENGTH);
+		}
+
+		secret.updated_at = time();
+
+		SECRETS.with(|secrets| {
+			secrets.borrow_mut().insert(key, secret.clone());
+		});
+
+		Ok(SecretInfo::from(&secret))
+	}
+
+	/// Delete a secret with its history.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID. A secret of the caller if not provided
+	/// - `name` - Name of the secret
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `SecretInfo` - Deleted secret
+	pub fn delete_secret(
+		workspace_id: Option<u32>,
+		name: String,
+		caller_principal: Principal
+	) -> Result<SecretInfo, ApiError> {
+		let owner = Self::resolve_owner(workspace_id, caller_principal, Role::Editor)?;
+
+		SECRETS.with(|secrets| {
+			secrets
+				.borrow_mut()
+				.remove(&SecretKey { owner, name })
+				.map(|secret| SecretInfo::from(&secret))
+				.ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+		})
+	}
+
+	/// Get the connectors and nodes referencing a secret. The nodes are fetched from the node canisters.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID. A secret of the caller if not provided
+	/// - `name` - Name of the secret
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<SecretUsage>` - Connectors and nodes referencing the secret
+	pub async fn get_secret_usage(
+		workspace_id: Option<u32>,
+		name: String,
+		caller_principal: Principal
+	) -> Result<Vec<SecretUsage>, ApiError> {
+		let owner = Self::resolve_owner(workspace_id, caller_principal, Role::Viewer)?;
+		Self::find_secret(&SecretKey { owner: owner.clone(), name: name.clone() })?;
+
+		let mut usage = ConnectorsStore::get_secret_usage(&owner, &name)
+			.into_iter()
+			.map(SecretUsage::Connector)
+			.collect::<Vec<SecretUsage>>();
+
+		// Secrets only resolve for circuits of the same owner
+		let circuits = CIRCUITS.with(|circuits| {
+			circuits
+				.borrow()
+				.iter()
+				.filter(|(key, _)| key.owner == owner)
+				.map(|(_, circuit)| (circuit.id, circuit.node_canister_id))
+				.collect::<Vec<(u32, Principal)>>()
+		});
+
+		for (circuit_id, node_canister_id) in circuits {
+			let nodes = NodeCanistersStore::get_circuit_nodes(node_canister_id, circuit_id).await?;
+
+			for node in nodes {
+				let node_type = serde_json::to_string(&node.node_type).unwrap_or_default();
+
+				if secret_references(&node_type).contains(&name) {
+					usage.push(SecretUsage::Node((circuit_id, node.id)));
+				}
+			}
+		}
+
+		Ok(usage)
+	}
+
+	/// Get the secrets referenced by the nodes of a node canister, from the owner of its circuit.
+	///
+	/// # Arguments
+	/// - `names` - Names of the secrets
+	/// - `node_canister_id` - Canister ID of the calling node canister
+	///
+	/// # Returns
+	/// - `HashMap<String, String>` - Values of the secrets by name
+	pub fn get_node_canister_secrets(
+		names: Vec<String>,
+		node_canister_id: Principal
+	) -> Result<HashMap<String, String>, ApiError> {
+		let (circuit_key, _) = CircuitsStore::get_node_canister_circuit(node_canister_id)?;
+
+		Self::reveal_secrets(&circuit_key.owner, &names)
+	}
+
+	/// Decrypt the current value of secrets.
+	///
+	/// # Arguments
+	/// - `owner` - Owner of the secrets
+	/// - `names` - Names of the secrets
+	///
+	/// # Returns
+	/// - `HashMap<String, String>` - Values of the secrets by name
+	pub fn reveal_secrets(owner: &str, names: &[String]) -> Result<HashMap<String, String>, ApiError> {
+		if names.is_empty() {
+			return Ok(HashMap::new());
+		}
+
+		let master_key = VAULT_MASTER_KEY.with(|master_key| master_key.borrow().get().clone());
+		let key = derive_secret_key(&master_key, owner);
+
+		names
+			.iter()
+			.map(|name| {
+				let secret = Self::find_secret(&SecretKey { owner: owner.to_string(), name: name.clone() }).map_err(
+					|_| ApiError::NotFound(format!("SECRET {name} NOT FOUND"))
+				)?;
+
+				let version = secret.current().ok_or(ApiError::NotFound(format!("SECRET {name} NOT FOUND")))?;
+				let value = decrypt_secret(&key, &version.nonce, name, &version.ciphertext)?;
+
+				Ok((name.clone(), value))
+			})
+			.collect()
+	}
+
+	async fn encrypt_version(
+		owner: &str,
+		name: &str,
+		value: &str,
+		version: u32,
+		caller_principal: Principal
+	) -> Result<SecretVersion, ApiError> {
+		let master_key = Self::master_key().await?;
+		let nonce = Self::random_bytes().await?[..SECRET_NONCE_LENGTH].to_vec();
+
+		let ciphertext = encrypt_secret(&derive_secret_key(&master_key, owner), &nonce, name, value)?;
+
+		Ok(SecretVersion { version, nonce, ciphertext, created_by: caller_principal, created_at: time() })
+	}
+
+	async fn master_key() -> Result<Vec<u8>, ApiError> {
+		let master_key = VAULT_MASTER_KEY.with(|master_key| master_key.borrow().get().clone());
+
+		if !master_key.is_empty() {
+			return Ok(master_key);
+		}
+
+		let random_bytes = Self::random_bytes().await?;
+
+		// Another call may have generated the master key in the meantime, it must never be replaced
+		VAULT_MASTER_KEY.with(|master_key| {
+			let mut master_key = master_key.borrow_mut();
+
+			if master_key.get().is_empty() {
+				master_key
+					.set(random_bytes)
+					.map_err(|_| ApiError::BadRequest("FAILED TO STORE VAULT MASTER KEY".to_string()))?;
+			}
+
+			Ok(master_key.get().clone())
+		})
+	}
+
+	async fn random_bytes() -> Result<Vec<u8>, ApiError> {
+		raw_rand()
+			.await
+			.map(|(bytes,)| bytes)
+			.map_err(|(_, message)| ApiError::InterCanister(message))
+	}
+
+	fn resolve_owner(
+		workspace_id: Option<u32>,
+		caller_principal: Principal,
+		required_role: Role
+	) -> Result<String, ApiError> {
+		match workspace_id {
+			Some(workspace_id) => {
+				WorkspacesStore::get_authorized_workspace(workspace_id, caller_principal, required_role)?;
+				Ok(WorkspacesStore::key_owner(workspace_id))
+			}
+			None => Ok(caller_principal.to_string()),
+		}
+	}
+
+	fn find_secret(key: &SecretKey) -> Result<Secret, ApiError> {
+		SECRETS.with(|secrets| secrets.borrow().get(key).ok_or(ApiError::NotFound("NOT FOUND".to_string())))
+	}
+}
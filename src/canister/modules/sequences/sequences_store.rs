@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+use lib::utils::sequence::{
+	next_id,
+	seed,
+	CIRCUITS_SEQUENCE,
+	CONNECTORS_SEQUENCE,
+	TRACES_SEQUENCE,
+	WORKSPACES_SEQUENCE,
+};
+use crate::canister_storage::{ CIRCUITS, CONNECTORS, SEQUENCES, TRACES, WORKSPACES };
+
+pub struct SequencesStore;
+
+impl SequencesStore {
+	/// Allocate the next ID of a sequence.
+	///
+	/// # Arguments
+	/// - `name` - Name of the sequence
+	///
+	/// # Returns
+	/// - `u32` - Allocated ID
+	pub fn next_id(name: &str) -> u32 {
+		SEQUENCES.with(|sequences| next_id(&mut sequences.borrow_mut(), name))
+	}
+
+	/// Seed the sequences from the existing data and repair colliding IDs.
+	/// Safe to run on every upgrade, sequences are only moved forward.
+	pub fn migrate() {
+		let max_circuit_id = CIRCUITS.with(|circuits| circuits.borrow().last_key_value().map(|(key, _)| key.id));
+		let max_connector_id = CONNECTORS.with(|connectors|
+			connectors
+				.borrow()
+				.last_key_value()
+				.map(|(key, _)| key.id)
+		);
+		let max_workspace_id = WORKSPACES.with(|workspaces| workspaces.borrow().last_key_value().map(|(key, _)| key));
+		let max_trace_id = TRACES.with(|traces| traces.borrow().last_key_value().map(|(key, _)| key.id));
+
+		SEQUENCES.with(|sequences| {
+			let mut sequences = sequences.borrow_mut();
+
+			seed(&mut sequences, CIRCUITS_SEQUENCE, max_circuit_id.unwrap_or(0));
+			seed(&mut sequences, CONNECTORS_SEQUENCE, max_connector_id.unwrap_or(0));
+			seed(&mut sequences, WORKSPACES_SEQUENCE, max_workspace_id.unwrap_or(0));
+			seed(&mut sequences, TRACES_SEQUENCE, max_trace_id.unwrap_or(0));
+		});
+
+		// Circuits, connectors and workspaces were allocated from the highest key, so they never collide.
+		// Traces had no allocator, give every trace sharing an ID with a previous one a new ID.
+		TRACES.with(|traces| {
+			let mut traces = traces.borrow_mut();
+
+			let mut seen_ids = HashSet::new();
+			let colliding_traces = traces
+				.iter()
+				.filter(|(key, _)| !seen_ids.insert(key.id))
+				.collect::<Vec<_>>();
+
+			for (mut key, mut trace) in colliding_traces {
+				traces.remove(&key);
+
+				let trace_id = Self::next_id(TRACES_SEQUENCE);
+				key.id = trace_id;
+				trace.id = trace_id;
+
+				traces.insert(key, trace);
+			}
+		})
+	}
+}
@@ -0,0 +1,58 @@
+use candid::Principal;
+use ic_cdk::{ caller, query, update };
+use lib::{
+	types::{ api_error::ApiError, share::{ Role, Share, ShareResource } },
+	utils::validate::validate_anonymous,
+};
+use super::shares_store::SharesStore;
+
+#[query]
+fn get_circuit_shares(circuit_id: u32) -> Result<Vec<Share>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SharesStore::get_shares(ShareResource::Circuit, circuit_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn get_connector_shares(connector_id: u32) -> Result<Vec<Share>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SharesStore::get_shares(ShareResource::Connector, connector_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn invite_to_circuit(circuit_id: u32, principal: Principal, role: Role) -> Result<Share, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) =>
+			SharesStore::invite(ShareResource::Circuit, circuit_id, principal, role, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn revoke_from_circuit(circuit_id: u32, principal: Principal) -> Result<Share, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => SharesStore::revoke(ShareResource::Circuit, circuit_id, principal, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn invite_to_connector(connector_id: u32, principal: Principal, role: Role) -> Result<Share, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) =>
+			SharesStore::invite(ShareResource::Connector, connector_id, principal, role, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn revoke_from_connector(connector_id: u32, principal: Principal) -> Result<Share, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) =>
+			SharesStore::revoke(ShareResource::Connector, connector_id, principal, caller_principal),
+		Err(err) => Err(err),
+	}
+}
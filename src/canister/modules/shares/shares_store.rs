@@ -0,0 +1,182 @@
+use candid::Principal;
+use ic_cdk::api::time;
+use lib::{
+	types::{ api_error::ApiError, share::{ Role, Share, ShareResource }, share_key::ShareKey },
+	utils::validate::{ validate_anonymous, validate_role },
+};
+use crate::{
+	canister_storage::SHARES,
+	modules::{ circuits::circuits_store::CircuitsStore, connectors::connectors_store::ConnectorsStore },
+};
+
+pub struct SharesStore;
+
+impl SharesStore {
+	/// Get the shared role of a principal on a circuit or connector. Ownership is not taken into account.
+	///
+	/// # Arguments
+	/// - `resource` - Resource type
+	/// - `resource_id` - Circuit or connector ID
+	/// - `principal` - Principal
+	///
+	/// # Returns
+	/// - `Option<Role>` - Role of the principal, if shared
+	pub fn get_role(resource: ShareResource, resource_id: u32, principal: Principal) -> Option<Role> {
+		SHARES.with(|shares| {
+			let shares = shares.borrow();
+
+			shares
+				.get(&(ShareKey { resource, resource_id, principal: principal.to_string() }))
+				.map(|share| share.role)
+		})
+	}
+
+	/// Get the shares of a circuit or connector.
+	///
+	/// # Arguments
+	/// - `resource` - Resource type
+	/// - `resource_id` - Circuit or connector ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Share>` - Shares
+	pub fn get_shares(
+		resource: ShareResource,
+		resource_id: u32,
+		caller_principal: Principal
+	) -> Result<Vec<Share>, ApiError> {
+		let role = Self::get_resource_role(&resource, resource_id, caller_principal)?;
+		validate_role(&role, Role::Viewer)?;
+
+		SHARES.with(|shares| {
+			let shares = shares.borrow();
+
+			let resource_shares = shares
+				.iter()
+				.filter(|(key, _)| key.resource == resource && key.resource_id == resource_id)
+				.map(|(_, share)| share.clone())
+				.collect::<Vec<Share>>();
+
+			Ok(resource_shares)
+		})
+	}
+
+	/// Share a circuit or connector with a principal, or change the role of an existing share.
+	///
+	/// # Arguments
+	/// - `resource` - Resource type
+	/// - `resource_id` - Circuit or connector ID
+	/// - `principal` - Principal to share with
+	/// - `role` - Role to grant
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Share` - Share
+	pub fn invite(
+		resource: ShareResource,
+		resource_id: u32,
+		principal: Principal,
+		role: Role,
+		caller_principal: Principal
+	) -> Result<Share, ApiError> {
+		let caller_role = Self::get_resource_role(&resource, resource_id, caller_principal)?;
+		validate_role(&caller_role, Role::Admin)?;
+		validate_anonymous(&principal)?;
+
+		if role == Role::Owner {
+			return Err(ApiError::BadRequest("OWNER ROLE CANNOT BE GRANTED".to_string()));
+		}
+
+		if let Ok(Role::Owner) = Self::get_resource_role(&resource, resource_id, principal) {
+			return Err(ApiError::BadRequest("PRINCIPAL IS THE OWNER".to_string()));
+		}
+
+		SHARES.with(|shares| {
+			let mut shares = shares.borrow_mut();
+
+			let share_key = ShareKey { resource: resource.clone(), resource_id, principal: principal.to_string() };
+
+			let share = match shares.get(&share_key) {
+				Some(mut share) => {
+					share.role = role;
+					share.updated_at = time();
+					share
+				}
+				None =>
+					Share {
+						resource,
+						resource_id,
+						principal,
+						role,
+						invited_by: caller_principal,
+						created_at: time(),
+						updated_at: time(),
+					},
+			};
+
+			// Add new share or overwrite existing one
+			shares.insert(share_key, share.clone());
+
+			Ok(share)
+		})
+	}
+
+	/// Revoke a share. Principals can always revoke their own share.
+	///
+	/// # Arguments
+	/// - `resource` - Resource type
+	/// - `resource_id` - Circuit or connector ID
+	/// - `principal` - Principal to revoke the share of
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Share` - Revoked share
+	pub fn revoke(
+		resource: ShareResource,
+		resource_id: u32,
+		principal: Principal,
+		caller_principal: Principal
+	) -> Result<Share, ApiError> {
+		let caller_role = Self::get_resource_role(&resource, resource_id, caller_principal)?;
+
+		if principal != caller_principal {
+			validate_role(&caller_role, Role::Admin)?;
+		}
+
+		SHARES.with(|shares| {
+			let mut shares = shares.borrow_mut();
+
+			shares
+				.remove(&(ShareKey { resource, resource_id, principal: principal.to_string() }))
+				.ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+		})
+	}
+
+	/// Remove all shares of a deleted circuit or connector.
+	///
+	/// # Arguments
+	/// - `resource` - Resource type
+	/// - `resource_id` - Circuit or connector ID
+	pub fn remove_shares(resource: ShareResource, resource_id: u32) {
+		SHARES.with(|shares| {
+			let mut shares = shares.borrow_mut();
+
+			let keys = shares
+				.iter()
+				.filter(|(key, _)| key.resource == resource && key.resource_id == resource_id)
+				.map(|(key, _)| key)
+				.collect::<Vec<ShareKey>>();
+
+			for key in keys {
+				shares.remove(&key);
+			}
+		})
+	}
+
+	fn get_resource_role(resource: &ShareResource, resource_id: u32, principal: Principal) -> Result<Role, ApiError> {
+		match resource {
+			ShareResource::Circuit => CircuitsStore::get_circuit_role(resource_id, principal),
+			ShareResource::Connector => ConnectorsStore::get_connector_role(resource_id, principal),
+		}
+	}
+}
@@ -1,11 +1,17 @@
-use ic_cdk::{ caller, query };
+use ic_cdk::{ caller, query, update };
 use lib::{ types::{ api_error::ApiError, trace::Trace }, utils::validate::validate_anonymous };
 use super::traces_store::TracesStore;
 
 #[query]
 fn get_circuit_traces(circuit_id: u32) -> Result<Vec<Trace>, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => Ok(TracesStore::get_circuit_traces(circuit_id, caller_principal)),
+		Ok(caller_principal) => TracesStore::get_circuit_traces(circuit_id, caller_principal),
 		Err(err) => Err(err),
 	}
 }
+
+#[update]
+fn add_node_canister_traces(traces: Vec<Trace>) -> Result<Vec<Trace>, ApiError> {
+	// Called by node canisters, the node canister itself is authorized by the circuit it runs
+	TracesStore::add_node_canister_traces(traces, caller())
+}
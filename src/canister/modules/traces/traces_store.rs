@@ -1,6 +1,13 @@
 use candid::Principal;
-use lib::types::trace::Trace;
-use crate::canister_storage::TRACES;
+use ic_cdk::api::time;
+use lib::{
+	types::{ api_error::ApiError, share::Role, trace::Trace, trace_key::TraceKey },
+	utils::sequence::TRACES_SEQUENCE,
+};
+use crate::{
+	canister_storage::TRACES,
+	modules::{ circuits::circuits_store::CircuitsStore, sequences::sequences_store::SequencesStore },
+};
 
 pub struct TracesStore;
 
@@ -12,19 +19,87 @@ impl TracesStore {
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
-	/// - `Vec<Node>` - Nodes
-	pub fn get_circuit_traces(circuit_id: u32, caller_principal: Principal) -> Vec<Trace> {
+	/// - `Vec<Trace>` - Traces
+	pub fn get_circuit_traces(circuit_id: u32, caller_principal: Principal) -> Result<Vec<Trace>, ApiError> {
+		let (circuit_key, _) = CircuitsStore::get_authorized_circuit(circuit_id, caller_principal, Role::Viewer)?;
+
 		TRACES.with(|traces| {
 			let traces = traces.borrow();
 
 			// Get circuit's traces
 			let circuit_traces = traces
 				.iter()
-				.filter(|(key, _)| key.circuit_id == circuit_id && key.owner == caller_principal.to_string())
+				.filter(|(key, _)| key.circuit_id == circuit_id && key.owner == circuit_key.owner)
 				.map(|(_, node)| node.clone())
 				.collect::<Vec<Trace>>();
 
-			circuit_traces
+			Ok(circuit_traces)
+		})
+	}
+
+	/// Add the traces of an execution of a circuit. Called by the node canister running the circuit, the traces get
+	/// their IDs here and belong to the circuit.
+	///
+	/// # Arguments
+	/// - `traces` - Traces
+	/// - `node_canister_id` - Canister ID of the node canister
+	///
+	/// # Returns
+	/// - `Vec<Trace>` - Added traces
+	pub fn add_node_canister_traces(traces: Vec<Trace>, node_canister_id: Principal) -> Result<Vec<Trace>, ApiError> {
+		let (circuit_key, circuit) = CircuitsStore::get_node_canister_circuit(node_canister_id)?;
+
+		if traces.iter().any(|trace| trace.circuit_id != circuit.id) {
+			return Err(ApiError::Unauthorized("UNAUTHORIZED".to_string()));
+		}
+
+		TRACES.with(|stored_traces| {
+			let mut stored_traces = stored_traces.borrow_mut();
+
+			let added_traces = traces
+				.into_iter()
+				.map(|mut trace| {
+					trace.id = SequencesStore::next_id(TRACES_SEQUENCE);
+					trace.user_id = circuit.user_id;
+					trace.created_at = time();
+					trace.updated_at = time();
+
+					let key = TraceKey {
+						id: trace.id,
+						circuit_id: trace.circuit_id,
+						node_id: trace.node_id,
+						owner: circuit_key.owner.clone(),
+					};
+					stored_traces.insert(key, trace.clone());
+
+					trace
+				})
+				.collect::<Vec<Trace>>();
+
+			Ok(added_traces)
+		})
+	}
+
+	/// Re-key the traces of a circuit to the new owner of the circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `old_owner` - Previous owner of the circuit
+	/// - `new_owner` - New owner of the circuit
+	pub fn move_circuit_traces(circuit_id: u32, old_owner: &str, new_owner: &str) {
+		TRACES.with(|traces| {
+			let mut traces = traces.borrow_mut();
+
+			let circuit_traces = traces
+				.iter()
+				.filter(|(key, _)| key.circuit_id == circuit_id && key.owner == old_owner)
+				.collect::<Vec<_>>();
+
+			for (mut key, trace) in circuit_traces {
+				traces.remove(&key);
+				key.owner = new_owner.to_string();
+				traces.insert(key, trace);
+			}
 		})
 	}
 }
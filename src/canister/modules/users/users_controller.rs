@@ -1,11 +1,12 @@
 use ic_cdk::{ caller, query, update };
-use lib::{ types::{ api_error::ApiError, user::User }, utils::validate::{ validate_admin, validate_anonymous } };
+use lib::{ types::{ api_error::ApiError, user::User }, utils::validate::validate_anonymous };
+use crate::modules::admins::admins_store::AdminsStore;
 
 use super::users_store::UsersStore;
 
 #[query]
 fn get_users() -> Result<Vec<User>, ApiError> {
-	match validate_admin(&caller()) {
+	match AdminsStore::validate_admin(&caller()) {
 		Ok(_) => Ok(UsersStore::get_users()),
 		Err(err) => Err(err),
 	}
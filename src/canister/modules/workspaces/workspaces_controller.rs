@@ -0,0 +1,71 @@
+use candid::Principal;
+use ic_cdk::{ caller, query, update };
+use lib::{
+	types::{ api_error::ApiError, share::Role, workspace::{ PostWorkspace, Workspace } },
+	utils::validate::validate_anonymous,
+};
+use super::workspaces_store::WorkspacesStore;
+
+#[query]
+fn get_user_workspaces() -> Result<Vec<Workspace>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(WorkspacesStore::get_user_workspaces(caller_principal)),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn get_workspace(workspace_id: u32) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => WorkspacesStore::get_workspace(workspace_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn add_workspace(data: PostWorkspace) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => Ok(WorkspacesStore::add_workspace(data, caller_principal)),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn edit_workspace(workspace_id: u32, data: PostWorkspace) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => WorkspacesStore::edit_workspace(workspace_id, data, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn add_workspace_member(workspace_id: u32, principal: Principal, role: Role) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => WorkspacesStore::add_member(workspace_id, principal, role, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn remove_workspace_member(workspace_id: u32, principal: Principal) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => WorkspacesStore::remove_member(workspace_id, principal, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn deposit_workspace_cycles(workspace_id: u32) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => WorkspacesStore::deposit_cycles(workspace_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+async fn fund_workspace_circuit(workspace_id: u32, circuit_id: u32, cycles: u128) -> Result<Workspace, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => WorkspacesStore::fund_circuit(workspace_id, circuit_id, cycles, caller_principal).await,
+		Err(err) => Err(err),
+	}
+}
@@ -0,0 +1,312 @@
+use candid::Principal;
+use ic_cdk::api::{
+	call::{ msg_cycles_accept128, msg_cycles_available128 },
+	management_canister::main::{ deposit_cycles, CanisterIdRecord },
+	time,
+};
+use lib::{
+	types::{ api_error::ApiError, share::Role, workspace::{ PostWorkspace, Workspace, WorkspaceMember } },
+	utils::{ sequence::WORKSPACES_SEQUENCE, validate::{ validate_anonymous, validate_role } },
+};
+use crate::{
+	canister_storage::WORKSPACES,
+	modules::{ circuits::circuits_store::CircuitsStore, sequences::sequences_store::SequencesStore },
+};
+
+pub struct WorkspacesStore;
+
+impl WorkspacesStore {
+	/// Get workspaces the principal is a member of.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Workspace>` - Workspaces
+	pub fn get_user_workspaces(caller_principal: Principal) -> Vec<Workspace> {
+		WORKSPACES.with(|workspaces| {
+			let workspaces = workspaces.borrow();
+
+			workspaces
+				.iter()
+				.filter(|(_, workspace)| workspace.get_member_role(caller_principal).is_some())
+				.map(|(_, workspace)| workspace.clone())
+				.collect::<Vec<Workspace>>()
+		})
+	}
+
+	/// Get workspace by id.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Workspace
+	pub fn get_workspace(workspace_id: u32, caller_principal: Principal) -> Result<Workspace, ApiError> {
+		Self::get_authorized_workspace(workspace_id, caller_principal, Role::Viewer)
+	}
+
+	/// Get the role of a principal in a workspace.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `principal` - Principal
+	///
+	/// # Returns
+	/// - `Option<Role>` - Role of the principal, if a member
+	pub fn get_member_role(workspace_id: u32, principal: Principal) -> Option<Role> {
+		WORKSPACES.with(|workspaces| {
+			workspaces
+				.borrow()
+				.get(&workspace_id)
+				.and_then(|workspace| workspace.get_member_role(principal))
+		})
+	}
+
+	/// Get workspace by id, if the caller has at least the required role.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `caller_principal` - Principal of the caller
+	/// - `required_role` - Minimum role required
+	///
+	/// # Returns
+	/// - `Workspace` - Workspace
+	pub fn get_authorized_workspace(
+		workspace_id: u32,
+		caller_principal: Principal,
+		required_role: Role
+	) -> Result<Workspace, ApiError> {
+		let workspace = WORKSPACES.with(|workspaces| workspaces.borrow().get(&workspace_id));
+
+		// Workspaces without membership are reported as not found, so their existence is not leaked
+		let role = workspace
+			.as_ref()
+			.and_then(|workspace| workspace.get_member_role(caller_principal))
+			.ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
+
+		validate_role(&role, required_role)?;
+
+		Ok(workspace.unwrap())
+	}
+
+	/// Add workspace. The caller becomes its owner.
+	///
+	/// # Arguments
+	/// - `data` - Workspace data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Added workspace
+	pub fn add_workspace(data: PostWorkspace, caller_principal: Principal) -> Workspace {
+		WORKSPACES.with(|workspaces| {
+			let mut workspaces = workspaces.borrow_mut();
+
+			let workspace_id = SequencesStore::next_id(WORKSPACES_SEQUENCE);
+
+			let new_workspace = Workspace {
+				id: workspace_id,
+				name: data.name,
+				members: vec![WorkspaceMember {
+					principal: caller_principal,
+					role: Role::Owner,
+					added_by: caller_principal,
+					added_at: time(),
+				}],
+				cycles_balance: 0,
+				created_at: time(),
+				updated_at: time(),
+			};
+
+			// Add new workspace
+			workspaces.insert(workspace_id, new_workspace.clone());
+
+			new_workspace
+		})
+	}
+
+	/// Edit workspace.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `data` - Workspace data
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Edited workspace
+	pub fn edit_workspace(
+		workspace_id: u32,
+		data: PostWorkspace,
+		caller_principal: Principal
+	) -> Result<Workspace, ApiError> {
+		let mut workspace = Self::get_authorized_workspace(workspace_id, caller_principal, Role::Admin)?;
+
+		// Mutate values
+		workspace.name = data.name;
+
+		Ok(Self::save_workspace(workspace))
+	}
+
+	/// Add a member to a workspace, or change the role of an existing member.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `principal` - Principal of the member
+	/// - `role` - Role of the member
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Edited workspace
+	pub fn add_member(
+		workspace_id: u32,
+		principal: Principal,
+		role: Role,
+		caller_principal: Principal
+	) -> Result<Workspace, ApiError> {
+		let mut workspace = Self::get_authorized_workspace(workspace_id, caller_principal, Role::Admin)?;
+		validate_anonymous(&principal)?;
+
+		if role == Role::Owner {
+			return Err(ApiError::BadRequest("OWNER ROLE CANNOT BE GRANTED".to_string()));
+		}
+
+		match workspace.members.iter_mut().find(|member| member.principal == principal) {
+			Some(member) if member.role == Role::Owner => {
+				return Err(ApiError::BadRequest("PRINCIPAL IS THE OWNER".to_string()));
+			}
+			Some(member) => {
+				member.role = role;
+			}
+			None =>
+				workspace.members.push(WorkspaceMember {
+					principal,
+					role,
+					added_by: caller_principal,
+					added_at: time(),
+				}),
+		}
+
+		Ok(Self::save_workspace(workspace))
+	}
+
+	/// Remove a member from a workspace. Members can always remove themselves, except the owner.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `principal` - Principal of the member
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Edited workspace
+	pub fn remove_member(
+		workspace_id: u32,
+		principal: Principal,
+		caller_principal: Principal
+	) -> Result<Workspace, ApiError> {
+		let required_role = if principal == caller_principal { Role::Viewer } else { Role::Admin };
+		let mut workspace = Self::get_authorized_workspace(workspace_id, caller_principal, required_role)?;
+
+		match workspace.get_member_role(principal) {
+			Some(Role::Owner) => {
+				return Err(ApiError::BadRequest("OWNER CANNOT BE REMOVED".to_string()));
+			}
+			Some(_) => workspace.members.retain(|member| member.principal != principal),
+			None => {
+				return Err(ApiError::NotFound("MEMBER NOT FOUND".to_string()));
+			}
+		}
+
+		Ok(Self::save_workspace(workspace))
+	}
+
+	/// Add the cycles attached to the call to the workspace's cycles balance.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Edited workspace
+	pub fn deposit_cycles(workspace_id: u32, caller_principal: Principal) -> Result<Workspace, ApiError> {
+		let mut workspace = Self::get_authorized_workspace(workspace_id, caller_principal, Role::Viewer)?;
+
+		let available_cycles = msg_cycles_available128();
+		if available_cycles == 0 {
+			return Err(ApiError::BadRequest("NO CYCLES ATTACHED".to_string()));
+		}
+
+		// Mutate values
+		workspace.cycles_balance += msg_cycles_accept128(available_cycles);
+
+		Ok(Self::save_workspace(workspace))
+	}
+
+	/// Fund the node canister of a workspace circuit from the workspace's cycles balance.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	/// - `circuit_id` - Circuit ID
+	/// - `cycles` - Amount of cycles
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Workspace` - Edited workspace
+	pub async fn fund_circuit(
+		workspace_id: u32,
+		circuit_id: u32,
+		cycles: u128,
+		caller_principal: Principal
+	) -> Result<Workspace, ApiError> {
+		let mut workspace = Self::get_authorized_workspace(workspace_id, caller_principal, Role::Editor)?;
+		let circuit = CircuitsStore::get_circuit(circuit_id, caller_principal)?;
+
+		if circuit.workspace_id != Some(workspace_id) {
+			return Err(ApiError::BadRequest("CIRCUIT NOT OWNED BY WORKSPACE".to_string()));
+		}
+
+		if workspace.cycles_balance < cycles {
+			return Err(ApiError::BadRequest("INSUFFICIENT CYCLES BALANCE".to_string()));
+		}
+
+		// Debit the balance before the call, so concurrent calls cannot spend the same cycles
+		workspace.cycles_balance -= cycles;
+		Self::save_workspace(workspace);
+
+		let result = deposit_cycles(CanisterIdRecord { canister_id: circuit.node_canister_id }, cycles).await;
+
+		if let Err((_, message)) = result {
+			// Refund the balance
+			if let Some(mut workspace) = WORKSPACES.with(|workspaces| workspaces.borrow().get(&workspace_id)) {
+				workspace.cycles_balance += cycles;
+				Self::save_workspace(workspace);
+			}
+
+			return Err(ApiError::InterCanister(message));
+		}
+
+		Self::get_workspace(workspace_id, caller_principal)
+	}
+
+	/// Owner of the keys of circuits and connectors owned by a workspace.
+	///
+	/// # Arguments
+	/// - `workspace_id` - Workspace ID
+	///
+	/// # Returns
+	/// - `String` - Key owner
+	pub fn key_owner(workspace_id: u32) -> String {
+		format!("workspace:{workspace_id}")
+	}
+
+	fn save_workspace(mut workspace: Workspace) -> Workspace {
+		workspace.updated_at = time();
+
+		WORKSPACES.with(|workspaces| {
+			// Add new workspace or overwrite existing one
+			workspaces.borrow_mut().insert(workspace.id, workspace.clone());
+		});
+
+		workspace
+	}
+}
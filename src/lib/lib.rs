@@ -1,22 +1,49 @@
 pub mod types {
+	pub mod admin;
+	pub mod admin_audit;
 	pub mod api_error;
+	pub mod candid_value;
 	pub mod circuit_key;
 	pub mod circuit;
+	pub mod circuit_export;
+	pub mod circuit_revision_key;
+	pub mod circuit_revision;
+	pub mod circuit_validation;
 	pub mod headers;
+	pub mod http_gateway;
+	pub mod node_canister_config;
 	pub mod node;
+	pub mod oauth2_token;
+	pub mod secret_key;
+	pub mod secret;
+	pub mod share_key;
+	pub mod share;
 	pub mod trace_key;
 	pub mod trace;
 	pub mod user;
 	pub mod connector;
 	pub mod connector_key;
+	pub mod connector_secrets;
+	pub mod workspace;
 }
 
 pub mod utils {
+	pub mod candid_conversion;
+	pub mod candid_interface;
+	pub mod cbor;
+	pub mod http_outcall;
 	pub mod idempotency;
+	pub mod jwt;
 	pub mod macros;
+	pub mod oauth2;
+	pub mod pins;
+	pub mod request_signing;
 	pub mod save_candid;
+	pub mod sequence;
+	pub mod template;
 	pub mod validate;
+	pub mod vault;
+	pub mod webhook;
 }
 
-pub mod whitelist;
 pub mod node_server;
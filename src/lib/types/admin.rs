@@ -0,0 +1,18 @@
+use candid::{ CandidType, Principal };
+use serde::Deserialize;
+use crate::impl_storable_for;
+
+impl_storable_for!(Admin);
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct Admin {
+	pub principal: Principal,
+	pub added_by: Principal,
+	pub added_at: u64,
+}
+
+/// Arguments of the main canister when it is installed.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct CanisterInitArgs {
+	/// Initial admins. The installer becomes the admin if empty
+	pub admins: Vec<Principal>,
+}
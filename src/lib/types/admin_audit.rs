@@ -0,0 +1,21 @@
+use candid::{ CandidType, Principal };
+use serde::Deserialize;
+use crate::impl_storable_for;
+
+impl_storable_for!(AdminAuditEntry);
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct AdminAuditEntry {
+	pub id: u32,
+	pub action: AdminAction,
+	/// Admin added or removed
+	pub principal: Principal,
+	/// Principal making the change, the canister itself for changes made on install or upgrade
+	pub changed_by: Principal,
+	pub created_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum AdminAction {
+	Added,
+	Removed,
+}
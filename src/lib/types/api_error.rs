@@ -7,4 +7,7 @@ pub enum ApiError {
 	NotFound(String),
 	AlreadyExists(String),
 	InterCanister(String),
+	BadRequest(String),
+	/// Error returned by the service behind a connector
+	Connector(String),
 }
@@ -0,0 +1,155 @@
+use candid::{ types::{ value::{ IDLField, IDLValue, VariantValue }, Label }, CandidType, Int, Nat, Principal };
+use serde::{ Deserialize, Serialize };
+use super::api_error::ApiError;
+
+/// Dynamic Candid value. `nat` and `int` are kept as decimal strings, so they survive JSON without losing precision.
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CandidValue {
+	Null,
+	Bool(bool),
+	Nat(String),
+	Int(String),
+	Nat8(u8),
+	Nat16(u16),
+	Nat32(u32),
+	Nat64(u64),
+	Int8(i8),
+	Int16(i16),
+	Int32(i32),
+	Int64(i64),
+	Float32(f32),
+	Float64(f64),
+	Text(String),
+	Principal(Principal),
+	Blob(Vec<u8>),
+	Opt(Option<Box<CandidValue>>),
+	Vec(Vec<CandidValue>),
+	Record(Vec<CandidField>),
+	Variant(Box<CandidField>),
+	Reserved,
+}
+
+// Values are compared structurally, a NaN float is the only value not equal to itself
+impl Eq for CandidValue {}
+
+/// Field of a record or variant. Fields without a name are labeled by their numeric ID, e.g. `0` for tuples.
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CandidField {
+	pub label: String,
+	pub value: CandidValue,
+}
+
+impl CandidValue {
+	/// Convert to the value model of the `candid` crate, used for encoding.
+	pub fn to_idl_value(&self) -> Result<IDLValue, ApiError> {
+		Ok(match self {
+			CandidValue::Null => IDLValue::Null,
+			CandidValue::Bool(value) => IDLValue::Bool(*value),
+			CandidValue::Nat(value) => IDLValue::Nat(parse_nat(value)?),
+			CandidValue::Int(value) => IDLValue::Int(parse_int(value)?),
+			CandidValue::Nat8(value) => IDLValue::Nat8(*value),
+			CandidValue::Nat16(value) => IDLValue::Nat16(*value),
+			CandidValue::Nat32(value) => IDLValue::Nat32(*value),
+			CandidValue::Nat64(value) => IDLValue::Nat64(*value),
+			CandidValue::Int8(value) => IDLValue::Int8(*value),
+			CandidValue::Int16(value) => IDLValue::Int16(*value),
+			CandidValue::Int32(value) => IDLValue::Int32(*value),
+			CandidValue::Int64(value) => IDLValue::Int64(*value),
+			CandidValue::Float32(value) => IDLValue::Float32(*value),
+			CandidValue::Float64(value) => IDLValue::Float64(*value),
+			CandidValue::Text(value) => IDLValue::Text(value.clone()),
+			CandidValue::Principal(value) => IDLValue::Principal(*value),
+			CandidValue::Blob(value) => IDLValue::Blob(value.clone()),
+			CandidValue::Opt(None) => IDLValue::None,
+			CandidValue::Opt(Some(value)) => IDLValue::Opt(Box::new(value.to_idl_value()?)),
+			CandidValue::Vec(values) => {
+				IDLValue::Vec(
+					values
+						.iter()
+						.map(|value| value.to_idl_value())
+						.collect::<Result<_, _>>()?
+				)
+			}
+			CandidValue::Record(fields) => {
+				IDLValue::Record(
+					fields
+						.iter()
+						.map(|field| field.to_idl_field())
+						.collect::<Result<_, _>>()?
+				)
+			}
+			// The index is resolved against the type when encoding with a type
+			CandidValue::Variant(field) => IDLValue::Variant(VariantValue(Box::new(field.to_idl_field()?), 0)),
+			CandidValue::Reserved => IDLValue::Reserved,
+		})
+	}
+
+	/// Convert from the value model of the `candid` crate. Service and function references become their text form.
+	pub fn from_idl_value(value: &IDLValue) -> Self {
+		match value {
+			IDLValue::Null => CandidValue::Null,
+			IDLValue::Bool(value) => CandidValue::Bool(*value),
+			IDLValue::Nat(value) => CandidValue::Nat(value.0.to_string()),
+			IDLValue::Int(value) => CandidValue::Int(value.0.to_string()),
+			// Numbers without a type only come from the parser
+			IDLValue::Number(value) => CandidValue::Int(value.clone()),
+			IDLValue::Nat8(value) => CandidValue::Nat8(*value),
+			IDLValue::Nat16(value) => CandidValue::Nat16(*value),
+			IDLValue::Nat32(value) => CandidValue::Nat32(*value),
+			IDLValue::Nat64(value) => CandidValue::Nat64(*value),
+			IDLValue::Int8(value) => CandidValue::Int8(*value),
+			IDLValue::Int16(value) => CandidValue::Int16(*value),
+			IDLValue::Int32(value) => CandidValue::Int32(*value),
+			IDLValue::Int64(value) => CandidValue::Int64(*value),
+			IDLValue::Float32(value) => CandidValue::Float32(*value),
+			IDLValue::Float64(value) => CandidValue::Float64(*value),
+			IDLValue::Text(value) => CandidValue::Text(value.clone()),
+			IDLValue::Principal(value) => CandidValue::Principal(*value),
+			IDLValue::Service(value) => CandidValue::Principal(*value),
+			IDLValue::Func(principal, method) => CandidValue::Text(format!("{principal}.{method}")),
+			IDLValue::Blob(value) => CandidValue::Blob(value.clone()),
+			IDLValue::None => CandidValue::Opt(None),
+			IDLValue::Opt(value) => CandidValue::Opt(Some(Box::new(Self::from_idl_value(value)))),
+			IDLValue::Vec(values) => CandidValue::Vec(values.iter().map(Self::from_idl_value).collect()),
+			IDLValue::Record(fields) => CandidValue::Record(fields.iter().map(CandidField::from_idl_field).collect()),
+			IDLValue::Variant(variant) => CandidValue::Variant(Box::new(CandidField::from_idl_field(&variant.0))),
+			IDLValue::Reserved => CandidValue::Reserved,
+		}
+	}
+}
+
+impl CandidField {
+	fn to_idl_field(&self) -> Result<IDLField, ApiError> {
+		let id = match self.label.parse::<u32>() {
+			Ok(id) => Label::Id(id),
+			Err(_) => Label::Named(self.label.clone()),
+		};
+
+		Ok(IDLField { id, val: self.value.to_idl_value()? })
+	}
+
+	fn from_idl_field(field: &IDLField) -> Self {
+		let label = match &field.id {
+			Label::Named(name) => name.clone(),
+			Label::Id(id) | Label::Unnamed(id) => id.to_string(),
+		};
+
+		Self { label, value: CandidValue::from_idl_value(&field.val) }
+	}
+}
+
+fn parse_nat(value: &str) -> Result<Nat, ApiError> {
+	value
+		.trim()
+		.replace('_', "")
+		.parse::<Nat>()
+		.map_err(|_| ApiError::BadRequest(format!("INVALID nat: {value}")))
+}
+
+fn parse_int(value: &str) -> Result<Int, ApiError> {
+	value
+		.trim()
+		.replace('_', "")
+		.parse::<Int>()
+		.map_err(|_| ApiError::BadRequest(format!("INVALID int: {value}")))
+}
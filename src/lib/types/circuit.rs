@@ -8,6 +8,8 @@ pub struct Circuit {
 	pub id: u32,
 	pub user_id: Principal,
 	pub node_canister_id: Principal,
+	// Workspace owning the circuit, if not owned personally
+	pub workspace_id: Option<u32>,
 	pub name: String,
 	pub description: Option<String>,
 	pub is_favorite: bool,
@@ -24,6 +26,7 @@ impl Circuit {
 			id: Default::default(),
 			user_id: Principal::anonymous(),
 			node_canister_id: Principal::anonymous(),
+			workspace_id: Default::default(),
 			name: Default::default(),
 			description: Default::default(),
 			is_favorite: Default::default(),
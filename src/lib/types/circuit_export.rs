@@ -0,0 +1,336 @@
+use std::collections::HashMap;
+use serde::{ Deserialize, Serialize };
+use serde_json::{ json, Value };
+use super::{ circuit::Circuit, node::{ Node, NodeType, Pin, PostNode } };
+
+/// Version of the export document schema. Bump this whenever the document layout changes, and migrate the
+/// documents of the previous version in `CircuitExport::migrate`.
+///
+/// - `1` - Connectors referenced by name only, pins without IDs
+/// - `2` - Connectors with the ID the nodes reference them by
+pub static CIRCUIT_EXPORT_SCHEMA_VERSION: u32 = 2;
+
+/// Oldest version of the export document schema that can still be imported.
+pub static CIRCUIT_EXPORT_MIN_SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitExport {
+	pub schema_version: u32,
+	pub circuit: CircuitExportMetadata,
+	pub nodes: Vec<NodeExport>,
+	/// Connectors referenced by the nodes
+	#[serde(default)]
+	pub connectors: Vec<ConnectorExport>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConnectorExport {
+	/// Connector ID as referenced by the nodes of the document
+	pub id: u32,
+	/// Name used to match a connector of the importing account
+	pub name: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CircuitExportMetadata {
+	pub name: String,
+	pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NodeExport {
+	pub order: u32,
+	pub is_enabled: bool,
+	pub node_type: NodeType,
+	pub pins: Vec<Pin>,
+}
+
+impl CircuitExport {
+	/// Build an export document from a circuit and its nodes. Secrets are stripped.
+	///
+	/// # Arguments
+	/// - `circuit` - Circuit
+	/// - `nodes` - Nodes of the circuit
+	/// - `connectors` - Connectors referenced by the nodes
+	///
+	/// # Returns
+	/// - `CircuitExport` - Export document
+	pub fn new(circuit: &Circuit, nodes: Vec<Node>, connectors: Vec<ConnectorExport>) -> Self {
+		let mut nodes = nodes
+			.into_iter()
+			.map(|node| {
+				let mut node_type = node.node_type;
+				node_type.strip_secrets();
+
+				NodeExport {
+					order: node.order,
+					is_enabled: node.is_enabled,
+					node_type,
+					pins: node.pins,
+				}
+			})
+			.collect::<Vec<NodeExport>>();
+
+		nodes.sort_by_key(|node| node.order);
+
+		Self {
+			schema_version: CIRCUIT_EXPORT_SCHEMA_VERSION,
+			circuit: CircuitExportMetadata {
+				name: circuit.name.clone(),
+				description: circuit.description.clone(),
+			},
+			nodes,
+			connectors,
+		}
+	}
+
+	/// Parse and validate an export document.
+	///
+	/// # Arguments
+	/// - `document` - Stringified JSON export document
+	///
+	/// # Returns
+	/// - `Result<CircuitExport, Vec<String>>` - Export document or the list of incompatibilities
+	pub fn from_json(document: &str) -> Result<Self, Vec<String>> {
+		let mut value: Value = serde_json::from_str(document).map_err(|err| vec![format!("INVALID JSON: {err}")])?;
+
+		// Check the schema version before the layout, so older/newer documents get a meaningful error
+		let version = match value.get("schema_version").and_then(|version| version.as_u64()) {
+			Some(version) => version,
+			None => {
+				return Err(vec!["MISSING SCHEMA VERSION".to_string()]);
+			}
+		};
+
+		let supported_versions = (CIRCUIT_EXPORT_MIN_SCHEMA_VERSION as u64)..=(CIRCUIT_EXPORT_SCHEMA_VERSION as u64);
+
+		if !supported_versions.contains(&version) {
+			let (min, max) = (CIRCUIT_EXPORT_MIN_SCHEMA_VERSION, CIRCUIT_EXPORT_SCHEMA_VERSION);
+			return Err(vec![format!("UNSUPPORTED SCHEMA VERSION: {version}, EXPECTED {min} TO {max}")]);
+		}
+
+		// Bring older documents to the current layout, one version at a time
+		for from_version in version..(CIRCUIT_EXPORT_SCHEMA_VERSION as u64) {
+			Self::migrate(&mut value, from_version)?;
+		}
+
+		let export: Self = serde_json::from_value(value).map_err(|err| vec![format!("INVALID DOCUMENT: {err}")])?;
+
+		let mut errors = vec![];
+
+		if export.circuit.name.trim().is_empty() {
+			errors.push("CIRCUIT NAME IS EMPTY".to_string());
+		}
+
+		let mut orders = export.nodes
+			.iter()
+			.map(|node| node.order)
+			.collect::<Vec<u32>>();
+		orders.sort();
+		orders.dedup();
+
+		if orders.len() != export.nodes.len() {
+			errors.push("DUPLICATE NODE ORDER".to_string());
+		}
+
+		for node in export.nodes.iter() {
+			if let Some(connector_id) = node.node_type.connector_id() {
+				if !export.connectors.iter().any(|export_connector| export_connector.id == connector_id) {
+					errors.push(format!("UNDECLARED CONNECTOR: {connector_id}"));
+				}
+			}
+		}
+
+		if errors.is_empty() {
+			Ok(export)
+		} else {
+			Err(errors)
+		}
+	}
+
+	/// Migrate a document to the next schema version.
+	///
+	/// # Arguments
+	/// - `value` - Document of the given version, migrated in place
+	/// - `from_version` - Schema version of the document
+	fn migrate(value: &mut Value, from_version: u64) -> Result<(), Vec<String>> {
+		if from_version == 1 {
+			// Version 1 listed connector names, the nodes did not reference connectors by ID yet
+			let names = match value.get("connectors") {
+				Some(Value::Array(connectors)) => connectors.clone(),
+				Some(_) => {
+					return Err(vec!["INVALID DOCUMENT: connectors must be a list of names".to_string()]);
+				}
+				None => vec![],
+			};
+
+			let mut connectors = vec![];
+			for (index, name) in names.into_iter().enumerate() {
+				match name {
+					Value::String(name) => connectors.push(json!({ "id": index + 1, "name": name })),
+					_ => {
+						return Err(vec!["INVALID DOCUMENT: connectors must be a list of names".to_string()]);
+					}
+				}
+			}
+
+			value["connectors"] = Value::Array(connectors);
+		}
+
+		value["schema_version"] = json!(from_version + 1);
+
+		Ok(())
+	}
+
+	/// Stringify the export document.
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(self).expect("Failed to serialize CircuitExport")
+	}
+
+	/// Nodes of the document in execution order, ready to be added to a node canister.
+	///
+	/// # Arguments
+	/// - `connector_ids` - Connector IDs of the document mapped to the connector IDs of the importing account
+	///
+	/// # Returns
+	/// - `Vec<PostNode>` - Nodes
+	pub fn post_nodes(&self, connector_ids: &HashMap<u32, u32>) -> Vec<PostNode> {
+		let mut nodes = self.nodes.clone();
+		nodes.sort_by_key(|node| node.order);
+
+		nodes
+			.into_iter()
+			.map(|mut node| {
+				if let Some(connector_id) = node.node_type.connector_id_mut() {
+					if let Some(imported_connector_id) = connector_ids.get(connector_id) {
+						*connector_id = *imported_connector_id;
+					}
+				}
+
+				PostNode {
+					node_type: node.node_type,
+					is_enabled: node.is_enabled,
+					pins: node.pins,
+				}
+			})
+			.collect()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+	use super::*;
+
+	fn node(id: u32, order: u32, node_type: Value) -> Node {
+		Node {
+			id,
+			order,
+			is_enabled: true,
+			node_type: serde_json::from_value(node_type).unwrap(),
+			..Default::default()
+		}
+	}
+
+	fn circuit_nodes() -> Vec<Node> {
+		let input = json!({
+			"Canister": {
+				"name": "Input",
+				"verification_type": { "Token": { "token": "super-secret", "field": "token" } },
+				"description": null,
+				"sample_data": "",
+			},
+		});
+		let lookup = json!({
+			"LookupHttpRequest": {
+				"name": "Lookup",
+				"description": null,
+				"url": "",
+				"method": "GET",
+				"headers": { "Authorization": "Bearer abc", "X-Api-Key": "{{secret.API_KEY}}", "Accept": "*/*" },
+				"request_body": null,
+				"cycles": 0,
+				"sample_data": "",
+				"connector": { "connector_id": 7, "path": "/users?api_key=abc&page=2" },
+			},
+		});
+
+		// Out of order, the export sorts the nodes
+		vec![node(2, 2, lookup), node(1, 1, input)]
+	}
+
+	fn circuit() -> Circuit {
+		Circuit {
+			name: "Webhook".to_string(),
+			description: Some("Looks up the user".to_string()),
+			..Circuit::default()
+		}
+	}
+
+	#[test]
+	fn export_import_round_trip() {
+		let connectors = vec![ConnectorExport { id: 7, name: "Users API".to_string() }];
+
+		let export = CircuitExport::new(&circuit(), circuit_nodes(), connectors);
+		let import = CircuitExport::from_json(&export.to_json()).unwrap();
+
+		assert_eq!(import.schema_version, CIRCUIT_EXPORT_SCHEMA_VERSION);
+		assert_eq!(import.circuit.name, "Webhook");
+		assert_eq!(import.circuit.description, Some("Looks up the user".to_string()));
+		assert_eq!(
+			import.nodes
+				.iter()
+				.map(|node| node.order)
+				.collect::<Vec<u32>>(),
+			vec![1, 2]
+		);
+		assert_eq!(import.connectors[0].name, "Users API");
+
+		let post_nodes = import.post_nodes(&HashMap::from([(7, 12)]));
+
+		assert_eq!(post_nodes[1].node_type.connector().map(|connector| connector.connector_id), Some(12));
+	}
+
+	#[test]
+	fn export_strips_secrets() {
+		let connectors = vec![ConnectorExport { id: 7, name: "Users API".to_string() }];
+
+		let document = CircuitExport::new(&circuit(), circuit_nodes(), connectors).to_json();
+
+		assert!(!document.contains("super-secret"));
+		assert!(!document.contains("Bearer abc"));
+		assert!(!document.contains("api_key=abc"));
+		assert!(document.contains("{{secret.API_KEY}}"));
+		assert!(document.contains("page=2"));
+	}
+
+	#[test]
+	fn import_migrates_and_validates_documents() {
+		let version_1 = json!({
+			"schema_version": 1,
+			"circuit": { "name": "Webhook", "description": null },
+			"nodes": [],
+			"connectors": ["Users API"],
+		});
+
+		let import = CircuitExport::from_json(&version_1.to_string()).unwrap();
+
+		assert_eq!(import.schema_version, CIRCUIT_EXPORT_SCHEMA_VERSION);
+		assert_eq!(import.connectors[0].id, 1);
+		assert_eq!(import.connectors[0].name, "Users API");
+
+		let newer = json!({ "schema_version": CIRCUIT_EXPORT_SCHEMA_VERSION + 1 });
+		assert_eq!(
+			CircuitExport::from_json(&newer.to_string()).unwrap_err(),
+			vec![format!("UNSUPPORTED SCHEMA VERSION: {}, EXPECTED 1 TO 2", CIRCUIT_EXPORT_SCHEMA_VERSION + 1)]
+		);
+
+		// The lookup node references a connector the document does not declare
+		let export = CircuitExport::new(&circuit(), circuit_nodes(), vec![]);
+		assert_eq!(CircuitExport::from_json(&export.to_json()).unwrap_err(), vec!["UNDECLARED CONNECTOR: 7"]);
+	}
+}
@@ -0,0 +1,122 @@
+use candid::{ CandidType, Principal };
+use serde::Deserialize;
+use crate::impl_storable_for;
+use super::node::Node;
+
+impl_storable_for!(CircuitRevision);
+/// Immutable snapshot of all nodes (and their pins) of a circuit at publish time.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct CircuitRevision {
+	pub revision: u32,
+	pub circuit_id: u32,
+	pub user_id: Principal,
+	pub nodes: Vec<Node>,
+	pub published_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub enum NodeChange {
+	Added(Node),
+	Removed(Node),
+	Changed(Box<NodeChangeDetails>),
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct NodeChangeDetails {
+	pub before: Node,
+	pub after: Node,
+	pub node_type_changed: bool,
+	pub pins_changed: bool,
+	pub order_changed: bool,
+	pub is_enabled_changed: bool,
+}
+
+impl CircuitRevision {
+	/// Diff two sets of nodes, matching nodes by ID.
+	///
+	/// # Arguments
+	/// - `before` - Nodes of the older revision
+	/// - `after` - Nodes of the newer revision (or draft)
+	///
+	/// # Returns
+	/// - `Vec<NodeChange>` - Added, removed and changed nodes
+	pub fn diff(before: &[Node], after: &[Node]) -> Vec<NodeChange> {
+		let mut changes = vec![];
+
+		for old_node in before {
+			match after.iter().find(|node| node.id == old_node.id) {
+				Some(new_node) => {
+					let details = NodeChangeDetails {
+						before: old_node.clone(),
+						after: new_node.clone(),
+						node_type_changed: old_node.node_type != new_node.node_type,
+						pins_changed: old_node.pins != new_node.pins,
+						order_changed: old_node.order != new_node.order,
+						is_enabled_changed: old_node.is_enabled != new_node.is_enabled,
+					};
+
+					if
+						details.node_type_changed ||
+						details.pins_changed ||
+						details.order_changed ||
+						details.is_enabled_changed
+					{
+						changes.push(NodeChange::Changed(Box::new(details)));
+					}
+				}
+				None => changes.push(NodeChange::Removed(old_node.clone())),
+			}
+		}
+
+		for new_node in after {
+			if !before.iter().any(|node| node.id == new_node.id) {
+				changes.push(NodeChange::Added(new_node.clone()));
+			}
+		}
+
+		changes
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn node(id: u32, order: u32) -> Node {
+		Node {
+			id,
+			order,
+			is_enabled: true,
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn diff_identical_nodes() {
+		let nodes = vec![node(1, 1), node(2, 2)];
+
+		assert!(CircuitRevision::diff(&nodes, &nodes).is_empty());
+	}
+
+	#[test]
+	fn diff_added_removed_and_changed_nodes() {
+		let mut disabled = node(2, 1);
+		disabled.is_enabled = false;
+
+		let changes = CircuitRevision::diff(&[node(1, 1), node(2, 2)], &[disabled, node(3, 2)]);
+
+		assert_eq!(changes.len(), 3);
+		assert!(matches!(&changes[0], NodeChange::Removed(node) if node.id == 1));
+		assert!(matches!(&changes[2], NodeChange::Added(node) if node.id == 3));
+
+		let NodeChange::Changed(details) = &changes[1] else {
+			panic!("expected node 2 to be changed, got {:?}", changes[1]);
+		};
+
+		assert_eq!(details.before.id, 2);
+		assert!(details.order_changed);
+		assert!(details.is_enabled_changed);
+		assert!(!details.node_type_changed);
+		assert!(!details.pins_changed);
+	}
+}
@@ -0,0 +1,10 @@
+use candid::CandidType;
+use serde::{ Deserialize, Serialize };
+use crate::impl_storable_for;
+
+impl_storable_for!(CircuitRevisionKey);
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CircuitRevisionKey {
+	pub circuit_id: u32,
+	pub revision: u32,
+}
@@ -0,0 +1,331 @@
+use candid::CandidType;
+use serde::Deserialize;
+use serde_json::Value;
+use super::{ api_error::ApiError, node::{ FilterPin, Node, NodeType, OperandType, PinType } };
+
+#[derive(Clone, Debug, CandidType, Deserialize, PartialEq, Eq)]
+pub enum ValidationSeverity {
+	/// Blocks the circuit from being enabled
+	Error,
+	Warning,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct ValidationIssue {
+	pub severity: ValidationSeverity,
+	pub code: String,
+	pub message: String,
+	pub node_id: Option<u32>,
+	pub pin_type: Option<String>,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct CircuitValidation {
+	pub circuit_id: u32,
+	pub is_valid: bool,
+	pub issues: Vec<ValidationIssue>,
+}
+
+impl CircuitValidation {
+	/// Validate the structure of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `nodes` - Nodes of the circuit
+	///
+	/// # Returns
+	/// - `CircuitValidation` - Errors and warnings. The circuit is valid if there are no errors
+	pub fn validate(circuit_id: u32, nodes: &[Node]) -> Self {
+		let mut nodes = nodes.to_vec();
+		nodes.sort_by_key(|node| node.order);
+
+		let mut issues = vec![];
+
+		if nodes.is_empty() {
+			issues.push(Self::error("NO_NODES", "Circuit has no nodes".to_string(), None, None));
+		}
+
+		let input_nodes = nodes
+			.iter()
+			.filter(|node| node.node_type.is_input())
+			.collect::<Vec<&Node>>();
+
+		match input_nodes.first() {
+			None if !nodes.is_empty() => {
+				issues.push(Self::error("NO_INPUT_NODE", "Circuit has no input node".to_string(), None, None));
+			}
+			Some(input_node) => {
+				// Executions skip disabled nodes, so no node would receive the input data
+				if !input_node.is_enabled {
+					issues.push(
+						Self::error(
+							"INPUT_NODE_DISABLED",
+							"Input node is disabled".to_string(),
+							Some(input_node.id),
+							None
+						)
+					);
+				}
+
+				// Every node before the input node would run without data
+				for node in nodes.iter().take_while(|node| node.id != input_node.id) {
+					let (code, message) = match node.node_type {
+						NodeType::Output(_) => ("OUTPUT_BEFORE_INPUT", "Output node is placed before the input node"),
+						_ => ("NODE_BEFORE_INPUT", "Node is placed before the input node"),
+					};
+
+					issues.push(Self::error(code, message.to_string(), Some(node.id), None));
+				}
+			}
+			None => {}
+		}
+
+		for input_node in input_nodes.iter().skip(1) {
+			issues.push(
+				Self::error(
+					"MULTIPLE_INPUT_NODES",
+					"Circuit has more than one input node".to_string(),
+					Some(input_node.id),
+					None
+				)
+			);
+		}
+
+		if !nodes.is_empty() && !nodes.iter().any(|node| matches!(node.node_type, NodeType::Output(_))) {
+			issues.push(Self::warning("NO_OUTPUT_NODE", "Circuit has no output node".to_string(), None, None));
+		}
+
+		for pair in nodes.windows(2) {
+			if pair[0].order == pair[1].order {
+				issues.push(
+					Self::error(
+						"DUPLICATE_ORDER",
+						format!("Node has the same order ({}) as node {}", pair[1].order, pair[0].id),
+						Some(pair[1].id),
+						None
+					)
+				);
+			}
+		}
+
+		for node in nodes.iter() {
+			for pin in node.pins.iter() {
+				let pin_type = Some(pin.pin_type.name().to_string());
+
+				match &pin.pin_type {
+					PinType::LookupFilterPin(_) | PinType::LookupTransformPin(_) if !node.node_type.is_lookup() => {
+						issues.push(
+							Self::error(
+								"LOOKUP_PIN_ON_NON_LOOKUP_NODE",
+								format!("{} can only be used on lookup nodes", pin.pin_type.name()),
+								Some(node.id),
+								pin_type.clone()
+							)
+						);
+					}
+					_ => {}
+				}
+
+				if let PinType::FilterPin(filter_pin) | PinType::LookupFilterPin(filter_pin) = &pin.pin_type {
+					issues.extend(Self::validate_filter_pin(node.id, &pin_type, filter_pin));
+				}
+			}
+		}
+
+		Self {
+			circuit_id,
+			is_valid: !issues.iter().any(|issue| issue.severity == ValidationSeverity::Error),
+			issues,
+		}
+	}
+
+	/// Fail if the circuit has blocking errors.
+	///
+	/// # Returns
+	/// - `Result<(), ApiError>` - Unit, or an error listing the codes of the blocking errors
+	pub fn ensure_valid(&self) -> Result<(), ApiError> {
+		if self.is_valid {
+			return Ok(());
+		}
+
+		let error_codes = self.issues
+			.iter()
+			.filter(|issue| issue.severity == ValidationSeverity::Error)
+			.map(|issue| issue.code.clone())
+			.collect::<Vec<String>>();
+
+		Err(ApiError::BadRequest(format!("INVALID CIRCUIT: {}", error_codes.join(", "))))
+	}
+
+	/// Check that the fields used by the filter rules exist in the pin's sample data.
+	fn validate_filter_pin(node_id: u32, pin_type: &Option<String>, filter_pin: &FilterPin) -> Vec<ValidationIssue> {
+		let sample_data = match serde_json::from_str::<Value>(&filter_pin.sample_data) {
+			Ok(sample_data) => sample_data,
+			Err(_) => {
+				return vec![
+					Self::warning(
+						"INVALID_SAMPLE_DATA",
+						"Sample data is not valid JSON, filter fields cannot be checked".to_string(),
+						Some(node_id),
+						pin_type.clone()
+					)
+				];
+			}
+		};
+
+		let mut fields = filter_pin.rules
+			.iter()
+			.map(|rule| rule.field.clone())
+			.collect::<Vec<String>>();
+
+		// The value of a rule refers to a field when compared against another field
+		fields.extend(
+			filter_pin.rules
+				.iter()
+				.filter(|rule| rule.operand.operand_type == OperandType::Field)
+				.map(|rule| rule.value.clone())
+		);
+
+		fields
+			.into_iter()
+			.filter(|field| Self::get_field(&sample_data, field).is_none())
+			.map(|field| {
+				Self::warning(
+					"FILTER_FIELD_NOT_FOUND",
+					format!("Field '{field}' does not exist in the sample data"),
+					Some(node_id),
+					pin_type.clone()
+				)
+			})
+			.collect()
+	}
+
+	/// Get a field from JSON by a dot separated path, e.g. `data.items.0.id`.
+	fn get_field<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+		path.split('.').try_fold(value, |value, key| {
+			match value {
+				Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+				_ => value.get(key),
+			}
+		})
+	}
+
+	fn error(code: &str, message: String, node_id: Option<u32>, pin_type: Option<String>) -> ValidationIssue {
+		ValidationIssue { severity: ValidationSeverity::Error, code: code.to_string(), message, node_id, pin_type }
+	}
+
+	fn warning(code: &str, message: String, node_id: Option<u32>, pin_type: Option<String>) -> ValidationIssue {
+		ValidationIssue { severity: ValidationSeverity::Warning, code: code.to_string(), message, node_id, pin_type }
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+	use super::*;
+
+	fn node(id: u32, order: u32, node_type: Value) -> Node {
+		Node {
+			id,
+			order,
+			is_enabled: true,
+			node_type: serde_json::from_value(node_type).unwrap(),
+			..Default::default()
+		}
+	}
+
+	fn input(id: u32, order: u32) -> Node {
+		node(
+			id,
+			order,
+			json!({
+				"Canister": { "name": "Input", "verification_type": "None", "description": null, "sample_data": "" },
+			})
+		)
+	}
+
+	fn output(id: u32, order: u32) -> Node {
+		node(
+			id,
+			order,
+			json!({ "Output": { "name": "Output", "description": null, "canister": "aaaaa-aa", "method": "notify" } })
+		)
+	}
+
+	fn codes(validation: &CircuitValidation) -> Vec<&str> {
+		validation.issues
+			.iter()
+			.map(|issue| issue.code.as_str())
+			.collect()
+	}
+
+	#[test]
+	fn validate_accepts_input_and_output() {
+		let validation = CircuitValidation::validate(1, &[output(2, 2), input(1, 1)]);
+
+		assert!(validation.is_valid);
+		assert!(validation.issues.is_empty());
+	}
+
+	#[test]
+	fn validate_reports_structure_errors() {
+		let empty = CircuitValidation::validate(1, &[]);
+		assert!(!empty.is_valid);
+		assert_eq!(codes(&empty), vec!["NO_NODES"]);
+
+		let output_first = CircuitValidation::validate(1, &[output(1, 1), input(2, 2)]);
+		assert!(!output_first.is_valid);
+		assert_eq!(codes(&output_first), vec!["OUTPUT_BEFORE_INPUT"]);
+
+		let two_inputs = CircuitValidation::validate(1, &[input(1, 1), input(2, 2), output(3, 2)]);
+		assert!(!two_inputs.is_valid);
+		assert_eq!(codes(&two_inputs), vec!["MULTIPLE_INPUT_NODES", "DUPLICATE_ORDER"]);
+	}
+
+	#[test]
+	fn validate_reports_pin_issues() {
+		let mut input = input(1, 1);
+		input.is_enabled = false;
+
+		let filter_pin = json!({
+			"rules": [
+				{
+					"field": "user.age",
+					"operator": "GreaterThan",
+					"value": "17",
+					"operand": { "operand_type": "Value", "data_type": "Number" }
+				}
+			],
+			"condition": "Is",
+			"condition_group": null,
+			"sample_data": "{ \"user\": { \"name\": \"Ada\" } }",
+		});
+		let pins = json!([
+			{ "id": 1, "order": 1, "pin_type": { "LookupTransformPin": { "input": "", "output": "" } } },
+			{ "id": 2, "order": 2, "pin_type": { "FilterPin": filter_pin } },
+		]);
+		input.pins = serde_json::from_value(pins).unwrap();
+
+		let validation = CircuitValidation::validate(1, &[input]);
+
+		assert!(!validation.is_valid);
+		assert_eq!(
+			codes(&validation),
+			vec!["INPUT_NODE_DISABLED", "NO_OUTPUT_NODE", "LOOKUP_PIN_ON_NON_LOOKUP_NODE", "FILTER_FIELD_NOT_FOUND"]
+		);
+		assert_eq!(validation.issues[0].severity, ValidationSeverity::Error);
+		assert_eq!(validation.issues[3].pin_type, Some("FilterPin".to_string()));
+	}
+
+	#[test]
+	fn ensure_valid_lists_blocking_errors() {
+		assert!(CircuitValidation::validate(1, &[input(1, 1), output(2, 2)]).ensure_valid().is_ok());
+
+		match CircuitValidation::validate(1, &[input(1, 1), input(2, 2), output(3, 2)]).ensure_valid() {
+			Err(ApiError::BadRequest(message)) => {
+				assert_eq!(message, "INVALID CIRCUIT: MULTIPLE_INPUT_NODES, DUPLICATE_ORDER");
+			}
+			result => panic!("expected a bad request, got {result:?}"),
+		}
+	}
+}
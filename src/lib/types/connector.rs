@@ -1,14 +1,22 @@
 use candid::{ CandidType, Deserialize, Principal };
-use crate::impl_storable_for;
-use super::{ headers::Headers, node::HttpRequestMethod };
+use serde::Serialize;
+use serde_json::Value;
+use crate::{ impl_storable_for, utils::template::lookup_path };
+use super::{ api_error::ApiError, connector_secrets::SecretStatus, headers::Headers, node::HttpRequestMethod };
 
 impl_storable_for!(Connector);
 #[derive(CandidType, Clone, Deserialize)]
 pub struct Connector {
 	pub id: u32,
 	pub user_id: Principal,
+	// Workspace owning the connector, if not owned personally
+	pub workspace_id: Option<u32>,
 	pub name: String,
 	pub connector_type: ConnectorType,
+	// Result of the last run of the test connection
+	pub last_test_result: Option<ConnectorTestResult>,
+	// Secret fields and whether they are set. Only filled in responses, secrets are stored apart from the connector
+	pub secrets: Option<Vec<SecretStatus>>,
 	pub created_at: u64,
 	pub updated_at: u64,
 }
@@ -18,6 +26,7 @@ impl Default for Connector {
 		Self {
 			id: Default::default(),
 			user_id: Principal::anonymous(),
+			workspace_id: Default::default(),
 			name: Default::default(),
 			connector_type: ConnectorType::Http(HttpConnector {
 				base_url: Default::default(),
@@ -25,12 +34,25 @@ impl Default for Connector {
 				authentication: Authentication::None,
 				test_connection: Default::default(),
 			}),
+			last_test_result: Default::default(),
+			secrets: Default::default(),
 			created_at: Default::default(),
 			updated_at: Default::default(),
 		}
 	}
 }
 
+impl Connector {
+	/// Authentication of the connector, if it is an HTTP connector.
+	pub fn authentication_mut(&mut self) -> Option<&mut Authentication> {
+		match &mut self.connector_type {
+			ConnectorType::Http(http_connector) => Some(&mut http_connector.authentication),
+			_ => None,
+		}
+	}
+}
+
+/// Secrets set to `SECRET_PLACEHOLDER` keep their stored value, any other value replaces it.
 #[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
 pub struct PostConnector {
 	pub name: String,
@@ -40,7 +62,57 @@ pub struct PostConnector {
 #[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
 pub enum ConnectorType {
 	Http(HttpConnector),
+	/// Canister ID as text. Superseded by `CanisterInterface`, kept for connectors stored before it
 	Canister(String),
+	CanisterInterface(CanisterConnector),
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct CanisterConnector {
+	pub canister_id: Principal,
+	// Candid interface (service description) of the canister
+	pub candid: String,
+	pub candid_source: CandidInterfaceSource,
+	// Methods of the Candid interface, derived from it when the connector is saved
+	pub methods: Vec<CanisterMethod>,
+	// When the Candid interface was last fetched or supplied
+	pub fetched_at: Option<u64>,
+}
+
+impl CanisterConnector {
+	/// Find a method of the Candid interface by name.
+	pub fn method(&self, name: &str) -> Option<&CanisterMethod> {
+		self.methods.iter().find(|method| method.name == name)
+	}
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub enum CandidInterfaceSource {
+	/// `candid:service` metadata of the canister, read from the state of its subnet
+	Metadata,
+	/// `__get_candid_interface_tmp_hack` query of the canister
+	InterfaceQuery,
+	/// Entered by the user before interfaces were fetched, or empty for connectors migrated from a canister ID.
+	/// Kept for stored connectors only, refreshing the interface replaces it
+	Manual,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct CanisterMethod {
+	pub name: String,
+	pub mode: CanisterMethodMode,
+	// Argument types in Candid syntax. Named types are defined in the Candid interface
+	pub args: Vec<String>,
+	// Return types in Candid syntax
+	pub returns: Vec<String>,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub enum CanisterMethodMode {
+	Update,
+	Query,
+	CompositeQuery,
+	Oneway,
 }
 
 #[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
@@ -51,12 +123,101 @@ pub struct HttpConnector {
 	pub test_connection: Option<TestConnection>,
 }
 
+impl HttpConnector {
+	/// Join the base URL of the connector with a relative path. Paths changing the scheme or host are rejected, the
+	/// headers and authentication of the connector are only sent to its base URL.
+	///
+	/// # Arguments
+	/// - `path` - Path relative to the base URL
+	///
+	/// # Returns
+	/// - `String` - Absolute URL
+	pub fn url(&self, path: &str) -> Result<String, ApiError> {
+		// Query strings may carry URLs, e.g. a redirect target
+		let path_only = path.split(['?', '#']).next().unwrap_or_default();
+
+		if path_only.contains("://") || path_only.contains('\\') || path_only.trim_start().starts_with("//") {
+			return Err(ApiError::BadRequest("CONNECTOR PATH MUST BE RELATIVE".to_string()));
+		}
+
+		let url = match path.is_empty() {
+			true => self.base_url.clone(),
+			false => format!("{}/{}", self.base_url.trim_end_matches('/'), path.trim_start_matches('/')),
+		};
+
+		if url_origin(&url) != url_origin(&self.base_url) {
+			return Err(ApiError::BadRequest("CONNECTOR PATH CHANGES THE HOST".to_string()));
+		}
+
+		Ok(url)
+	}
+
+	/// Fields of the connector that may reference secrets of the vault as `{{secret.NAME}}`.
+	pub fn templates_mut(&mut self) -> Vec<&mut String> {
+		let mut templates = vec![&mut self.base_url];
+		templates.extend(self.headers.values_mut());
+		templates.extend(
+			self.authentication
+				.secrets_mut()
+				.into_iter()
+				.map(|(_, value)| value)
+		);
+
+		templates
+	}
+}
+
+/// Scheme and authority of a URL, lowercased, e.g. `https://api.example.com:8443`.
+fn url_origin(url: &str) -> Option<String> {
+	let (scheme, rest) = url.trim().split_once("://")?;
+	let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+
+	Some(format!("{scheme}://{}", &rest[..authority_end]).to_lowercase())
+}
+
 #[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
 pub enum Authentication {
 	None,
 	Basic(String, String), // username, password
 	JWT(JWTConfig),
 	Token(TokenConfig),
+	OAuth2(OAuth2Config),
+	/// AWS Signature Version 4, computed for every request
+	AwsSigV4(AwsSigV4Config),
+	/// HMAC signature of a canonical string, computed for every request
+	Hmac(HmacSigningConfig),
+}
+
+impl Authentication {
+	/// Secret fields of the authentication by name.
+	pub fn secrets_mut(&mut self) -> Vec<(&'static str, &mut String)> {
+		match self {
+			Authentication::None => vec![],
+			Authentication::Basic(_, password) => vec![("password", password)],
+			Authentication::JWT(jwt_config) =>
+				vec![("secret", &mut jwt_config.secret), ("secret_key", &mut jwt_config.secret_key)],
+			Authentication::Token(token_config) => vec![("token", &mut token_config.token)],
+			Authentication::OAuth2(oauth2_config) => {
+				let mut secrets = vec![("client_secret", &mut oauth2_config.client_secret)];
+
+				if let Some(refresh_token) = &mut oauth2_config.refresh_token {
+					secrets.push(("refresh_token", refresh_token));
+				}
+
+				secrets
+			}
+			Authentication::AwsSigV4(aws_config) => {
+				let mut secrets = vec![("secret_access_key", &mut aws_config.secret_access_key)];
+
+				if let Some(session_token) = &mut aws_config.session_token {
+					secrets.push(("session_token", session_token));
+				}
+
+				secrets
+			}
+			Authentication::Hmac(hmac_config) => vec![("secret", &mut hmac_config.secret)],
+		}
+	}
 }
 
 #[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
@@ -70,6 +231,68 @@ pub struct JWTConfig {
 }
 
 #[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct OAuth2Config {
+	pub token_url: String,
+	pub client_id: String,
+	pub client_secret: String,
+	pub scopes: Vec<String>,
+	/// Refresh token to use instead of the client credentials grant
+	pub refresh_token: Option<String>,
+	pub location: TokenLocation,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct AwsSigV4Config {
+	pub access_key_id: String,
+	pub secret_access_key: String,
+	/// e.g. `eu-west-1`
+	pub region: String,
+	/// e.g. `s3`, `execute-api`
+	pub service: String,
+	/// Session token of temporary credentials, sent as `X-Amz-Security-Token`
+	pub session_token: Option<String>,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct HmacSigningConfig {
+	pub algorithm: HmacAlgorithm,
+	pub secret: String,
+	/// Header carrying the signature
+	pub header_name: String,
+	/// Template of the signed string, with the placeholders `{{method}}`, `{{url}}`, `{{path}}`, `{{query}}`,
+	/// `{{body}}`, `{{body_sha256}}` and `{{timestamp}}` (seconds), e.g. `{{timestamp}}.{{body}}`
+	pub canonical_template: String,
+	pub encoding: DigestEncoding,
+	/// Prefix of the header value, e.g. `sha256=`
+	pub prefix: Option<String>,
+	/// Header carrying the timestamp used in the signature
+	pub timestamp_header: Option<String>,
+}
+
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum HmacAlgorithm {
+	SHA256,
+	SHA384,
+	SHA512,
+}
+
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum DigestEncoding {
+	Hex,
+	Base64,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct JwtPreview {
+	// Token with a masked signature
+	pub token: String,
+	// Stringified JSON
+	pub header: String,
+	// Stringified JSON
+	pub claims: String,
+}
+
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum SignatureMethod {
 	HS256,
 	HS384,
@@ -100,3 +323,134 @@ pub struct TestConnection {
 	pub method: HttpRequestMethod,
 	pub error: Option<(String, String)>, // (error_field, error_value)
 }
+
+impl TestConnection {
+	/// Evaluate the response of a test request. Responses outside the 2xx range fail, as do JSON responses where
+	/// the error field holds the error value.
+	///
+	/// # Arguments
+	/// - `status` - HTTP status
+	/// - `body` - Response body
+	///
+	/// # Returns
+	/// - `(bool, Option<String>)` - Whether the test passed, and why not
+	pub fn evaluate(&self, status: u16, body: &str) -> (bool, Option<String>) {
+		if !(200..300).contains(&status) {
+			return (false, Some(format!("UNEXPECTED STATUS {status}")));
+		}
+
+		let Some((error_field, error_value)) = &self.error else {
+			return (true, None);
+		};
+
+		let value = serde_json
+			::from_str::<Value>(body)
+			.ok()
+			.and_then(|body| lookup_path(&body, error_field).cloned());
+
+		// The error value is compared with strings as-is and with other values as JSON
+		let is_error = match value {
+			Some(Value::String(value)) => &value == error_value,
+			Some(value) => &value.to_string() == error_value,
+			None => false,
+		};
+
+		match is_error {
+			true => (false, Some(format!("ERROR FIELD {error_field} IS {error_value}"))),
+			false => (true, None),
+		}
+	}
+}
+
+/// Node referencing a connector.
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ConnectorUsage {
+	pub circuit_id: u32,
+	pub node_id: u32,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub struct ConnectorTestResult {
+	pub passed: bool,
+	// HTTP status, 0 if the request failed before a response was received
+	pub status: u16,
+	pub latency_ms: u64,
+	// Start of the response body
+	pub body_excerpt: String,
+	pub error: Option<String>,
+	pub tested_at: u64,
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn test_connection(error: Option<(&str, &str)>) -> TestConnection {
+		TestConnection {
+			relative_url: "/status".to_string(),
+			method: HttpRequestMethod::GET,
+			error: error.map(|(field, value)| (field.to_string(), value.to_string())),
+		}
+	}
+
+	#[test]
+	fn evaluate_checks_the_status() {
+		let test_connection = test_connection(None);
+
+		assert_eq!(test_connection.evaluate(200, "OK"), (true, None));
+		assert_eq!(test_connection.evaluate(204, ""), (true, None));
+		assert_eq!(test_connection.evaluate(401, ""), (false, Some("UNEXPECTED STATUS 401".to_string())));
+		assert_eq!(test_connection.evaluate(302, ""), (false, Some("UNEXPECTED STATUS 302".to_string())));
+	}
+
+	#[test]
+	fn evaluate_checks_the_error_field() {
+		let status_error = test_connection(Some(("status", "error")));
+		let nested_error = test_connection(Some(("result.ok", "false")));
+
+		assert_eq!(
+			status_error.evaluate(200, r#"{ "status": "error" }"#),
+			(false, Some("ERROR FIELD status IS error".to_string()))
+		);
+		assert_eq!(status_error.evaluate(200, r#"{ "status": "ok" }"#), (true, None));
+		assert_eq!(status_error.evaluate(200, "not JSON"), (true, None));
+
+		// Values other than strings are compared as JSON
+		assert_eq!(
+			nested_error.evaluate(200, r#"{ "result": { "ok": false } }"#),
+			(false, Some("ERROR FIELD result.ok IS false".to_string()))
+		);
+		assert_eq!(nested_error.evaluate(200, r#"{ "result": { "ok": true } }"#), (true, None));
+	}
+
+	#[test]
+	fn url_joins_relative_paths() {
+		let http_connector = HttpConnector {
+			base_url: "https://api.example.com/v1/".to_string(),
+			headers: Headers::new(),
+			authentication: Authentication::None,
+			test_connection: None,
+		};
+
+		assert_eq!(http_connector.url("").unwrap(), "https://api.example.com/v1/");
+		assert_eq!(http_connector.url("/users?id=1").unwrap(), "https://api.example.com/v1/users?id=1");
+		assert_eq!(
+			http_connector.url("redirect?to=https://example.org").unwrap(),
+			"https://api.example.com/v1/redirect?to=https://example.org"
+		);
+	}
+
+	#[test]
+	fn url_rejects_paths_changing_the_host() {
+		let http_connector = HttpConnector {
+			base_url: "https://api.example.com".to_string(),
+			headers: Headers::new(),
+			authentication: Authentication::None,
+			test_connection: None,
+		};
+
+		for path in ["https://evil.example.com/", "//evil.example.com", "\\\\evil.example.com", "/a/http://evil"] {
+			assert!(matches!(http_connector.url(path), Err(ApiError::BadRequest(_))), "{path}");
+		}
+	}
+}
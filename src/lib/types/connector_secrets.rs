@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use candid::CandidType;
+use serde::Deserialize;
+use crate::impl_storable_for;
+use super::connector::Authentication;
+
+/// Value returned in place of a secret that is set. Posting it back keeps the stored secret.
+pub static SECRET_PLACEHOLDER: &str = "********";
+
+impl_storable_for!(ConnectorSecrets);
+/// Secrets of a connector, stored apart from the connector so they are never returned by queries.
+#[derive(CandidType, Clone, Debug, Default, Deserialize)]
+pub struct ConnectorSecrets {
+	/// Secret values by the name of their field, see `Authentication::secrets_mut`
+	pub values: HashMap<String, String>,
+}
+
+/// Whether a secret field of a connector holds a value.
+#[derive(CandidType, Clone, Debug, Deserialize, PartialEq, Eq)]
+pub struct SecretStatus {
+	pub name: String,
+	pub set: bool,
+}
+
+impl ConnectorSecrets {
+	/// Move the secrets out of an authentication, leaving empty values behind.
+	///
+	/// # Arguments
+	/// - `authentication` - Authentication to take the secrets from
+	///
+	/// # Returns
+	/// - `ConnectorSecrets` - Secrets that are set
+	pub fn take(authentication: &mut Authentication) -> Self {
+		let values = authentication
+			.secrets_mut()
+			.into_iter()
+			.filter(|(_, value)| !value.is_empty())
+			.map(|(name, value)| (name.to_string(), std::mem::take(value)))
+			.collect();
+
+		Self { values }
+	}
+
+	/// Move the secrets out of an edited authentication. Fields holding the placeholder keep their current secret,
+	/// other values replace it and empty values clear it.
+	///
+	/// # Arguments
+	/// - `authentication` - Edited authentication
+	/// - `current` - Secrets currently stored for the connector
+	///
+	/// # Returns
+	/// - `ConnectorSecrets` - Secrets to store
+	pub fn merge(authentication: &mut Authentication, current: &ConnectorSecrets) -> Self {
+		for (name, value) in authentication.secrets_mut() {
+			if value == SECRET_PLACEHOLDER {
+				*value = current.values.get(name).cloned().unwrap_or_default();
+			}
+		}
+
+		Self::take(authentication)
+	}
+
+	/// Put the secrets back into an authentication, for requests made with the connector.
+	///
+	/// # Arguments
+	/// - `authentication` - Authentication without secrets
+	pub fn restore(&self, authentication: &mut Authentication) {
+		for (name, value) in authentication.secrets_mut() {
+			if let Some(secret) = self.values.get(name) {
+				*value = secret.clone();
+			}
+		}
+	}
+
+	/// Replace the secrets of an authentication with the placeholder.
+	///
+	/// # Arguments
+	/// - `authentication` - Authentication without secrets
+	///
+	/// # Returns
+	/// - `Vec<SecretStatus>` - Secret fields of the authentication and whether they are set
+	pub fn mask(&self, authentication: &mut Authentication) -> Vec<SecretStatus> {
+		authentication
+			.secrets_mut()
+			.into_iter()
+			.map(|(name, value)| {
+				let set = self.values.contains_key(name);
+				*value = match set {
+					true => SECRET_PLACEHOLDER.to_string(),
+					false => String::default(),
+				};
+
+				SecretStatus { name: name.to_string(), set }
+			})
+			.collect()
+	}
+}
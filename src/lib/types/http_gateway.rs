@@ -0,0 +1,51 @@
+use candid::CandidType;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Request received from the HTTP gateway through the `http_request` and `http_request_update` methods.
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct HttpGatewayRequest {
+	pub method: String,
+	pub url: String,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+}
+
+/// Response returned to the HTTP gateway.
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct HttpGatewayResponse {
+	pub status_code: u16,
+	pub headers: Vec<(String, String)>,
+	pub body: Vec<u8>,
+	/// Asks the gateway to send the request again as an update call
+	pub upgrade: Option<bool>,
+}
+
+impl HttpGatewayResponse {
+	/// Response with a JSON body.
+	///
+	/// # Arguments
+	/// - `status_code` - Status code
+	/// - `body` - JSON body
+	///
+	/// # Returns
+	/// - `HttpGatewayResponse` - Response
+	pub fn json(status_code: u16, body: Value) -> Self {
+		Self {
+			status_code,
+			headers: vec![("Content-Type".to_string(), "application/json".to_string())],
+			body: body.to_string().into_bytes(),
+			upgrade: None,
+		}
+	}
+
+	/// Response asking the gateway to upgrade the request to an update call.
+	pub fn upgrade() -> Self {
+		Self {
+			status_code: 200,
+			headers: vec![],
+			body: vec![],
+			upgrade: Some(true),
+		}
+	}
+}
@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 use candid::{ CandidType, types::principal::Principal };
 use serde::{ Deserialize, Serialize };
-use crate::impl_storable_for;
-use super::headers::Headers;
+use crate::{ impl_storable_for, utils::{ jwt::is_public_key, vault::secret_references } };
+use super::{
+	candid_value::CandidValue,
+	connector::{ DigestEncoding, HmacAlgorithm, SignatureMethod },
+	headers::Headers,
+};
 
 impl_storable_for!(Node);
 #[derive(Clone, Debug, CandidType, Deserialize)]
@@ -44,7 +48,7 @@ impl Default for Node {
 	}
 }
 
-#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub enum NodeType {
 	/// Canister or HttpRequest will both act as the Input Node
 	Canister(Canister),
@@ -57,21 +61,264 @@ pub enum NodeType {
 	LookupHttpRequest(HttpRequest),
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+/// Header names whose values are considered secrets and removed on export.
+static SECRET_HEADERS: [&str; 5] = ["authorization", "proxy-authorization", "cookie", "x-api-key", "x-auth-token"];
+
+/// Parts of query parameter and body field names holding secrets
+static SECRET_NAMES: [&str; 8] = ["token", "secret", "password", "passwd", "api_key", "apikey", "signature", "auth"];
+
+impl NodeType {
+	/// Whether the node acts as the input node of the circuit.
+	pub fn is_input(&self) -> bool {
+		matches!(self, NodeType::Canister(_) | NodeType::HttpRequest(_))
+	}
+
+	/// Whether the node is a lookup node.
+	pub fn is_lookup(&self) -> bool {
+		matches!(self, NodeType::LookupCanister(_) | NodeType::LookupHttpRequest(_))
+	}
+
+	/// Connector referenced by the node, if any.
+	pub fn connector(&self) -> Option<&ConnectorReference> {
+		match self {
+			NodeType::HttpRequest(http_request) | NodeType::LookupHttpRequest(http_request) => {
+				http_request.connector.as_ref()
+			}
+			_ => None,
+		}
+	}
+
+	/// ID of the connector referenced by the node, if any. HTTP nodes reference HTTP connectors, lookup canister and
+	/// output nodes canister connectors.
+	pub fn connector_id(&self) -> Option<u32> {
+		match self {
+			NodeType::HttpRequest(http_request) | NodeType::LookupHttpRequest(http_request) => {
+				http_request.connector.as_ref().map(|connector| connector.connector_id)
+			}
+			NodeType::LookupCanister(lookup_canister) => lookup_canister.connector_id,
+			NodeType::Output(output) => output.connector_id,
+			NodeType::Canister(_) => None,
+		}
+	}
+
+	/// Mutable reference to the ID of the connector referenced by the node, if any.
+	pub fn connector_id_mut(&mut self) -> Option<&mut u32> {
+		match self {
+			NodeType::HttpRequest(http_request) | NodeType::LookupHttpRequest(http_request) => {
+				http_request.connector.as_mut().map(|connector| &mut connector.connector_id)
+			}
+			NodeType::LookupCanister(lookup_canister) => lookup_canister.connector_id.as_mut(),
+			NodeType::Output(output) => output.connector_id.as_mut(),
+			NodeType::Canister(_) => None,
+		}
+	}
+
+	/// Remove secrets (verification tokens, credential headers, URL credentials and secret query parameters and
+	/// body fields) from the node type. References to the vault are kept.
+	pub fn strip_secrets(&mut self) {
+		match self {
+			NodeType::Canister(canister) => {
+				match &mut canister.verification_type {
+					VerificationType::Token(token) => {
+						token.token = String::default();
+					}
+					// Vault references are kept, they do not reveal the secret
+					VerificationType::HmacSignature(HmacSignature { secret, .. })
+						if secret_references(secret).is_empty() => {
+						*secret = String::default();
+					}
+					// Public keys are kept, secrets are removed unless they reference the vault
+					VerificationType::Jwt(jwt_verification) => {
+						jwt_verification.keys.retain(|key| is_public_key(key) || !secret_references(key).is_empty());
+					}
+					_ => {}
+				}
+			}
+			NodeType::HttpRequest(http_request) | NodeType::LookupHttpRequest(http_request) => {
+				for (name, value) in http_request.headers.iter_mut() {
+					if SECRET_HEADERS.contains(&name.to_lowercase().as_str()) && secret_references(value).is_empty() {
+						*value = String::default();
+					}
+				}
+
+				http_request.url = strip_url_secrets(&http_request.url);
+
+				if let Some(connector) = http_request.connector.as_mut() {
+					connector.path = strip_url_secrets(&connector.path);
+				}
+
+				if let Some(request_body) = http_request.request_body.as_mut() {
+					*request_body = strip_body_secrets(request_body);
+				}
+			}
+			NodeType::Output(_) | NodeType::LookupCanister(_) => {}
+		}
+	}
+}
+
+/// Whether a query parameter or body field name looks like it holds a secret.
+fn is_secret_name(name: &str) -> bool {
+	let name = name.to_lowercase().replace('-', "_");
+
+	SECRET_NAMES.iter().any(|secret_name| name.contains(secret_name)) || name == "key" || name == "sig"
+}
+
+/// Remove the credentials (`user:password@`) and the values of secret query parameters from a URL or path.
+/// Values referencing the vault are kept.
+fn strip_url_secrets(url: &str) -> String {
+	let (url, fragment) = match url.split_once('#') {
+		Some((url, fragment)) => (url, Some(fragment)),
+		None => (url, None),
+	};
+
+	let (url, query) = match url.split_once('?') {
+		Some((url, query)) => (url, Some(query)),
+		None => (url, None),
+	};
+
+	let mut stripped_url = match url.split_once("://") {
+		Some((scheme, rest)) => {
+			let authority_end = rest.find('/').unwrap_or(rest.len());
+
+			match rest[..authority_end].rfind('@') {
+				Some(at) => format!("{scheme}://{}", &rest[at + 1..]),
+				None => url.to_string(),
+			}
+		}
+		None => url.to_string(),
+	};
+
+	if let Some(query) = query {
+		stripped_url = format!("{stripped_url}?{}", strip_form_secrets(query));
+	}
+
+	if let Some(fragment) = fragment {
+		stripped_url = format!("{stripped_url}#{fragment}");
+	}
+
+	stripped_url
+}
+
+/// Remove the values of secret parameters from a query string or form encoded body.
+fn strip_form_secrets(form: &str) -> String {
+	form.split('&')
+		.map(|parameter| {
+			match parameter.split_once('=') {
+				Some((name, value)) if is_secret_name(name) && secret_references(value).is_empty() => {
+					format!("{name}=")
+				}
+				_ => parameter.to_string(),
+			}
+		})
+		.collect::<Vec<String>>()
+		.join("&")
+}
+
+/// Remove the values of secret fields from a request body. JSON bodies (also templated ones that are not valid
+/// JSON) get the string values of their secret fields emptied, other bodies are treated as form encoded.
+fn strip_body_secrets(body: &str) -> String {
+	if !body.trim_start().starts_with(['{', '[']) {
+		return match body.contains('=') && !body.contains(char::is_whitespace) {
+			true => strip_form_secrets(body),
+			false => body.to_string(),
+		};
+	}
+
+	let mut stripped_body = String::with_capacity(body.len());
+	let mut rest = body;
+	let mut last_string: Option<&str> = None;
+
+	while let Some(start) = rest.find(['"', ':']) {
+		let is_string = rest.as_bytes()[start] == b'"';
+		stripped_body.push_str(&rest[..=start]);
+		rest = &rest[start + 1..];
+
+		if is_string {
+			let (string, remainder) = split_string(rest);
+			last_string = Some(string);
+			stripped_body.push_str(&rest[..rest.len() - remainder.len()]);
+			rest = remainder;
+			continue;
+		}
+
+		// Empty the string value following a secret key
+		let is_secret = last_string.take().is_some_and(is_secret_name);
+		let value = rest.trim_start();
+
+		if is_secret && value.starts_with('"') {
+			let (string, remainder) = split_string(&value[1..]);
+
+			if secret_references(string).is_empty() {
+				stripped_body.push_str(&rest[..=rest.len() - value.len()]);
+				stripped_body.push('"');
+				rest = remainder;
+			}
+		}
+	}
+
+	stripped_body.push_str(rest);
+	stripped_body
+}
+
+/// Split the content of a JSON string (after its opening quote) from the text following its closing quote.
+fn split_string(text: &str) -> (&str, &str) {
+	let mut escaped = false;
+
+	for (index, c) in text.char_indices() {
+		match c {
+			'\\' if !escaped => {
+				escaped = true;
+				continue;
+			}
+			'"' if !escaped => {
+				return (&text[..index], &text[index + 1..]);
+			}
+			_ => {}
+		}
+
+		escaped = false;
+	}
+
+	(text, "")
+}
+
+#[derive(CandidType, Debug, Clone, Serialize, Deserialize)]
+pub struct PostNode {
+	pub node_type: NodeType,
+	pub is_enabled: bool,
+	pub pins: Vec<Pin>,
+}
+
+impl From<Node> for PostNode {
+	fn from(node: Node) -> Self {
+		Self {
+			node_type: node.node_type,
+			is_enabled: node.is_enabled,
+			pins: node.pins,
+		}
+	}
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Output {
 	name: String,
 	description: Option<String>,
-	canister: Principal,
-	method: String,
+	pub canister: Principal,
+	/// Method called with the stringified JSON data as its only argument, or with the data converted to the
+	/// argument types of the method if a connector is referenced
+	pub method: String,
+	/// Canister connector providing the Candid interface. Methods taking several arguments get them from a JSON
+	/// array. The connector's canister is called instead of `canister`
+	pub connector_id: Option<u32>,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Transformer {
 	pub input: String,
 	pub output: String,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LookupCanister {
 	pub name: String,
 	pub description: Option<String>,
@@ -80,9 +327,13 @@ pub struct LookupCanister {
 	pub args: Vec<Arg>,
 	pub cycles: u128,
 	pub sample_data: String,
+	/// Canister connector providing the Candid interface. The arguments are converted to the types of the method and
+	/// the reply is decoded with the field names of the return types. The connector's canister is called instead of
+	/// `canister`
+	pub connector_id: Option<u32>,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LookupHttpRequest {
 	pub url: String,
 	pub method: HttpRequestMethod,
@@ -100,9 +351,11 @@ pub enum Arg {
 	Boolean(String),
 	Array(String),
 	Object(String),
+	/// Value of any Candid type
+	Candid(CandidValue),
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct HttpRequest {
 	name: String,
 	description: Option<String>,
@@ -112,6 +365,15 @@ pub struct HttpRequest {
 	pub request_body: Option<String>,
 	pub cycles: u128,
 	sample_data: String,
+	/// Connector providing the base URL, headers and authentication. The `url` is ignored if provided
+	pub connector: Option<ConnectorReference>,
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectorReference {
+	pub connector_id: u32,
+	/// Path relative to the base URL of the connector
+	pub path: String,
 }
 
 #[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -122,7 +384,19 @@ pub enum HttpRequestMethod {
 	DELETE,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+impl HttpRequestMethod {
+	/// Name of the method as sent in a request.
+	pub fn name(&self) -> &'static str {
+		match self {
+			HttpRequestMethod::GET => "GET",
+			HttpRequestMethod::POST => "POST",
+			HttpRequestMethod::PUT => "PUT",
+			HttpRequestMethod::DELETE => "DELETE",
+		}
+	}
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Canister {
 	name: String,
 	verification_type: VerificationType,
@@ -130,26 +404,85 @@ pub struct Canister {
 	sample_data: String,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+impl Canister {
+	/// Verification applied to incoming data.
+	pub fn verification_type(&self) -> &VerificationType {
+		&self.verification_type
+	}
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum VerificationType {
 	None,
 	Token(Token),
 	Whitelist(Vec<Principal>),
+	/// HMAC signature of the payload, as sent by e.g. GitHub, Stripe and Shopify
+	HmacSignature(HmacSignature),
+	/// Bearer JWT in the `Authorization` header. The verified claims are available as `{{auth.claims.*}}`
+	Jwt(JwtVerification),
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HmacSignature {
+	pub algorithm: HmacAlgorithm,
+	/// Signing secret, a reference to a secret of the vault, e.g. `{{secret.GITHUB_WEBHOOK_SECRET}}`
+	pub secret: String,
+	/// Header carrying the signature, e.g. `X-Hub-Signature-256`
+	pub header: String,
+	pub encoding: DigestEncoding,
+	/// Prefix of the header value, e.g. `sha256=`
+	pub prefix: Option<String>,
+	pub replay_protection: Option<ReplayProtection>,
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JwtVerification {
+	/// Algorithms a token may be signed with
+	pub algorithms: Vec<SignatureMethod>,
+	/// PEM public keys for RS* and ES*, secrets for HS*. Secrets should be a reference to a secret of the vault
+	pub keys: Vec<String>,
+	/// Expected `iss` claim
+	pub issuer: Option<String>,
+	/// Expected `aud` claim
+	pub audience: Option<String>,
+	/// Leeway for the `exp` and `nbf` claims
+	pub clock_skew_seconds: u64,
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayProtection {
+	/// Header carrying the time the payload was signed, in seconds
+	pub timestamp_header: String,
+	/// Maximum difference between the timestamp and the time of arrival
+	pub tolerance_seconds: u64,
+	/// Template of the signed payload, with the placeholders `{{timestamp}}` and `{{body}}`,
+	/// e.g. `{{timestamp}}.{{body}}`
+	pub signed_payload: String,
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Token {
-	token: String,
-	field: String,
+	/// Expected token, may be a reference to a secret of the vault
+	pub token: String,
+	/// Field of the input data carrying the token
+	pub field: String,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Pin {
+	/// Assigned by the node canister when the pin is added to a node
+	pub id: Option<u32>,
 	pub pin_type: PinType,
+	/// Execution order of the pin within the node
 	pub order: u32,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct PostPin {
+	pub pin_type: PinType,
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum PinType {
 	/// You can use this Pin to apply JS logic to the data within a Node prior starting the Node.
 	PrePin(CustomPinLogic),
@@ -166,48 +499,68 @@ pub enum PinType {
 	LookupFilterPin(FilterPin),
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+impl PinType {
+	/// Whether the pin can only be used once per node. Lookup pins merge or filter the single lookup response.
+	pub fn is_unique(&self) -> bool {
+		matches!(self, PinType::LookupTransformPin(_) | PinType::LookupFilterPin(_))
+	}
+
+	/// Name of the pin type variant.
+	pub fn name(&self) -> &'static str {
+		match self {
+			PinType::PrePin(_) => "PrePin",
+			PinType::PostPin(_) => "PostPin",
+			PinType::PreMapperPin(_) => "PreMapperPin",
+			PinType::PostMapperPin(_) => "PostMapperPin",
+			PinType::FilterPin(_) => "FilterPin",
+			PinType::LookupTransformPin(_) => "LookupTransformPin",
+			PinType::LookupFilterPin(_) => "LookupFilterPin",
+		}
+	}
+}
+
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct CustomPinLogic {
 	function: Option<String>,
 	script: Option<String>,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MapperPin {
 	// Input and Output
-	fields: Vec<(String, String)>,
-	sample_data: String,
+	pub fields: Vec<(String, String)>,
+	pub sample_data: String,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct FilterPin {
-	rules: Vec<Rule>,
-	condition: Condition,
-	condition_group: Option<ConditionGroup>,
-	sample_data: String,
+	pub rules: Vec<Rule>,
+	pub condition: Condition,
+	pub condition_group: Option<ConditionGroup>,
+	pub sample_data: String,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Rule {
-	field: String,
-	operator: Operator,
-	value: String,
-	operand: Operand,
+	pub field: String,
+	pub operator: Operator,
+	pub value: String,
+	pub operand: Operand,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Condition {
 	Not,
 	Is,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ConditionGroup {
 	And,
 	Or,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Operator {
 	Equal,
 	NotEqual,
@@ -218,19 +571,19 @@ pub enum Operator {
 	Contains,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Operand {
-	operand_type: OperandType,
-	data_type: DataType,
+	pub operand_type: OperandType,
+	pub data_type: DataType,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OperandType {
 	Value,
 	Field,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DataType {
 	String,
 	Number,
@@ -239,18 +592,26 @@ pub enum DataType {
 	Boolean,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LookupTransformPin {
-	input: String,
-	output: String,
+	/// Path of the lookup reply to merge, the whole reply if empty
+	pub input: String,
+	/// Path of the data the reply is merged at
+	pub output: String,
 }
 
-#[derive(CandidType, Debug, Clone, PartialEq, Eq, Deserialize)]
+#[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LookupCanisterPreview {
 	pub canister: Principal,
 	pub method: String,
 	pub args: Vec<PreviewArg>,
 	pub cycles: u128,
+	/// Canister connector providing the Candid interface. The arguments are converted to the types of the method and
+	/// the reply is decoded with the field names of the return types. The `canister` is ignored if provided
+	pub connector_id: Option<u32>,
+	/// Lookup canister node previewed. Its lookup pins are applied to the reply and the result is stored as its
+	/// sample data
+	pub node_id: Option<u32>,
 }
 
 #[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -262,13 +623,21 @@ pub enum PreviewArg {
 	Boolean(bool),
 	Array(Vec<Arg>),
 	Object(HashMap<String, Arg>),
+	/// Value of any Candid type. Previews with such arguments call the canister directly
+	Candid(CandidValue),
 }
 
 #[derive(CandidType, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct LookupHttpRequestPreview {
+	/// Must not reference secrets of the vault, unless a node is previewed
 	pub url: String,
 	pub method: HttpRequestMethod,
 	pub headers: Headers,
 	pub request_body: Option<String>,
 	pub cycles: u128,
+	/// Only allowed when previewing a node
+	pub connector: Option<ConnectorReference>,
+	/// Lookup HTTP request node previewed. Its stored request is sent with the secrets and connector it references,
+	/// the other fields are ignored
+	pub node_id: Option<u32>,
 }
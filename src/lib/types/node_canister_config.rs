@@ -0,0 +1,22 @@
+use candid::{ CandidType, Principal };
+use serde::Deserialize;
+use crate::impl_storable_for;
+
+impl_storable_for!(NodeCanisterConfig);
+/// Init args and configuration of a node canister.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct NodeCanisterConfig {
+	/// Principal of the user owning the node canister
+	pub owner: Principal,
+	/// Principal of the main canister that created the node canister
+	pub main_canister: Principal,
+}
+
+impl Default for NodeCanisterConfig {
+	fn default() -> Self {
+		Self {
+			owner: Principal::anonymous(),
+			main_canister: Principal::anonymous(),
+		}
+	}
+}
@@ -0,0 +1,17 @@
+use candid::CandidType;
+use serde::Deserialize;
+use crate::impl_storable_for;
+
+impl_storable_for!(OAuth2Token);
+/// Access token fetched from the token endpoint of an OAuth2 connector.
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct OAuth2Token {
+	pub access_token: String,
+	/// Latest refresh token, token endpoints may rotate it on every refresh
+	pub refresh_token: Option<String>,
+	/// Expiry in seconds, `None` if the token endpoint did not report one
+	pub expires_at: Option<u64>,
+	/// Fingerprint of the configuration the token was fetched with
+	pub config_fingerprint: String,
+	pub created_at: u64,
+}
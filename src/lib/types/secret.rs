@@ -0,0 +1,84 @@
+use candid::{ CandidType, Deserialize, Principal };
+use crate::impl_storable_for;
+
+impl_storable_for!(Secret);
+/// Secret of the vault. Values are never returned and are obfuscated at rest with a key derived from the master key
+/// of the main canister. The master key is kept in the same stable memory, so this does not protect the values from
+/// anyone able to read the canister's memory, e.g. the node providers of its subnet.
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct Secret {
+	pub name: String,
+	// Workspace owning the secret, if not owned personally
+	pub workspace_id: Option<u32>,
+	// Oldest first, the last version is the current value
+	pub versions: Vec<SecretVersion>,
+	pub created_at: u64,
+	pub updated_at: u64,
+}
+
+#[derive(CandidType, Clone, Debug, Deserialize)]
+pub struct SecretVersion {
+	pub version: u32,
+	pub nonce: Vec<u8>,
+	pub ciphertext: Vec<u8>,
+	pub created_by: Principal,
+	pub created_at: u64,
+}
+
+impl Secret {
+	/// Current version of the secret.
+	pub fn current(&self) -> Option<&SecretVersion> {
+		self.versions.last()
+	}
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct PostSecret {
+	pub name: String,
+	pub value: String,
+}
+
+/// Secret without its values.
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct SecretInfo {
+	pub name: String,
+	pub workspace_id: Option<u32>,
+	pub version: u32,
+	pub versions: Vec<SecretVersionInfo>,
+	pub created_at: u64,
+	pub updated_at: u64,
+}
+
+#[derive(CandidType, Debug, Clone, Deserialize)]
+pub struct SecretVersionInfo {
+	pub version: u32,
+	pub created_by: Principal,
+	pub created_at: u64,
+}
+
+impl From<&Secret> for SecretInfo {
+	fn from(secret: &Secret) -> Self {
+		Self {
+			name: secret.name.clone(),
+			workspace_id: secret.workspace_id,
+			version: secret.current().map(|version| version.version).unwrap_or_default(),
+			versions: secret.versions
+				.iter()
+				.map(|version| SecretVersionInfo {
+					version: version.version,
+					created_by: version.created_by,
+					created_at: version.created_at,
+				})
+				.collect(),
+			created_at: secret.created_at,
+			updated_at: secret.updated_at,
+		}
+	}
+}
+
+/// Resource referencing a secret.
+#[derive(CandidType, Debug, Clone, Deserialize, PartialEq, Eq)]
+pub enum SecretUsage {
+	Connector(u32), // connector_id
+	Node((u32, u32)), // (circuit_id, node_id)
+}
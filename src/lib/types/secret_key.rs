@@ -0,0 +1,11 @@
+use candid::CandidType;
+use serde::{ Deserialize, Serialize };
+use crate::impl_storable_for;
+
+impl_storable_for!(SecretKey);
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SecretKey {
+	// Principal of the user, or the key owner of the workspace owning the secret
+	pub owner: String,
+	pub name: String,
+}
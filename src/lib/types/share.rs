@@ -0,0 +1,47 @@
+use candid::{ CandidType, Principal };
+use serde::{ Deserialize, Serialize };
+use crate::impl_storable_for;
+use super::{ circuit::Circuit, connector::Connector };
+
+impl_storable_for!(Share);
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct Share {
+	pub resource: ShareResource,
+	pub resource_id: u32,
+	pub principal: Principal,
+	pub role: Role,
+	pub invited_by: Principal,
+	pub created_at: u64,
+	pub updated_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ShareResource {
+	Circuit,
+	Connector,
+}
+
+/// Roles are ordered, every role includes the permissions of the roles before it.
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+	/// Can view the item
+	Viewer,
+	/// Can view and edit the item
+	Editor,
+	/// Can view, edit and share the item
+	Admin,
+	/// Owner of the item. Cannot be granted
+	Owner,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct UserCircuit {
+	pub circuit: Circuit,
+	pub role: Role,
+}
+
+#[derive(Clone, CandidType, Deserialize)]
+pub struct UserConnector {
+	pub connector: Connector,
+	pub role: Role,
+}
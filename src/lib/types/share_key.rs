@@ -0,0 +1,12 @@
+use candid::CandidType;
+use serde::{ Deserialize, Serialize };
+use crate::impl_storable_for;
+use super::share::ShareResource;
+
+impl_storable_for!(ShareKey);
+#[derive(Clone, Debug, CandidType, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ShareKey {
+	pub resource: ShareResource,
+	pub resource_id: u32,
+	pub principal: String,
+}
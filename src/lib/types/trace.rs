@@ -9,6 +9,8 @@ pub struct Trace {
 	pub user_id: Principal,
 	pub node_id: u32,
 	pub circuit_id: u32,
+	// Published revision of the circuit that was executed
+	pub revision: Option<u32>,
 	pub status: TraceStatus,
 	pub errors: Vec<TraceError>,
 	// Stringified JSON
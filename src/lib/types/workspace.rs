@@ -0,0 +1,39 @@
+use candid::{ CandidType, Principal };
+use serde::Deserialize;
+use crate::impl_storable_for;
+use super::share::Role;
+
+impl_storable_for!(Workspace);
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct Workspace {
+	pub id: u32,
+	pub name: String,
+	pub members: Vec<WorkspaceMember>,
+	// Shared cycles balance used to fund the node canisters of the workspace's circuits
+	pub cycles_balance: u128,
+	pub created_at: u64,
+	pub updated_at: u64,
+}
+
+impl Workspace {
+	/// Get the role of a principal in the workspace.
+	pub fn get_member_role(&self, principal: Principal) -> Option<Role> {
+		self.members
+			.iter()
+			.find(|member| member.principal == principal)
+			.map(|member| member.role.clone())
+	}
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct WorkspaceMember {
+	pub principal: Principal,
+	pub role: Role,
+	pub added_by: Principal,
+	pub added_at: u64,
+}
+
+#[derive(Clone, Debug, CandidType, Deserialize)]
+pub struct PostWorkspace {
+	pub name: String,
+}
@@ -0,0 +1,714 @@
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use candid::{ types::{ internal::TypeInner, Label, Type }, IDLArgs, TypeEnv };
+use candid_parser::parse_idl_value;
+use serde_json::{ Map, Value };
+use crate::types::{
+	api_error::ApiError,
+	candid_value::{ CandidField, CandidValue },
+	node::{ Arg, PreviewArg },
+};
+
+/// Convert JSON to a Candid value of the given type.
+///
+/// Numbers may be given as JSON numbers or as strings, big numbers should be strings to keep their precision.
+/// Principals are text, blobs are an array of bytes or a base64 string. Optional values are `null` or the value.
+/// Records are objects, tuples may also be arrays. Variants are an object with a single tag, or the tag as a string
+/// for tags without a value.
+///
+/// # Arguments
+/// - `value` - JSON value
+/// - `env` - Named types of the Candid interface
+/// - `candid_type` - Type to convert to
+/// - `path` - Path of the value, used in error messages, e.g. `args.0`
+///
+/// # Returns
+/// - `CandidValue` - Candid value
+pub fn candid_value_from_json(
+	value: &Value,
+	env: &TypeEnv,
+	candid_type: &Type,
+	path: &str
+) -> Result<CandidValue, ApiError> {
+	let candid_type = env.trace_type(candid_type).map_err(|err| type_error(path, &err.to_string()))?;
+
+	match (candid_type.as_ref(), value) {
+		(TypeInner::Null, Value::Null) => Ok(CandidValue::Null),
+		(TypeInner::Reserved, _) => Ok(CandidValue::Reserved),
+		(TypeInner::Bool, Value::Bool(value)) => Ok(CandidValue::Bool(*value)),
+		(TypeInner::Bool, Value::String(text)) => {
+			match text.trim() {
+				"true" => Ok(CandidValue::Bool(true)),
+				"false" => Ok(CandidValue::Bool(false)),
+				_ => Err(mismatch(path, &candid_type, value)),
+			}
+		}
+		(TypeInner::Nat | TypeInner::Int, Value::Number(_) | Value::String(_)) => {
+			let number = number_text(value);
+			let candid_value = match candid_type.as_ref() {
+				TypeInner::Nat => CandidValue::Nat(number),
+				_ => CandidValue::Int(number),
+			};
+
+			// Validate the number
+			candid_value.to_idl_value().map_err(|_| mismatch(path, &candid_type, value))?;
+
+			Ok(candid_value)
+		}
+		(TypeInner::Nat8, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Nat8)
+		}
+		(TypeInner::Nat16, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Nat16)
+		}
+		(TypeInner::Nat32, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Nat32)
+		}
+		(TypeInner::Nat64, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Nat64)
+		}
+		(TypeInner::Int8, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Int8)
+		}
+		(TypeInner::Int16, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Int16)
+		}
+		(TypeInner::Int32, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Int32)
+		}
+		(TypeInner::Int64, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Int64)
+		}
+		(TypeInner::Float32, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Float32)
+		}
+		(TypeInner::Float64, Value::Number(_) | Value::String(_)) => {
+			parse_number(value, path, &candid_type, CandidValue::Float64)
+		}
+		(TypeInner::Text, Value::String(value)) => Ok(CandidValue::Text(value.clone())),
+		(TypeInner::Principal, Value::String(text)) => {
+			candid::Principal
+				::from_text(text.trim())
+				.map(CandidValue::Principal)
+				.map_err(|_| mismatch(path, &candid_type, value))
+		}
+		(TypeInner::Opt(_), Value::Null) => Ok(CandidValue::Opt(None)),
+		(TypeInner::Opt(inner_type), value) => {
+			let inner_value = candid_value_from_json(value, env, inner_type, path)?;
+
+			Ok(CandidValue::Opt(Some(Box::new(inner_value))))
+		}
+		(TypeInner::Vec(_), Value::String(text)) if candid_type.is_blob(env) => {
+			STANDARD
+				.decode(text.trim())
+				.map(CandidValue::Blob)
+				.map_err(|_| ApiError::BadRequest(format!("INVALID BASE64 BLOB AT {path}")))
+		}
+		(TypeInner::Vec(item_type), Value::Array(items)) => {
+			let values = items
+				.iter()
+				.enumerate()
+				.map(|(index, item)| candid_value_from_json(item, env, item_type, &format!("{path}.{index}")))
+				.collect::<Result<Vec<CandidValue>, ApiError>>()?;
+
+			match candid_type.is_blob(env) {
+				true => {
+					let bytes = values
+						.into_iter()
+						.map(|value| {
+							match value {
+								CandidValue::Nat8(byte) => byte,
+								_ => 0,
+							}
+						})
+						.collect();
+
+					Ok(CandidValue::Blob(bytes))
+				}
+				false => Ok(CandidValue::Vec(values)),
+			}
+		}
+		(TypeInner::Record(fields), Value::Object(object)) => {
+			let record_fields = fields
+				.iter()
+				.map(|field| {
+					let label = label_text(&field.id);
+					let field_path = format!("{path}.{label}");
+
+					let field_value = match object.get(&label) {
+						Some(field_value) => candid_value_from_json(field_value, env, &field.ty, &field_path)?,
+						None => missing_field(env, &field.ty, &field_path)?,
+					};
+
+					Ok(CandidField { label, value: field_value })
+				})
+				.collect::<Result<Vec<CandidField>, ApiError>>()?;
+
+			// Unknown fields are rejected, they are usually a typo in a field name
+			let unknown_field = object.keys().find(|key| !fields.iter().any(|field| &label_text(&field.id) == *key));
+			if let Some(unknown) = unknown_field {
+				return Err(ApiError::BadRequest(format!("UNKNOWN FIELD {unknown} AT {path}")));
+			}
+
+			Ok(CandidValue::Record(record_fields))
+		}
+		(TypeInner::Record(fields), Value::Array(items)) if is_tuple(fields) => {
+			if items.len() != fields.len() {
+				return Err(
+					ApiError::BadRequest(
+						format!("EXPECTED {} TUPLE ITEMS AT {path}, GOT {}", fields.len(), items.len())
+					)
+				);
+			}
+
+			let record_fields = fields
+				.iter()
+				.zip(items)
+				.map(|(field, item)| {
+					let label = label_text(&field.id);
+					let field_value = candid_value_from_json(item, env, &field.ty, &format!("{path}.{label}"))?;
+
+					Ok(CandidField { label, value: field_value })
+				})
+				.collect::<Result<Vec<CandidField>, ApiError>>()?;
+
+			Ok(CandidValue::Record(record_fields))
+		}
+		(TypeInner::Variant(fields), Value::String(tag)) => {
+			let field = fields
+				.iter()
+				.find(|field| &label_text(&field.id) == tag)
+				.ok_or(ApiError::BadRequest(format!("UNKNOWN VARIANT TAG {tag} AT {path}")))?;
+
+			let field_value = candid_value_from_json(&Value::Null, env, &field.ty, &format!("{path}.{tag}"))?;
+
+			Ok(CandidValue::Variant(Box::new(CandidField { label: tag.clone(), value: field_value })))
+		}
+		(TypeInner::Variant(fields), Value::Object(object)) if object.len() == 1 => {
+			let (tag, tag_value) = object.iter().next().unwrap();
+
+			let field = fields
+				.iter()
+				.find(|field| &label_text(&field.id) == tag)
+				.ok_or(ApiError::BadRequest(format!("UNKNOWN VARIANT TAG {tag} AT {path}")))?;
+
+			let field_value = candid_value_from_json(tag_value, env, &field.ty, &format!("{path}.{tag}"))?;
+
+			Ok(CandidValue::Variant(Box::new(CandidField { label: tag.clone(), value: field_value })))
+		}
+		(TypeInner::Empty | TypeInner::Func(_) | TypeInner::Service(_) | TypeInner::Class(_, _), _) => {
+			Err(ApiError::BadRequest(format!("UNSUPPORTED TYPE {candid_type} AT {path}")))
+		}
+		_ => Err(mismatch(path, &candid_type, value)),
+	}
+}
+
+/// Convert the text form of a value to a Candid value of the given type. Text is used as-is, numbers, booleans and
+/// principals are parsed, and other values are parsed as JSON, or as Candid text syntax if they are not JSON.
+///
+/// # Arguments
+/// - `text` - Text form of the value
+/// - `env` - Named types of the Candid interface
+/// - `candid_type` - Type to convert to
+/// - `path` - Path of the value, used in error messages
+///
+/// # Returns
+/// - `CandidValue` - Candid value
+pub fn candid_value_from_text(
+	text: &str,
+	env: &TypeEnv,
+	candid_type: &Type,
+	path: &str
+) -> Result<CandidValue, ApiError> {
+	let traced_type = env.trace_type(candid_type).map_err(|err| type_error(path, &err.to_string()))?;
+
+	match traced_type.as_ref() {
+		TypeInner::Text => Ok(CandidValue::Text(text.to_string())),
+		| TypeInner::Bool
+		| TypeInner::Nat
+		| TypeInner::Int
+		| TypeInner::Nat8
+		| TypeInner::Nat16
+		| TypeInner::Nat32
+		| TypeInner::Nat64
+		| TypeInner::Int8
+		| TypeInner::Int16
+		| TypeInner::Int32
+		| TypeInner::Int64
+		| TypeInner::Float32
+		| TypeInner::Float64
+		| TypeInner::Principal => candid_value_from_json(&Value::String(text.to_string()), env, &traced_type, path),
+		_ => {
+			if let Ok(value) = serde_json::from_str::<Value>(text) {
+				return candid_value_from_json(&value, env, &traced_type, path);
+			}
+
+			let value = parse_idl_value(text)
+				.and_then(|value| Ok(value.annotate_type(true, env, &traced_type)?))
+				.map_err(|err| ApiError::BadRequest(format!("INVALID VALUE AT {path}: {err}")))?;
+
+			Ok(CandidValue::from_idl_value(&value))
+		}
+	}
+}
+
+/// Convert JSON to a Candid value without knowing the type: integers become `int`, other numbers `float64`,
+/// arrays `vec` and objects `record`.
+///
+/// # Arguments
+/// - `value` - JSON value
+///
+/// # Returns
+/// - `CandidValue` - Candid value
+pub fn candid_value_from_untyped_json(value: &Value) -> CandidValue {
+	match value {
+		Value::Null => CandidValue::Null,
+		Value::Bool(value) => CandidValue::Bool(*value),
+		Value::Number(number) => {
+			match number.is_f64() {
+				true => CandidValue::Float64(number.as_f64().unwrap_or_default()),
+				false => CandidValue::Int(number.to_string()),
+			}
+		}
+		Value::String(value) => CandidValue::Text(value.clone()),
+		Value::Array(items) => CandidValue::Vec(items.iter().map(candid_value_from_untyped_json).collect()),
+		Value::Object(object) => CandidValue::Record(untyped_fields(object)),
+	}
+}
+
+/// Convert an argument of a lookup canister node to a Candid value, with the type of the parameter if known.
+///
+/// # Arguments
+/// - `arg` - Argument
+/// - `parameter` - Named types of the Candid interface and the type of the parameter
+/// - `path` - Path of the argument, used in error messages
+///
+/// # Returns
+/// - `CandidValue` - Candid value
+pub fn candid_value_from_arg(
+	arg: &Arg,
+	parameter: Option<(&TypeEnv, &Type)>,
+	path: &str
+) -> Result<CandidValue, ApiError> {
+	let text = match arg {
+		Arg::Candid(value) => {
+			return Ok(value.clone());
+		}
+		| Arg::String(text)
+		| Arg::Number(text)
+		| Arg::Principal(text)
+		| Arg::BigInt(text)
+		| Arg::Boolean(text)
+		| Arg::Array(text)
+		| Arg::Object(text) => text,
+	};
+
+	if let Some((env, candid_type)) = parameter {
+		return candid_value_from_text(text, env, candid_type, path);
+	}
+
+	let invalid = |kind: &str| ApiError::BadRequest(format!("INVALID {kind} AT {path}: {text}"));
+
+	match arg {
+		Arg::String(_) => Ok(CandidValue::Text(text.clone())),
+		Arg::Number(_) => text.trim().parse::<u32>().map(CandidValue::Nat32).map_err(|_| invalid("nat32")),
+		Arg::BigInt(_) => text.trim().parse::<u64>().map(CandidValue::Nat64).map_err(|_| invalid("nat64")),
+		Arg::Boolean(_) => text.trim().parse::<bool>().map(CandidValue::Bool).map_err(|_| invalid("bool")),
+		Arg::Principal(_) => {
+			candid::Principal
+				::from_text(text.trim())
+				.map(CandidValue::Principal)
+				.map_err(|_| invalid("principal"))
+		}
+		_ => {
+			serde_json
+				::from_str::<Value>(text)
+				.map(|value| candid_value_from_untyped_json(&value))
+				.map_err(|_| invalid("JSON"))
+		}
+	}
+}
+
+/// Convert an argument of a lookup canister preview to a Candid value. Without the type of the parameter the types
+/// the preview used so far are kept, e.g. `nat32` for numbers.
+///
+/// # Arguments
+/// - `arg` - Preview argument
+/// - `parameter` - Named types of the Candid interface and the type of the parameter
+/// - `path` - Path of the argument, used in error messages
+///
+/// # Returns
+/// - `CandidValue` - Candid value
+pub fn candid_value_from_preview_arg(
+	arg: &PreviewArg,
+	parameter: Option<(&TypeEnv, &Type)>,
+	path: &str
+) -> Result<CandidValue, ApiError> {
+	if let (Some((env, candid_type)), false) = (parameter, matches!(arg, PreviewArg::Candid(_))) {
+		return candid_value_from_json(&preview_arg_json(arg), env, candid_type, path);
+	}
+
+	Ok(match arg {
+		PreviewArg::Candid(value) => value.clone(),
+		PreviewArg::String(value) => CandidValue::Text(value.clone()),
+		PreviewArg::Number(value) => CandidValue::Nat32(*value),
+		PreviewArg::Principal(value) => CandidValue::Principal(*value),
+		PreviewArg::BigInt(value) => CandidValue::Nat64(*value),
+		PreviewArg::Boolean(value) => CandidValue::Bool(*value),
+		PreviewArg::Array(items) => {
+			CandidValue::Vec(
+				items
+					.iter()
+					.enumerate()
+					.map(|(index, item)| candid_value_from_arg(item, None, &format!("{path}.{index}")))
+					.collect::<Result<Vec<CandidValue>, ApiError>>()?
+			)
+		}
+		PreviewArg::Object(object) => {
+			let mut fields = object
+				.iter()
+				.map(|(label, item)| {
+					let value = candid_value_from_arg(item, None, &format!("{path}.{label}"))?;
+
+					Ok(CandidField { label: label.clone(), value })
+				})
+				.collect::<Result<Vec<CandidField>, ApiError>>()?;
+			fields.sort_by(|a, b| a.label.cmp(&b.label));
+
+			CandidValue::Record(fields)
+		}
+	})
+}
+
+/// Convert JSON data to the arguments of a method. Methods taking a single argument get the data as-is, methods
+/// taking several arguments get them from an array with one item per argument.
+///
+/// # Arguments
+/// - `data` - JSON data
+/// - `env` - Named types of the Candid interface
+/// - `arg_types` - Argument types of the method
+///
+/// # Returns
+/// - `Vec<CandidValue>` - Argument values
+pub fn candid_args_from_json(data: &Value, env: &TypeEnv, arg_types: &[Type]) -> Result<Vec<CandidValue>, ApiError> {
+	let items = match (arg_types.len(), data) {
+		(0, _) => vec![],
+		(1, _) => vec![data],
+		(length, Value::Array(items)) if items.len() == length => items.iter().collect(),
+		(length, _) => {
+			return Err(ApiError::BadRequest(format!("EXPECTED AN ARRAY OF {length} ARGUMENTS")));
+		}
+	};
+
+	items
+		.into_iter()
+		.zip(arg_types)
+		.enumerate()
+		.map(|(index, (item, arg_type))| candid_value_from_json(item, env, arg_type, &format!("args.{index}")))
+		.collect()
+}
+
+/// Encode Candid values as the arguments of a call. Values are checked against the argument types if known.
+///
+/// # Arguments
+/// - `values` - Argument values
+/// - `arg_types` - Named types of the Candid interface and the argument types of the method
+///
+/// # Returns
+/// - `Vec<u8>` - Candid encoded arguments
+pub fn encode_candid_args(values: &[CandidValue], arg_types: Option<(&TypeEnv, &[Type])>) -> Result<Vec<u8>, ApiError> {
+	let args = IDLArgs::new(
+		&values
+			.iter()
+			.map(|value| value.to_idl_value())
+			.collect::<Result<Vec<_>, ApiError>>()?
+	);
+
+	let bytes = match arg_types {
+		Some((env, types)) => {
+			if values.len() < types.len() {
+				return Err(ApiError::BadRequest(format!("EXPECTED {} ARGUMENTS, GOT {}", types.len(), values.len())));
+			}
+
+			args.to_bytes_with_types(env, types)
+		}
+		None => args.to_bytes(),
+	};
+
+	bytes.map_err(|err| ApiError::BadRequest(format!("FAILED TO ENCODE ARGUMENTS: {err}")))
+}
+
+/// Convert a Candid value to JSON. `nat` and `int` become strings to keep their precision, principals become text,
+/// blobs a base64 string, variants an object with a single tag and tuples an array. Fields of values decoded without
+/// a type are labeled by their hash.
+///
+/// # Arguments
+/// - `value` - Candid value
+///
+/// # Returns
+/// - `Value` - JSON value
+pub fn candid_value_to_json(value: &CandidValue) -> Value {
+	match value {
+		CandidValue::Null | CandidValue::Reserved | CandidValue::Opt(None) => Value::Null,
+		CandidValue::Bool(value) => Value::Bool(*value),
+		CandidValue::Nat(value) | CandidValue::Int(value) => Value::String(value.clone()),
+		CandidValue::Nat8(value) => Value::from(*value),
+		CandidValue::Nat16(value) => Value::from(*value),
+		CandidValue::Nat32(value) => Value::from(*value),
+		CandidValue::Nat64(value) => Value::from(*value),
+		CandidValue::Int8(value) => Value::from(*value),
+		CandidValue::Int16(value) => Value::from(*value),
+		CandidValue::Int32(value) => Value::from(*value),
+		CandidValue::Int64(value) => Value::from(*value),
+		// NaN and infinity have no JSON form and become null
+		CandidValue::Float32(value) => Value::from(*value),
+		CandidValue::Float64(value) => Value::from(*value),
+		CandidValue::Text(value) => Value::String(value.clone()),
+		CandidValue::Principal(value) => Value::String(value.to_text()),
+		CandidValue::Blob(value) => Value::String(STANDARD.encode(value)),
+		CandidValue::Opt(Some(value)) => candid_value_to_json(value),
+		CandidValue::Vec(values) => Value::Array(values.iter().map(candid_value_to_json).collect()),
+		CandidValue::Record(fields) => {
+			let is_tuple =
+				!fields.is_empty() && fields.iter().enumerate().all(|(index, field)| field.label == index.to_string());
+
+			match is_tuple {
+				true => Value::Array(fields.iter().map(|field| candid_value_to_json(&field.value)).collect()),
+				false => {
+					Value::Object(
+						fields
+							.iter()
+							.map(|field| (field.label.clone(), candid_value_to_json(&field.value)))
+							.collect()
+					)
+				}
+			}
+		}
+		CandidValue::Variant(field) => {
+			let mut object = Map::new();
+			object.insert(field.label.clone(), candid_value_to_json(&field.value));
+
+			Value::Object(object)
+		}
+	}
+}
+
+/// Decode the Candid reply of a call to JSON. With the return types of the method, record and variant fields get
+/// their names instead of hashes. A single return value is returned as-is, multiple values as an array.
+///
+/// # Arguments
+/// - `reply` - Candid encoded reply
+/// - `return_types` - Named types of the Candid interface and the return types of the method
+///
+/// # Returns
+/// - `Value` - JSON value
+pub fn decode_candid_reply(reply: &[u8], return_types: Option<(&TypeEnv, &[Type])>) -> Result<Value, ApiError> {
+	let args = match return_types {
+		Some((env, types)) => IDLArgs::from_bytes_with_types(reply, env, types),
+		None => IDLArgs::from_bytes(reply),
+	}.map_err(|err| ApiError::BadRequest(format!("FAILED TO DECODE REPLY: {err}")))?;
+
+	let mut values = args.args
+		.iter()
+		.map(|value| candid_value_to_json(&CandidValue::from_idl_value(value)))
+		.collect::<Vec<Value>>();
+
+	Ok(match values.len() {
+		0 => Value::Null,
+		1 => values.remove(0),
+		_ => Value::Array(values),
+	})
+}
+
+fn preview_arg_json(arg: &PreviewArg) -> Value {
+	match arg {
+		PreviewArg::Candid(value) => candid_value_to_json(value),
+		PreviewArg::String(value) => Value::String(value.clone()),
+		PreviewArg::Number(value) => Value::from(*value),
+		PreviewArg::Principal(value) => Value::String(value.to_text()),
+		PreviewArg::BigInt(value) => Value::from(*value),
+		PreviewArg::Boolean(value) => Value::Bool(*value),
+		PreviewArg::Array(items) => Value::Array(items.iter().map(arg_json).collect()),
+		PreviewArg::Object(object) => {
+			Value::Object(
+				object
+					.iter()
+					.map(|(label, item)| (label.clone(), arg_json(item)))
+					.collect()
+			)
+		}
+	}
+}
+
+fn arg_json(arg: &Arg) -> Value {
+	match arg {
+		Arg::Candid(value) => candid_value_to_json(value),
+		Arg::Array(text) | Arg::Object(text) => {
+			serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.clone()))
+		}
+		// Typed conversion parses numbers, booleans and principals from text
+		| Arg::String(text)
+		| Arg::Number(text)
+		| Arg::Principal(text)
+		| Arg::BigInt(text)
+		| Arg::Boolean(text) => Value::String(text.clone()),
+	}
+}
+
+fn parse_number<T: std::str::FromStr>(
+	value: &Value,
+	path: &str,
+	candid_type: &Type,
+	to_value: fn(T) -> CandidValue
+) -> Result<CandidValue, ApiError> {
+	number_text(value)
+		.parse::<T>()
+		.map(to_value)
+		.map_err(|_| mismatch(path, candid_type, value))
+}
+
+fn number_text(value: &Value) -> String {
+	match value {
+		Value::String(text) => text.trim().replace('_', ""),
+		value => value.to_string(),
+	}
+}
+
+fn missing_field(env: &TypeEnv, field_type: &Type, path: &str) -> Result<CandidValue, ApiError> {
+	let field_type = env.trace_type(field_type).map_err(|err| type_error(path, &err.to_string()))?;
+
+	match field_type.as_ref() {
+		TypeInner::Opt(_) => Ok(CandidValue::Opt(None)),
+		TypeInner::Null => Ok(CandidValue::Null),
+		TypeInner::Reserved => Ok(CandidValue::Reserved),
+		_ => Err(ApiError::BadRequest(format!("MISSING FIELD AT {path}: EXPECTED {field_type}"))),
+	}
+}
+
+fn untyped_fields(object: &Map<String, Value>) -> Vec<CandidField> {
+	object
+		.iter()
+		.map(|(label, value)| CandidField { label: label.clone(), value: candid_value_from_untyped_json(value) })
+		.collect()
+}
+
+fn is_tuple(fields: &[candid::types::Field]) -> bool {
+	fields
+		.iter()
+		.enumerate()
+		.all(|(index, field)| field.id.get_id() == (index as u32))
+}
+
+fn label_text(label: &Label) -> String {
+	match label {
+		Label::Named(name) => name.clone(),
+		Label::Id(id) | Label::Unnamed(id) => id.to_string(),
+	}
+}
+
+fn mismatch(path: &str, candid_type: &Type, value: &Value) -> ApiError {
+	ApiError::BadRequest(format!("TYPE MISMATCH AT {path}: EXPECTED {candid_type}, GOT {value}"))
+}
+
+fn type_error(path: &str, message: &str) -> ApiError {
+	ApiError::BadRequest(format!("INVALID TYPE AT {path}: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+	use crate::utils::candid_interface::method_signature;
+	use super::*;
+
+	static CANDID: &str = r#"
+		type User = record { id : nat; name : text; tags : vec text; active : opt bool };
+		service : { get_user : (nat64, User) -> (variant { ok : User; err : text }) query }
+	"#;
+
+	#[test]
+	fn encode_candid_args_known_bytes() {
+		let bytes = encode_candid_args(&[CandidValue::Text("hi".to_string())], None).unwrap();
+
+		// Magic number, no type table, one argument of type text, then the length and bytes of the text
+		assert_eq!(bytes, b"DIDL\x00\x01\x71\x02hi");
+	}
+
+	#[test]
+	fn candid_value_from_json_converts_to_argument_types() {
+		let (env, arg_types, _) = method_signature(CANDID, "get_user").unwrap();
+
+		let user = json!({ "id": "12345678901234567890", "name": "Ada", "tags": ["admin"], "active": null });
+
+		assert_eq!(candid_value_from_json(&json!(7), &env, &arg_types[0], "args.0").unwrap(), CandidValue::Nat64(7));
+		assert_eq!(
+			candid_value_from_json(&user, &env, &arg_types[1], "args.1").unwrap(),
+			// Fields are in the order of their label hashes
+			CandidValue::Record(
+				vec![
+					CandidField {
+						label: "id".to_string(),
+						value: CandidValue::Nat("12345678901234567890".to_string()),
+					},
+					CandidField { label: "active".to_string(), value: CandidValue::Opt(None) },
+					CandidField { label: "name".to_string(), value: CandidValue::Text("Ada".to_string()) },
+					CandidField {
+						label: "tags".to_string(),
+						value: CandidValue::Vec(vec![CandidValue::Text("admin".to_string())]),
+					}
+				]
+			)
+		);
+
+		assert!(candid_value_from_json(&json!("seven"), &env, &arg_types[0], "args.0").is_err());
+		assert!(candid_value_from_json(&json!({ "id": 1 }), &env, &arg_types[1], "args.1").is_err());
+	}
+
+	#[test]
+	fn candid_args_from_json_spreads_arrays_over_arguments() {
+		let (env, arg_types, _) = method_signature(CANDID, "get_user").unwrap();
+
+		let user = json!({ "id": "1", "name": "Ada", "tags": [], "active": true });
+		let values = candid_args_from_json(&json!([7, user]), &env, &arg_types).unwrap();
+
+		assert_eq!(values.len(), 2);
+		assert_eq!(values[0], CandidValue::Nat64(7));
+		assert!(matches!(candid_args_from_json(&json!([7]), &env, &arg_types), Err(ApiError::BadRequest(_))));
+		assert!(matches!(candid_args_from_json(&user, &env, &arg_types), Err(ApiError::BadRequest(_))));
+
+		// A single argument gets the data as-is, also arrays
+		let (env, arg_types, _) = method_signature("service : { notify : (vec text) -> () }", "notify").unwrap();
+
+		assert_eq!(
+			candid_args_from_json(&json!(["admin"]), &env, &arg_types).unwrap(),
+			vec![CandidValue::Vec(vec![CandidValue::Text("admin".to_string())])]
+		);
+	}
+
+	#[test]
+	fn decode_candid_reply_round_trip() {
+		let (env, _, return_types) = method_signature(CANDID, "get_user").unwrap();
+
+		let reply = json!({ "ok": { "id": "42", "name": "Ada", "tags": ["a", "b"], "active": true } });
+
+		let value = candid_value_from_json(&reply, &env, &return_types[0], "reply").unwrap();
+		let bytes = encode_candid_args(&[value], Some((&env, return_types.as_slice()))).unwrap();
+
+		assert_eq!(decode_candid_reply(&bytes, Some((&env, return_types.as_slice()))).unwrap(), reply);
+	}
+
+	#[test]
+	fn candid_value_from_arg_without_types() {
+		for (arg, expected) in [
+			(Arg::String("hi".to_string()), CandidValue::Text("hi".to_string())),
+			(Arg::Number("7".to_string()), CandidValue::Nat32(7)),
+			(Arg::BigInt("7".to_string()), CandidValue::Nat64(7)),
+			(Arg::Boolean("true".to_string()), CandidValue::Bool(true)),
+			(Arg::Principal("aaaaa-aa".to_string()), CandidValue::Principal(candid::Principal::management_canister())),
+			(Arg::Array("[1]".to_string()), CandidValue::Vec(vec![CandidValue::Int("1".to_string())])),
+		] {
+			assert_eq!(candid_value_from_arg(&arg, None, "args.0").unwrap(), expected);
+		}
+
+		assert!(candid_value_from_arg(&Arg::Number("-1".to_string()), None, "args.0").is_err());
+		assert!(candid_value_from_arg(&Arg::Principal("not a principal".to_string()), None, "args.0").is_err());
+	}
+}
@@ -0,0 +1,315 @@
+use candid::{ types::{ FuncMode, Type }, Nat, Principal, TypeEnv };
+use candid_parser::{ check_prog, IDLProg };
+use ic_cdk::api::{
+	management_canister::http_request::{
+		http_request,
+		CanisterHttpRequestArgument,
+		HttpHeader,
+		HttpMethod,
+		HttpResponse,
+		TransformArgs,
+		TransformContext,
+		TransformFunc,
+	},
+	time,
+};
+use crate::{
+	types::{ api_error::ApiError, connector::{ CanisterMethod, CanisterMethodMode } },
+	utils::{ cbor::{ decode_cbor, encode_cbor, CborValue }, http_outcall::response_status },
+};
+
+/// Query exposing the Candid interface of canisters built with the Rust or Motoko CDK.
+pub static CANDID_INTERFACE_QUERY: &str = "__get_candid_interface_tmp_hack";
+
+/// Name of the transform query every canister fetching Candid metadata exposes.
+pub static CANDID_METADATA_TRANSFORM: &str = "transform_candid_metadata";
+
+/// URL of the IC HTTP interface the state of canisters is read from.
+pub static IC_API_URL: &str = "https://icp-api.io";
+
+/// Maximum size of a `read_state` response, the certificate holds the whole Candid interface.
+static CANDID_METADATA_MAX_RESPONSE_BYTES: u64 = 512 * 1024;
+
+// Principal of anonymous requests, which need no signature
+static ANONYMOUS_SENDER: u8 = 0x04;
+
+// Requests are rejected if they expire more than 5 minutes in the future
+static INGRESS_EXPIRY: u64 = 4 * 60 * 1_000_000_000;
+
+// Node types of the hash tree of a certificate
+static HASH_TREE_FORK: u64 = 1;
+static HASH_TREE_LABELED: u64 = 2;
+static HASH_TREE_LEAF: u64 = 3;
+
+/// Fetch the `candid:service` metadata of a canister. Canisters cannot read the metadata of other canisters, so it
+/// is read from the certified state of the subnet with an anonymous `read_state` request to the IC HTTP interface.
+///
+/// The signature of the certificate is not verified, as BLS signatures cannot be verified in this canister. The
+/// interface is trusted like the reply of the interface query: every replica fetches it and the replicas must agree
+/// on the response.
+///
+/// # Arguments
+/// - `canister_id` - Canister ID
+/// - `cycles` - Cycles attached to the outcall
+///
+/// # Returns
+/// - `String` - Candid interface (service description)
+pub async fn fetch_candid_metadata(canister_id: Principal, cycles: u128) -> Result<String, ApiError> {
+	let path = CborValue::Array(
+		vec![
+			CborValue::Bytes(b"canister".to_vec()),
+			CborValue::Bytes(canister_id.as_slice().to_vec()),
+			CborValue::Bytes(b"metadata".to_vec()),
+			CborValue::Bytes(b"candid:service".to_vec())
+		]
+	);
+
+	let content = CborValue::Map(
+		vec![
+			(CborValue::Text("request_type".to_string()), CborValue::Text("read_state".to_string())),
+			(CborValue::Text("sender".to_string()), CborValue::Bytes(vec![ANONYMOUS_SENDER])),
+			(CborValue::Text("paths".to_string()), CborValue::Array(vec![path])),
+			(CborValue::Text("ingress_expiry".to_string()), CborValue::Unsigned(time() + INGRESS_EXPIRY))
+		]
+	);
+
+	let request = CanisterHttpRequestArgument {
+		url: format!("{IC_API_URL}/api/v2/canister/{}/read_state", canister_id.to_text()),
+		max_response_bytes: Some(CANDID_METADATA_MAX_RESPONSE_BYTES),
+		method: HttpMethod::POST,
+		headers: vec![HttpHeader { name: "Content-Type".to_string(), value: "application/cbor".to_string() }],
+		body: Some(encode_cbor(&CborValue::Map(vec![(CborValue::Text("content".to_string()), content)]))),
+		transform: Some(TransformContext {
+			function: TransformFunc(candid::Func {
+				method: CANDID_METADATA_TRANSFORM.to_string(),
+				principal: ic_cdk::id(),
+			}),
+			context: canister_id.as_slice().to_vec(),
+		}),
+	};
+
+	let (response,) = http_request(request, cycles).await.map_err(|(code, message)| {
+		ApiError::InterCanister(format!("FAILED TO READ CANDID METADATA: {code:?}: {message}"))
+	})?;
+
+	match response_status(&response) {
+		200 => {
+			String::from_utf8(response.body).map_err(|_|
+				ApiError::BadRequest("CANDID METADATA IS NOT UTF-8".to_string())
+			)
+		}
+		404 => Err(ApiError::NotFound("CANDID METADATA NOT FOUND".to_string())),
+		status => Err(ApiError::InterCanister(format!("FAILED TO READ CANDID METADATA: STATUS {status}"))),
+	}
+}
+
+/// Transform of `read_state` responses. Certificates differ between replicas, so only the `candid:service`
+/// metadata is kept. The status is 404 if the canister has no such metadata, and 502 if the response is invalid.
+///
+/// # Arguments
+/// - `raw` - Response received by a replica, with the canister ID as context
+///
+/// # Returns
+/// - `HttpResponse` - Response every replica agrees on
+pub fn transform_candid_metadata(raw: TransformArgs) -> HttpResponse {
+	let (status, body) = match response_status(&raw.response) {
+		200 => {
+			match candid_metadata_from_response(&raw.response.body, &raw.context) {
+				Ok(Some(candid)) => (200_u16, candid),
+				Ok(None) => (404, vec![]),
+				Err(_) => (502, vec![]),
+			}
+		}
+		status => (status, vec![]),
+	};
+
+	HttpResponse {
+		status: Nat::from(status),
+		headers: vec![],
+		body,
+	}
+}
+
+/// Parse a Candid interface.
+///
+/// # Arguments
+/// - `candid` - Candid interface (service description)
+///
+/// # Returns
+/// - `(TypeEnv, Type)` - Named types of the interface and the service type
+pub fn parse_candid_interface(candid: &str) -> Result<(TypeEnv, Type), ApiError> {
+	let program = candid
+		.parse::<IDLProg>()
+		.map_err(|err| ApiError::BadRequest(format!("INVALID CANDID INTERFACE: {err}")))?;
+
+	let mut env = TypeEnv::new();
+	let service = check_prog(&mut env, &program).map_err(|err|
+		ApiError::BadRequest(format!("INVALID CANDID INTERFACE: {err}"))
+	)?;
+
+	match service {
+		Some(service) => Ok((env, service)),
+		None => Err(ApiError::BadRequest("CANDID INTERFACE HAS NO SERVICE".to_string())),
+	}
+}
+
+/// List the methods of a Candid interface with their argument and return types.
+///
+/// # Arguments
+/// - `candid` - Candid interface (service description)
+///
+/// # Returns
+/// - `Vec<CanisterMethod>` - Methods, sorted by name
+pub fn candid_methods(candid: &str) -> Result<Vec<CanisterMethod>, ApiError> {
+	let (env, service) = parse_candid_interface(candid)?;
+
+	let methods = env.as_service(&service).map_err(|err| ApiError::BadRequest(format!("INVALID SERVICE: {err}")))?;
+
+	methods
+		.iter()
+		.map(|(name, method_type)| {
+			let function = env
+				.as_func(method_type)
+				.map_err(|err| ApiError::BadRequest(format!("INVALID METHOD {name}: {err}")))?;
+
+			Ok(CanisterMethod {
+				name: name.clone(),
+				mode: method_mode(&function.modes),
+				args: function.args.iter().map(|arg_type| arg_type.to_string()).collect(),
+				returns: function.rets.iter().map(|return_type| return_type.to_string()).collect(),
+			})
+		})
+		.collect()
+}
+
+/// Get the argument and return types of a method of a Candid interface.
+///
+/// # Arguments
+/// - `candid` - Candid interface (service description)
+/// - `method` - Method name
+///
+/// # Returns
+/// - `(TypeEnv, Vec<Type>, Vec<Type>)` - Named types of the interface, the argument types and the return types
+pub fn method_signature(candid: &str, method: &str) -> Result<(TypeEnv, Vec<Type>, Vec<Type>), ApiError> {
+	let (env, service) = parse_candid_interface(candid)?;
+
+	let function = env
+		.get_method(&service, method)
+		.map_err(|_| ApiError::NotFound(format!("METHOD {method} NOT FOUND IN CANDID INTERFACE")))?
+		.clone();
+
+	Ok((env, function.args, function.rets))
+}
+
+/// Get the `candid:service` metadata of a canister from the certificate of a `read_state` response.
+fn candid_metadata_from_response(body: &[u8], canister_id: &[u8]) -> Result<Option<Vec<u8>>, ApiError> {
+	let invalid = || ApiError::BadRequest("INVALID READ STATE RESPONSE".to_string());
+
+	let certificate = match decode_cbor(body)?.get("certificate") {
+		Some(CborValue::Bytes(certificate)) => decode_cbor(certificate)?,
+		_ => {
+			return Err(invalid());
+		}
+	};
+
+	let tree = certificate.get("tree").ok_or_else(invalid)?;
+
+	Ok(lookup_hash_tree(tree, &[b"canister", canister_id, b"metadata", b"candid:service"]).cloned())
+}
+
+/// Look up the leaf at a path of labels in a hash tree. Pruned branches are treated as absent.
+fn lookup_hash_tree<'a>(tree: &'a CborValue, path: &[&[u8]]) -> Option<&'a Vec<u8>> {
+	let CborValue::Array(node) = tree else {
+		return None;
+	};
+
+	match (path.split_first(), node.as_slice()) {
+		(None, [CborValue::Unsigned(node_type), CborValue::Bytes(value)]) if *node_type == HASH_TREE_LEAF => {
+			Some(value)
+		}
+		(Some(_), [CborValue::Unsigned(node_type), left, right]) if *node_type == HASH_TREE_FORK => {
+			lookup_hash_tree(left, path).or_else(|| lookup_hash_tree(right, path))
+		}
+		(Some((label, rest)), [CborValue::Unsigned(node_type), CborValue::Bytes(node_label), subtree])
+			if *node_type == HASH_TREE_LABELED && node_label.as_slice() == *label => {
+			lookup_hash_tree(subtree, rest)
+		}
+		_ => None,
+	}
+}
+
+fn method_mode(modes: &[FuncMode]) -> CanisterMethodMode {
+	match modes.first() {
+		Some(FuncMode::Query) => CanisterMethodMode::Query,
+		Some(FuncMode::CompositeQuery) => CanisterMethodMode::CompositeQuery,
+		Some(FuncMode::Oneway) => CanisterMethodMode::Oneway,
+		None => CanisterMethodMode::Update,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn labeled(label: &[u8], subtree: CborValue) -> CborValue {
+		CborValue::Array(vec![CborValue::Unsigned(2), CborValue::Bytes(label.to_vec()), subtree])
+	}
+
+	fn fork(left: CborValue, right: CborValue) -> CborValue {
+		CborValue::Array(vec![CborValue::Unsigned(1), left, right])
+	}
+
+	fn leaf(value: &[u8]) -> CborValue {
+		CborValue::Array(vec![CborValue::Unsigned(3), CborValue::Bytes(value.to_vec())])
+	}
+
+	fn pruned() -> CborValue {
+		CborValue::Array(vec![CborValue::Unsigned(4), CborValue::Bytes(vec![0; 32])])
+	}
+
+	fn read_state_response(tree: CborValue) -> Vec<u8> {
+		let certificate = CborValue::Map(
+			vec![
+				(CborValue::Text("tree".to_string()), tree),
+				(CborValue::Text("signature".to_string()), CborValue::Bytes(vec![0; 48]))
+			]
+		);
+
+		encode_cbor(
+			&CborValue::Map(
+				vec![(CborValue::Text("certificate".to_string()), CborValue::Bytes(encode_cbor(&certificate)))]
+			)
+		)
+	}
+
+	#[test]
+	fn candid_metadata_from_response_finds_the_leaf() {
+		let canister_id = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+		let candid = b"service : { greet : (text) -> (text) query }";
+
+		let metadata = fork(
+			labeled(b"candid:args", leaf(b"()")),
+			labeled(b"candid:service", leaf(candid))
+		);
+		let tree = fork(
+			labeled(b"canister", labeled(canister_id.as_slice(), labeled(b"metadata", metadata))),
+			labeled(b"time", leaf(&[0x80, 0x01]))
+		);
+
+		let response = read_state_response(tree);
+
+		assert_eq!(candid_metadata_from_response(&response, canister_id.as_slice()).unwrap(), Some(candid.to_vec()));
+		assert_eq!(candid_metadata_from_response(&response, Principal::anonymous().as_slice()).unwrap(), None);
+	}
+
+	#[test]
+	fn candid_metadata_from_response_treats_pruned_branches_as_absent() {
+		let canister_id = Principal::from_text("ryjl3-tyaaa-aaaaa-aaaba-cai").unwrap();
+		let tree = fork(pruned(), labeled(b"time", leaf(&[0x80, 0x01])));
+
+		assert_eq!(candid_metadata_from_response(&read_state_response(tree), canister_id.as_slice()).unwrap(), None);
+		assert!(
+			matches!(candid_metadata_from_response(b"not cbor", canister_id.as_slice()), Err(ApiError::BadRequest(_)))
+		);
+	}
+}
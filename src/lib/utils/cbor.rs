@@ -0,0 +1,263 @@
+use crate::types::api_error::ApiError;
+
+/// Maximum nesting of decoded values, hash trees of certificates are far less deep.
+static MAX_DEPTH: usize = 64;
+
+/// CBOR value. Only the data items used by the IC HTTP interface are supported: tags are skipped when decoding,
+/// floats and indefinite lengths are rejected.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CborValue {
+	Unsigned(u64),
+	/// Negative integer `-1 - n`
+	Negative(u64),
+	Bytes(Vec<u8>),
+	Text(String),
+	Array(Vec<CborValue>),
+	Map(Vec<(CborValue, CborValue)>),
+	/// Simple value, e.g. `false` (20), `true` (21) and `null` (22)
+	Simple(u8),
+}
+
+impl CborValue {
+	/// Get the value of a map by a text key.
+	pub fn get(&self, key: &str) -> Option<&CborValue> {
+		match self {
+			CborValue::Map(entries) => {
+				entries
+					.iter()
+					.find(|(entry_key, _)| matches!(entry_key, CborValue::Text(text) if text == key))
+					.map(|(_, value)| value)
+			}
+			_ => None,
+		}
+	}
+}
+
+/// Encode a CBOR value, with the shortest length encodings.
+///
+/// # Arguments
+/// - `value` - CBOR value
+///
+/// # Returns
+/// - `Vec<u8>` - Encoded value
+pub fn encode_cbor(value: &CborValue) -> Vec<u8> {
+	let mut bytes = vec![];
+	encode_value(value, &mut bytes);
+
+	bytes
+}
+
+/// Decode a CBOR value. The whole input must be a single value.
+///
+/// # Arguments
+/// - `bytes` - Encoded value
+///
+/// # Returns
+/// - `CborValue` - Decoded value
+pub fn decode_cbor(bytes: &[u8]) -> Result<CborValue, ApiError> {
+	let mut decoder = Decoder { bytes, position: 0 };
+	let value = decoder.value(0)?;
+
+	match decoder.position == bytes.len() {
+		true => Ok(value),
+		false => Err(invalid("TRAILING BYTES")),
+	}
+}
+
+fn encode_value(value: &CborValue, bytes: &mut Vec<u8>) {
+	match value {
+		CborValue::Unsigned(number) => encode_head(0, *number, bytes),
+		CborValue::Negative(number) => encode_head(1, *number, bytes),
+		CborValue::Bytes(data) => {
+			encode_head(2, data.len() as u64, bytes);
+			bytes.extend_from_slice(data);
+		}
+		CborValue::Text(text) => {
+			encode_head(3, text.len() as u64, bytes);
+			bytes.extend_from_slice(text.as_bytes());
+		}
+		CborValue::Array(items) => {
+			encode_head(4, items.len() as u64, bytes);
+			items.iter().for_each(|item| encode_value(item, bytes));
+		}
+		CborValue::Map(entries) => {
+			encode_head(5, entries.len() as u64, bytes);
+			entries.iter().for_each(|(key, value)| {
+				encode_value(key, bytes);
+				encode_value(value, bytes);
+			});
+		}
+		CborValue::Simple(simple) => encode_head(7, *simple as u64, bytes),
+	}
+}
+
+fn encode_head(major_type: u8, argument: u64, bytes: &mut Vec<u8>) {
+	let major_type = major_type << 5;
+
+	match argument {
+		0..=23 => bytes.push(major_type | (argument as u8)),
+		24..=0xff => bytes.extend_from_slice(&[major_type | 24, argument as u8]),
+		0x100..=0xffff => {
+			bytes.push(major_type | 25);
+			bytes.extend_from_slice(&(argument as u16).to_be_bytes());
+		}
+		0x1_0000..=0xffff_ffff => {
+			bytes.push(major_type | 26);
+			bytes.extend_from_slice(&(argument as u32).to_be_bytes());
+		}
+		_ => {
+			bytes.push(major_type | 27);
+			bytes.extend_from_slice(&argument.to_be_bytes());
+		}
+	}
+}
+
+fn invalid(reason: &str) -> ApiError {
+	ApiError::BadRequest(format!("INVALID CBOR: {reason}"))
+}
+
+struct Decoder<'a> {
+	bytes: &'a [u8],
+	position: usize,
+}
+
+impl Decoder<'_> {
+	fn value(&mut self, depth: usize) -> Result<CborValue, ApiError> {
+		if depth > MAX_DEPTH {
+			return Err(invalid("NESTED TOO DEEPLY"));
+		}
+
+		let initial_byte = self.take(1)?[0];
+		let major_type = initial_byte >> 5;
+		let additional_info = initial_byte & 0x1f;
+
+		// Floats share the major type of simple values, their argument is not a length
+		if major_type == 7 && additional_info > 24 {
+			return Err(invalid("FLOATS ARE NOT SUPPORTED"));
+		}
+
+		let argument = self.argument(additional_info)?;
+
+		match major_type {
+			0 => Ok(CborValue::Unsigned(argument)),
+			1 => Ok(CborValue::Negative(argument)),
+			2 => Ok(CborValue::Bytes(self.take(self.length(argument)?)?.to_vec())),
+			3 => {
+				let text = self.take(self.length(argument)?)?.to_vec();
+
+				String::from_utf8(text)
+					.map(CborValue::Text)
+					.map_err(|_| invalid("TEXT IS NOT UTF-8"))
+			}
+			4 => {
+				(0..self.length(argument)?)
+					.map(|_| self.value(depth + 1))
+					.collect::<Result<Vec<CborValue>, ApiError>>()
+					.map(CborValue::Array)
+			}
+			5 => {
+				(0..self.length(argument)?)
+					.map(|_| Ok((self.value(depth + 1)?, self.value(depth + 1)?)))
+					.collect::<Result<Vec<(CborValue, CborValue)>, ApiError>>()
+					.map(CborValue::Map)
+			}
+			// Tags, e.g. the self-describe tag in front of IC responses, only annotate the value that follows
+			6 => self.value(depth + 1),
+			_ => Ok(CborValue::Simple(argument as u8)),
+		}
+	}
+
+	fn argument(&mut self, additional_info: u8) -> Result<u64, ApiError> {
+		match additional_info {
+			0..=23 => Ok(additional_info as u64),
+			24 => Ok(self.take(1)?[0] as u64),
+			25 => Ok(u16::from_be_bytes(self.take(2)?.try_into().unwrap_or_default()) as u64),
+			26 => Ok(u32::from_be_bytes(self.take(4)?.try_into().unwrap_or_default()) as u64),
+			27 => Ok(u64::from_be_bytes(self.take(8)?.try_into().unwrap_or_default())),
+			_ => Err(invalid("INDEFINITE LENGTHS ARE NOT SUPPORTED")),
+		}
+	}
+
+	// Lengths are bounded by the remaining input, so a forged length cannot allocate more than was received
+	fn length(&self, argument: u64) -> Result<usize, ApiError> {
+		match usize::try_from(argument) {
+			Ok(length) if length <= self.bytes.len() - self.position => Ok(length),
+			_ => Err(invalid("UNEXPECTED END")),
+		}
+	}
+
+	fn take(&mut self, length: usize) -> Result<&[u8], ApiError> {
+		let end = self.position.checked_add(length).filter(|end| *end <= self.bytes.len());
+
+		match end {
+			Some(end) => {
+				let bytes = &self.bytes[self.position..end];
+				self.position = end;
+
+				Ok(bytes)
+			}
+			None => Err(invalid("UNEXPECTED END")),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn encode_cbor_matches_rfc_examples() {
+		// Examples of RFC 8949, appendix A
+		assert_eq!(encode_cbor(&CborValue::Unsigned(23)), vec![0x17]);
+		assert_eq!(encode_cbor(&CborValue::Unsigned(1000)), vec![0x19, 0x03, 0xe8]);
+		assert_eq!(encode_cbor(&CborValue::Unsigned(1_000_000_000_000)), hex::decode("1b000000e8d4a51000").unwrap());
+		assert_eq!(encode_cbor(&CborValue::Negative(99)), vec![0x38, 0x63]);
+		assert_eq!(encode_cbor(&CborValue::Bytes(vec![1, 2, 3, 4])), vec![0x44, 1, 2, 3, 4]);
+		assert_eq!(encode_cbor(&CborValue::Text("IETF".to_string())), vec![0x64, 0x49, 0x45, 0x54, 0x46]);
+
+		let map = CborValue::Map(
+			vec![
+				(CborValue::Text("a".to_string()), CborValue::Unsigned(1)),
+				(
+					CborValue::Text("b".to_string()),
+					CborValue::Array(vec![CborValue::Unsigned(2), CborValue::Unsigned(3)]),
+				)
+			]
+		);
+		assert_eq!(encode_cbor(&map), hex::decode("a26161016162820203").unwrap());
+	}
+
+	#[test]
+	fn decode_cbor_reverses_encoding() {
+		let value = CborValue::Map(
+			vec![
+				(CborValue::Text("certificate".to_string()), CborValue::Bytes(vec![0; 300])),
+				(CborValue::Text("status".to_string()), CborValue::Simple(21)),
+				(CborValue::Unsigned(70_000), CborValue::Array(vec![CborValue::Negative(0)]))
+			]
+		);
+
+		assert_eq!(decode_cbor(&encode_cbor(&value)).unwrap(), value);
+		assert_eq!(value.get("status"), Some(&CborValue::Simple(21)));
+		assert_eq!(value.get("missing"), None);
+
+		// Self-describe tag in front of the value
+		assert_eq!(decode_cbor(&hex::decode("d9d9f71903e8").unwrap()).unwrap(), CborValue::Unsigned(1000));
+	}
+
+	#[test]
+	fn decode_cbor_rejects_invalid_input() {
+		// Bytes claiming a length larger than the input
+		assert!(matches!(decode_cbor(&[0x5a, 0xff, 0xff, 0xff, 0xff]), Err(ApiError::BadRequest(_))));
+		// Half-precision float
+		assert!(matches!(decode_cbor(&[0xf9, 0x3c, 0x00]), Err(ApiError::BadRequest(_))));
+		// Indefinite length array
+		assert!(matches!(decode_cbor(&[0x9f, 0x01, 0xff]), Err(ApiError::BadRequest(_))));
+		// Trailing bytes
+		assert!(matches!(decode_cbor(&[0x01, 0x02]), Err(ApiError::BadRequest(_))));
+		// Nesting deeper than any certificate
+		let mut nested = vec![0x81; 100];
+		nested.push(0x01);
+		assert!(matches!(decode_cbor(&nested), Err(ApiError::BadRequest(_))));
+	}
+}
@@ -0,0 +1,183 @@
+use candid::Nat;
+use ic_cdk::api::management_canister::http_request::{
+	http_request,
+	CanisterHttpRequestArgument,
+	HttpHeader,
+	HttpMethod,
+	HttpResponse,
+	TransformArgs,
+	TransformContext,
+	TransformFunc,
+};
+use serde_json::Value;
+use crate::{
+	node_server::HOST,
+	types::{ api_error::ApiError, headers::Headers, node::HttpRequestMethod },
+	utils::idempotency::generate_idempotency_key,
+};
+
+/// Name of the transform query every canister making outcalls exposes.
+pub static HTTP_OUTCALL_TRANSFORM: &str = "transform";
+
+/// Maximum response size of a direct outcall, the cycles cost grows with it.
+pub static HTTP_OUTCALL_MAX_RESPONSE_BYTES: u64 = 64 * 1024;
+
+/// Canister HTTP Request call, forwarded by the node server so the request is only sent once.
+///
+/// # Arguments
+/// - `body` - Value
+/// - `cycles` - u128
+/// - `url` - URL of the node server endpoint
+pub async fn forward_http_request(body: Value, cycles: u128, url: &str) -> Result<String, ApiError> {
+	// Prepare headers for the system http_request call
+	let request_headers = vec![
+		HttpHeader {
+			name: "Host".to_string(),
+			value: format!("{HOST}:443"),
+		},
+		HttpHeader {
+			name: "User-Agent".to_string(),
+			value: "demo_HTTP_POST_canister".to_string(),
+		},
+		HttpHeader {
+			name: "Idempotency-Key".to_string(),
+			value: generate_idempotency_key().await.unwrap(),
+		},
+		HttpHeader {
+			name: "Content-Type".to_string(),
+			value: "application/json".to_string(),
+		}
+	];
+
+	let request_body: Option<Vec<u8>> = Some(body.to_string().into_bytes());
+
+	let request = CanisterHttpRequestArgument {
+		url: url.to_string(),
+		max_response_bytes: None, //optional for request
+		method: HttpMethod::POST,
+		headers: request_headers,
+		body: request_body,
+		transform: Some(TransformContext {
+			function: TransformFunc(candid::Func {
+				method: HTTP_OUTCALL_TRANSFORM.to_string(),
+				principal: ic_cdk::id(),
+			}),
+			context: vec![],
+		}),
+	};
+
+	match http_request(request, cycles).await {
+		Ok((response,)) => {
+			// if successful, `HttpResponse` has this structure:
+			// pub struct HttpResponse {
+			//     pub status: Nat,
+			//     pub headers: Vec<HttpHeader>,
+			//     pub body: Vec<u8>,
+			// }
+
+			// You need to decode that Vec<u8> that is the body into readable text.
+			// To do this:
+			//  1. Call `String::from_utf8()` on response.body
+			//  2. You use a switch to explicitly call out both cases of decoding the Blob into ?Text
+
+			// The API response will looks like this:
+
+			// ("[[1682978460,5.714,5.718,5.714,5.714,243.5678]]")
+
+			// Which can be formatted as this
+			//  [
+			//     [
+			//         1682978460, <-- start/timestamp
+			//         5.714, <-- low
+			//         5.718, <-- high
+			//         5.714, <-- open
+			//         5.714, <-- close
+			//         243.5678 <-- volume
+			//     ],
+			//  ]
+
+			// Return the body as a string and end the method
+			let str_body = String::from_utf8(response.body).expect("Transformed response is not UTF-8 encoded.");
+
+			Ok(str_body)
+		}
+		Err((r, m)) => {
+			let message = format!("The http_request resulted into error. RejectionCode: {r:?}, Error: {m}");
+
+			//Return the error as a string and end the method
+			Err(ApiError::InterCanister(message))
+		}
+	}
+}
+
+/// Make an HTTP outcall directly to the service, without the forwarding node server.
+/// Only use this for idempotent requests, every replica of the subnet sends the request.
+///
+/// # Arguments
+/// - `url` - URL
+/// - `method` - Method
+/// - `headers` - Headers
+/// - `body` - Body
+/// - `cycles` - Cycles attached to the outcall
+///
+/// # Returns
+/// - `HttpResponse` - Response with its status and body
+pub async fn http_outcall(
+	url: String,
+	method: &HttpRequestMethod,
+	headers: Headers,
+	body: Option<String>,
+	cycles: u128
+) -> Result<HttpResponse, ApiError> {
+	let method = match method {
+		HttpRequestMethod::GET => HttpMethod::GET,
+		HttpRequestMethod::POST => HttpMethod::POST,
+		_ => {
+			return Err(ApiError::BadRequest("METHOD NOT SUPPORTED BY HTTP OUTCALLS".to_string()));
+		}
+	};
+
+	let request = CanisterHttpRequestArgument {
+		url,
+		max_response_bytes: Some(HTTP_OUTCALL_MAX_RESPONSE_BYTES),
+		method,
+		headers: headers
+			.into_iter()
+			.map(|(name, value)| HttpHeader { name, value })
+			.collect(),
+		body: body.map(|body| body.into_bytes()),
+		transform: Some(TransformContext {
+			function: TransformFunc(candid::Func {
+				method: HTTP_OUTCALL_TRANSFORM.to_string(),
+				principal: ic_cdk::id(),
+			}),
+			context: vec![],
+		}),
+	};
+
+	match http_request(request, cycles).await {
+		Ok((response,)) => Ok(response),
+		Err((code, message)) => Err(ApiError::Connector(format!("HTTP OUTCALL FAILED: {code:?}: {message}"))),
+	}
+}
+
+/// Transform of direct outcalls. Headers differ between replicas, so only the status and body are kept.
+///
+/// # Arguments
+/// - `raw` - Response received by a replica
+///
+/// # Returns
+/// - `HttpResponse` - Response every replica agrees on
+pub fn transform_status_and_body(raw: TransformArgs) -> HttpResponse {
+	HttpResponse {
+		status: raw.response.status,
+		headers: vec![],
+		body: raw.response.body,
+	}
+}
+
+/// Status of a response as a number, 0 if it does not fit.
+pub fn response_status(response: &HttpResponse) -> u16 {
+	let Nat(status) = &response.status;
+	u16::try_from(status.clone()).unwrap_or_default()
+}
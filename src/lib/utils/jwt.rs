@@ -0,0 +1,447 @@
+use base64::{ engine::general_purpose::URL_SAFE_NO_PAD, Engine };
+use hmac::{ Hmac, Mac };
+use p256::elliptic_curve;
+use rand_chacha::{ rand_core::SeedableRng, ChaCha20Rng };
+use rsa::{
+	pkcs1::{ DecodeRsaPrivateKey, DecodeRsaPublicKey },
+	pkcs1v15::{ Signature as RsaSignature, SigningKey as RsaSigningKey, VerifyingKey as RsaVerifyingKey },
+	pkcs8::{ DecodePrivateKey, DecodePublicKey },
+	signature::{ RandomizedSigner, SignatureEncoding, Signer, Verifier },
+	RsaPrivateKey,
+	RsaPublicKey,
+};
+use serde_json::{ json, Map, Value };
+use sha2::{ Digest, Sha256, Sha384, Sha512 };
+use crate::types::{
+	api_error::ApiError,
+	connector::{ JWTConfig, JwtPreview, SignatureMethod },
+	connector_secrets::SECRET_PLACEHOLDER,
+	node::JwtVerification,
+};
+use super::template::render_template;
+
+/// Lifetime of a minted token when the payload does not define `exp`.
+pub static JWT_LIFETIME_SECONDS: u64 = 3600;
+
+/// Mint a token from the configuration of a connector.
+///
+/// # Arguments
+/// - `config` - JWT configuration
+/// - `data` - Data used to render the payload template
+/// - `now_seconds` - Current time in seconds, used for `iat` and `exp`
+///
+/// # Returns
+/// - `String` - Signed token
+pub fn mint_jwt(config: &JWTConfig, data: &Value, now_seconds: u64) -> Result<String, ApiError> {
+	let header = json!({ "alg": algorithm(&config.signature_method), "typ": "JWT" });
+	let claims = render_claims(&config.payload, data, now_seconds)?;
+
+	let signing_input = format!("{}.{}", encode_segment(&header), encode_segment(&claims));
+	let signature = sign(config, signing_input.as_bytes())?;
+
+	Ok(format!("{signing_input}.{}", URL_SAFE_NO_PAD.encode(signature)))
+}
+
+/// Decode the header and claims of a token, without verifying its signature. The signature is masked in the
+/// returned token, so the preview cannot be used to authenticate.
+///
+/// # Arguments
+/// - `token` - Token
+///
+/// # Returns
+/// - `JwtPreview` - Token with a masked signature and its stringified JSON header and claims
+pub fn decode_jwt(token: &str) -> Result<JwtPreview, ApiError> {
+	let segments = token.split('.').collect::<Vec<&str>>();
+
+	if segments.len() != 3 {
+		return Err(ApiError::BadRequest("INVALID JWT".to_string()));
+	}
+
+	Ok(JwtPreview {
+		token: format!("{}.{}.{SECRET_PLACEHOLDER}", segments[0], segments[1]),
+		header: decode_segment(segments[0])?.to_string(),
+		claims: decode_segment(segments[1])?.to_string(),
+	})
+}
+
+/// Verify a token and its registered claims. The signature must match one of the keys with one of the allowed
+/// algorithms, the token must not be expired or not yet valid, and the issuer and audience must match if expected.
+///
+/// # Arguments
+/// - `token` - Token
+/// - `config` - Verification configuration, with its secrets resolved
+/// - `now_seconds` - Current time in seconds
+///
+/// # Returns
+/// - `Value` - Verified claims
+pub fn verify_jwt(token: &str, config: &JwtVerification, now_seconds: u64) -> Result<Value, ApiError> {
+	let segments = token.split('.').collect::<Vec<&str>>();
+
+	if segments.len() != 3 {
+		return Err(ApiError::Unauthorized("INVALID JWT".to_string()));
+	}
+
+	let header = decode_segment(segments[0]).map_err(|_| ApiError::Unauthorized("INVALID JWT".to_string()))?;
+	let claims = decode_segment(segments[1]).map_err(|_| ApiError::Unauthorized("INVALID JWT".to_string()))?;
+	let signature = URL_SAFE_NO_PAD.decode(segments[2]).map_err(|_| ApiError::Unauthorized("INVALID JWT".to_string()))?;
+
+	// The algorithm of the token is only trusted if it is allowed, so a token cannot pick a weaker one
+	let signature_method = config.algorithms
+		.iter()
+		.find(|signature_method| Some(algorithm(signature_method)) == header["alg"].as_str())
+		.ok_or(ApiError::Unauthorized("JWT ALGORITHM NOT ALLOWED".to_string()))?;
+
+	let signing_input = format!("{}.{}", segments[0], segments[1]);
+	let is_verified = config.keys
+		.iter()
+		.any(|key| verify(signature_method, key, signing_input.as_bytes(), &signature));
+
+	if !is_verified {
+		return Err(ApiError::Unauthorized("INVALID JWT SIGNATURE".to_string()));
+	}
+
+	let skew = config.clock_skew_seconds;
+
+	if let Some(expires_at) = claims["exp"].as_u64() {
+		if now_seconds > expires_at.saturating_add(skew) {
+			return Err(ApiError::Unauthorized("JWT EXPIRED".to_string()));
+		}
+	}
+
+	if let Some(not_before) = claims["nbf"].as_u64() {
+		if now_seconds.saturating_add(skew) < not_before {
+			return Err(ApiError::Unauthorized("JWT NOT YET VALID".to_string()));
+		}
+	}
+
+	if let Some(issuer) = &config.issuer {
+		if claims["iss"].as_str() != Some(issuer.as_str()) {
+			return Err(ApiError::Unauthorized("INVALID JWT ISSUER".to_string()));
+		}
+	}
+
+	if let Some(audience) = &config.audience {
+		let matches_audience = match &claims["aud"] {
+			Value::String(aud) => aud == audience,
+			Value::Array(auds) => auds.iter().any(|aud| aud.as_str() == Some(audience.as_str())),
+			_ => false,
+		};
+
+		if !matches_audience {
+			return Err(ApiError::Unauthorized("INVALID JWT AUDIENCE".to_string()));
+		}
+	}
+
+	Ok(claims)
+}
+
+/// Whether a key is a PEM encoded key rather than a secret.
+pub fn is_public_key(key: &str) -> bool {
+	key.trim_start().starts_with("-----BEGIN")
+}
+
+/// Name of the signature method as used in the `alg` header.
+pub fn algorithm(signature_method: &SignatureMethod) -> &'static str {
+	match signature_method {
+		SignatureMethod::HS256 => "HS256",
+		SignatureMethod::HS384 => "HS384",
+		SignatureMethod::HS512 => "HS512",
+		SignatureMethod::RS256 => "RS256",
+		SignatureMethod::RS384 => "RS384",
+		SignatureMethod::RS512 => "RS512",
+		SignatureMethod::ES256 => "ES256",
+		SignatureMethod::ES384 => "ES384",
+		SignatureMethod::ES512 => "ES512",
+	}
+}
+
+fn render_claims(payload: &str, data: &Value, now_seconds: u64) -> Result<Value, ApiError> {
+	let rendered = match payload.trim() {
+		"" => "{}".to_string(),
+		payload => render_template(payload, data),
+	};
+
+	let mut claims: Map<String, Value> = serde_json
+		::from_str(&rendered)
+		.map_err(|err| ApiError::BadRequest(format!("INVALID JWT PAYLOAD: {err}")))?;
+
+	claims.insert("iat".to_string(), json!(now_seconds));
+	claims.entry("exp").or_insert(json!(now_seconds + JWT_LIFETIME_SECONDS));
+
+	Ok(Value::Object(claims))
+}
+
+fn sign(config: &JWTConfig, message: &[u8]) -> Result<Vec<u8>, ApiError> {
+	let secret = config.secret.as_bytes();
+	let pem = config.secret_key.as_str();
+
+	match config.signature_method {
+		SignatureMethod::HS256 => hmac_sign::<Hmac<Sha256>>(secret, message),
+		SignatureMethod::HS384 => hmac_sign::<Hmac<Sha384>>(secret, message),
+		SignatureMethod::HS512 => hmac_sign::<Hmac<Sha512>>(secret, message),
+		SignatureMethod::RS256 => rsa_sign::<Sha256>(pem, message),
+		SignatureMethod::RS384 => rsa_sign::<Sha384>(pem, message),
+		SignatureMethod::RS512 => rsa_sign::<Sha512>(pem, message),
+		SignatureMethod::ES256 => {
+			let signing_key = p256::ecdsa::SigningKey::from(ec_secret_key::<p256::NistP256>(pem)?);
+			let signature: p256::ecdsa::Signature = signing_key.sign(message);
+
+			Ok(signature.to_vec())
+		}
+		SignatureMethod::ES384 => {
+			let signing_key = p384::ecdsa::SigningKey::from(ec_secret_key::<p384::NistP384>(pem)?);
+			let signature: p384::ecdsa::Signature = signing_key.sign(message);
+
+			Ok(signature.to_vec())
+		}
+		SignatureMethod::ES512 => {
+			let secret_key = ec_secret_key::<p521::NistP521>(pem)?;
+			let signing_key = p521::ecdsa::SigningKey
+				::from_bytes(&secret_key.to_bytes())
+				.map_err(|_| ApiError::BadRequest("INVALID ECDSA KEY".to_string()))?;
+
+			// P-521 has no deterministic signer, the nonce is drawn from a generator keyed by the key and message
+			let mut seed_mac = <Hmac<Sha256> as hmac::digest::KeyInit>
+				::new_from_slice(&secret_key.to_bytes())
+				.map_err(|_| ApiError::BadRequest("INVALID ECDSA KEY".to_string()))?;
+			seed_mac.update(message);
+
+			let mut rng = ChaCha20Rng::from_seed(seed_mac.finalize().into_bytes().into());
+			let signature: p521::ecdsa::Signature = signing_key.sign_with_rng(&mut rng, message);
+
+			Ok(signature.to_vec())
+		}
+	}
+}
+
+fn verify(signature_method: &SignatureMethod, key: &str, message: &[u8], signature: &[u8]) -> bool {
+	// HMAC secrets and public keys are never used for the other kind of algorithm
+	let is_hmac = matches!(signature_method, SignatureMethod::HS256 | SignatureMethod::HS384 | SignatureMethod::HS512);
+	if is_hmac == is_public_key(key) {
+		return false;
+	}
+
+	match signature_method {
+		SignatureMethod::HS256 => hmac_verify::<Hmac<Sha256>>(key.as_bytes(), message, signature),
+		SignatureMethod::HS384 => hmac_verify::<Hmac<Sha384>>(key.as_bytes(), message, signature),
+		SignatureMethod::HS512 => hmac_verify::<Hmac<Sha512>>(key.as_bytes(), message, signature),
+		SignatureMethod::RS256 => rsa_verify::<Sha256>(key, message, signature),
+		SignatureMethod::RS384 => rsa_verify::<Sha384>(key, message, signature),
+		SignatureMethod::RS512 => rsa_verify::<Sha512>(key, message, signature),
+		SignatureMethod::ES256 => {
+			let (Some(public_key), Ok(signature)) = (
+				ec_public_key::<p256::NistP256>(key),
+				p256::ecdsa::Signature::from_slice(signature),
+			) else {
+				return false;
+			};
+
+			p256::ecdsa::VerifyingKey::from(public_key).verify(message, &signature).is_ok()
+		}
+		SignatureMethod::ES384 => {
+			let (Some(public_key), Ok(signature)) = (
+				ec_public_key::<p384::NistP384>(key),
+				p384::ecdsa::Signature::from_slice(signature),
+			) else {
+				return false;
+			};
+
+			p384::ecdsa::VerifyingKey::from(public_key).verify(message, &signature).is_ok()
+		}
+		SignatureMethod::ES512 => {
+			let (Some(public_key), Ok(signature)) = (
+				ec_public_key::<p521::NistP521>(key),
+				p521::ecdsa::Signature::from_slice(signature),
+			) else {
+				return false;
+			};
+
+			match p521::ecdsa::VerifyingKey::from_affine(*public_key.as_affine()) {
+				Ok(verifying_key) => verifying_key.verify(message, &signature).is_ok(),
+				Err(_) => false,
+			}
+		}
+	}
+}
+
+fn hmac_verify<M: Mac + hmac::digest::KeyInit>(secret: &[u8], message: &[u8], signature: &[u8]) -> bool {
+	match <M as hmac::digest::KeyInit>::new_from_slice(secret) {
+		Ok(mut mac) if !secret.is_empty() => {
+			mac.update(message);
+			mac.verify_slice(signature).is_ok()
+		}
+		_ => false,
+	}
+}
+
+fn rsa_verify<D>(pem: &str, message: &[u8], signature: &[u8]) -> bool
+	where D: Digest + rsa::pkcs8::AssociatedOid
+{
+	// SPKI ("BEGIN PUBLIC KEY") and PKCS#1 ("BEGIN RSA PUBLIC KEY") keys are both accepted
+	let Ok(public_key) = RsaPublicKey::from_public_key_pem(pem).or_else(|_| RsaPublicKey::from_pkcs1_pem(pem)) else {
+		return false;
+	};
+
+	match RsaSignature::try_from(signature) {
+		Ok(signature) => RsaVerifyingKey::<D>::new(public_key).verify(message, &signature).is_ok(),
+		Err(_) => false,
+	}
+}
+
+fn ec_public_key<C>(pem: &str) -> Option<elliptic_curve::PublicKey<C>>
+	where
+		C: elliptic_curve::Curve + elliptic_curve::CurveArithmetic + rsa::pkcs8::AssociatedOid,
+		elliptic_curve::FieldBytesSize<C>: elliptic_curve::sec1::ModulusSize,
+		elliptic_curve::AffinePoint<C>: elliptic_curve::sec1::FromEncodedPoint<C> +
+			elliptic_curve::sec1::ToEncodedPoint<C>
+{
+	elliptic_curve::PublicKey::<C>::from_public_key_pem(pem).ok()
+}
+
+fn hmac_sign<M: Mac + hmac::digest::KeyInit>(secret: &[u8], message: &[u8]) -> Result<Vec<u8>, ApiError> {
+	if secret.is_empty() {
+		return Err(ApiError::BadRequest("MISSING JWT SECRET".to_string()));
+	}
+
+	let mut mac = <M as hmac::digest::KeyInit>
+		::new_from_slice(secret)
+		.map_err(|_| ApiError::BadRequest("INVALID JWT SECRET".to_string()))?;
+	mac.update(message);
+
+	Ok(mac.finalize().into_bytes().to_vec())
+}
+
+fn rsa_sign<D>(pem: &str, message: &[u8]) -> Result<Vec<u8>, ApiError>
+	where D: Digest + rsa::pkcs8::AssociatedOid
+{
+	// PKCS#8 ("BEGIN PRIVATE KEY") and PKCS#1 ("BEGIN RSA PRIVATE KEY") keys are both accepted
+	let private_key = RsaPrivateKey::from_pkcs8_pem(pem)
+		.or_else(|_| RsaPrivateKey::from_pkcs1_pem(pem))
+		.map_err(|_| ApiError::BadRequest("INVALID RSA KEY".to_string()))?;
+
+	let signing_key = RsaSigningKey::<D>::new(private_key);
+
+	Ok(signing_key.sign(message).to_vec())
+}
+
+fn ec_secret_key<C>(pem: &str) -> Result<elliptic_curve::SecretKey<C>, ApiError>
+	where
+		C: elliptic_curve::Curve + elliptic_curve::CurveArithmetic + rsa::pkcs8::AssociatedOid,
+		elliptic_curve::FieldBytesSize<C>: elliptic_curve::sec1::ModulusSize,
+		elliptic_curve::AffinePoint<C>: elliptic_curve::sec1::FromEncodedPoint<C> +
+			elliptic_curve::sec1::ToEncodedPoint<C>
+{
+	// PKCS#8 ("BEGIN PRIVATE KEY") and SEC1 ("BEGIN EC PRIVATE KEY") keys are both accepted
+	elliptic_curve::SecretKey::<C>
+		::from_pkcs8_pem(pem)
+		.or_else(|_| elliptic_curve::SecretKey::<C>::from_sec1_pem(pem))
+		.map_err(|_| ApiError::BadRequest("INVALID ECDSA KEY".to_string()))
+}
+
+fn encode_segment(value: &Value) -> String {
+	URL_SAFE_NO_PAD.encode(value.to_string())
+}
+
+fn decode_segment(segment: &str) -> Result<Value, ApiError> {
+	let bytes = URL_SAFE_NO_PAD.decode(segment).map_err(|_| ApiError::BadRequest("INVALID JWT".to_string()))?;
+
+	serde_json::from_slice(&bytes).map_err(|_| ApiError::BadRequest("INVALID JWT".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::types::connector::TokenLocation;
+	use super::*;
+
+	// Example token of jwt.io, signed with the secret `your-256-bit-secret`
+	static JWT_IO_TOKEN: &str = concat!(
+		"eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.",
+		"eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiaWF0IjoxNTE2MjM5MDIyfQ.",
+		"SflKxwRJSMeKKF2QT4fwpMeJf36POk6yJV_adQssw5c"
+	);
+
+	static JWT_IO_SECRET: &str = "your-256-bit-secret";
+
+	static JWT_IO_IAT: u64 = 1516239022;
+
+	fn jwt_config() -> JWTConfig {
+		JWTConfig {
+			signature_method: SignatureMethod::HS256,
+			secret: JWT_IO_SECRET.to_string(),
+			secret_key: String::new(),
+			payload: r#"{ "sub": "{{user.id}}", "name": "John Doe" }"#.to_string(),
+			location: TokenLocation::HTTPHeader(("Authorization".to_string(), "Bearer".to_string())),
+			sample_data: String::new(),
+		}
+	}
+
+	fn jwt_verification() -> JwtVerification {
+		JwtVerification {
+			algorithms: vec![SignatureMethod::HS256],
+			keys: vec![JWT_IO_SECRET.to_string()],
+			issuer: None,
+			audience: None,
+			clock_skew_seconds: 0,
+		}
+	}
+
+	#[test]
+	fn mint_jwt_signs_rendered_claims() {
+		let token = mint_jwt(&jwt_config(), &json!({ "user": { "id": "1234567890" } }), JWT_IO_IAT).unwrap();
+
+		assert_eq!(
+			token,
+			concat!(
+				"eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.",
+				"eyJleHAiOjE1MTYyNDI2MjIsImlhdCI6MTUxNjIzOTAyMiwibmFtZSI6IkpvaG4gRG9lIiwic3ViIjoiMTIzNDU2Nzg5MCJ9.",
+				"LtDmkuKZdFuQ6iyvq3pt3z-M_D3yT4Ob-AmVfeQ2MpA"
+			)
+		);
+	}
+
+	#[test]
+	fn verify_jwt_accepts_known_token() {
+		let claims = verify_jwt(JWT_IO_TOKEN, &jwt_verification(), JWT_IO_IAT).unwrap();
+
+		assert_eq!(claims, json!({ "sub": "1234567890", "name": "John Doe", "iat": JWT_IO_IAT }));
+	}
+
+	#[test]
+	fn verify_jwt_accepts_minted_token() {
+		let token = mint_jwt(&jwt_config(), &json!({ "user": { "id": "42" } }), JWT_IO_IAT).unwrap();
+
+		let claims = verify_jwt(&token, &jwt_verification(), JWT_IO_IAT + 60).unwrap();
+
+		assert_eq!(claims["sub"], "42");
+		assert_eq!(claims["exp"], JWT_IO_IAT + JWT_LIFETIME_SECONDS);
+	}
+
+	#[test]
+	fn verify_jwt_rejects_invalid_tokens() {
+		let mut wrong_key = jwt_verification();
+		wrong_key.keys = vec!["another-secret".to_string()];
+
+		let mut wrong_algorithm = jwt_verification();
+		wrong_algorithm.algorithms = vec![SignatureMethod::HS512];
+
+		let mut wrong_issuer = jwt_verification();
+		wrong_issuer.issuer = Some("https://issuer.example.com".to_string());
+
+		let tampered_token = JWT_IO_TOKEN.replace(".SflK", ".SflL");
+		let expired_token = mint_jwt(&jwt_config(), &json!({}), JWT_IO_IAT).unwrap();
+
+		for (token, config, now_seconds, message) in [
+			(JWT_IO_TOKEN, &wrong_key, JWT_IO_IAT, "INVALID JWT SIGNATURE"),
+			(JWT_IO_TOKEN, &wrong_algorithm, JWT_IO_IAT, "JWT ALGORITHM NOT ALLOWED"),
+			(JWT_IO_TOKEN, &wrong_issuer, JWT_IO_IAT, "INVALID JWT ISSUER"),
+			(&tampered_token, &jwt_verification(), JWT_IO_IAT, "INVALID JWT SIGNATURE"),
+			(&expired_token, &jwt_verification(), JWT_IO_IAT + JWT_LIFETIME_SECONDS + 1, "JWT EXPIRED"),
+			("not-a-token", &jwt_verification(), JWT_IO_IAT, "INVALID JWT"),
+		] {
+			match verify_jwt(token, config, now_seconds) {
+				Err(ApiError::Unauthorized(error)) => assert_eq!(error, message),
+				result => panic!("expected {message}, got {result:?}"),
+			}
+		}
+	}
+}
@@ -0,0 +1,270 @@
+use ic_cdk::api::time;
+use serde_json::Value;
+use sha2::{ Digest, Sha256 };
+use crate::{
+	node_server::URL_API_FORWARDING,
+	types::{ api_error::ApiError, connector::OAuth2Config, oauth2_token::OAuth2Token },
+};
+use super::{ http_outcall::forward_http_request, request_signing::url_encode };
+
+/// Access tokens are refreshed this many seconds before they expire.
+pub static OAUTH2_EXPIRY_MARGIN_SECONDS: u64 = 60;
+
+/// Fingerprint of an OAuth2 configuration. Cached tokens fetched with another configuration are discarded.
+///
+/// # Arguments
+/// - `config` - OAuth2 configuration
+///
+/// # Returns
+/// - `String` - Hex encoded SHA-256 of the configuration
+pub fn oauth2_fingerprint(config: &OAuth2Config) -> String {
+	let mut hasher = Sha256::new();
+
+	for part in [
+		config.token_url.as_str(),
+		config.client_id.as_str(),
+		config.client_secret.as_str(),
+		&config.scopes.join(" "),
+		config.refresh_token.as_deref().unwrap_or_default(),
+	] {
+		hasher.update(part.as_bytes());
+		hasher.update([0]);
+	}
+
+	hasher
+		.finalize()
+		.iter()
+		.map(|byte| format!("{byte:02x}"))
+		.collect()
+}
+
+/// Whether a cached token can still be used.
+///
+/// # Arguments
+/// - `token` - Cached token
+/// - `config` - Current OAuth2 configuration
+/// - `now_seconds` - Current time in seconds
+///
+/// # Returns
+/// - `bool` - Whether the token is valid
+pub fn is_oauth2_token_valid(token: &OAuth2Token, config: &OAuth2Config, now_seconds: u64) -> bool {
+	if token.config_fingerprint != oauth2_fingerprint(config) {
+		return false;
+	}
+
+	match token.expires_at {
+		Some(expires_at) => now_seconds + OAUTH2_EXPIRY_MARGIN_SECONDS < expires_at,
+		None => true,
+	}
+}
+
+/// Form encoded body of a token request. The refresh token grant is used if a refresh token is provided,
+/// the client credentials grant otherwise.
+///
+/// # Arguments
+/// - `config` - OAuth2 configuration
+/// - `refresh_token` - Refresh token
+///
+/// # Returns
+/// - `String` - Request body
+pub fn oauth2_token_request_body(config: &OAuth2Config, refresh_token: Option<&str>) -> String {
+	let mut fields = match refresh_token {
+		Some(refresh_token) => vec![("grant_type", "refresh_token"), ("refresh_token", refresh_token)],
+		None => vec![("grant_type", "client_credentials")],
+	};
+
+	fields.push(("client_id", &config.client_id));
+	fields.push(("client_secret", &config.client_secret));
+
+	let scope = config.scopes.join(" ");
+	if !scope.is_empty() {
+		fields.push(("scope", &scope));
+	}
+
+	fields
+		.iter()
+		.map(|(name, value)| format!("{}={}", url_encode(name), url_encode(value)))
+		.collect::<Vec<String>>()
+		.join("&")
+}
+
+/// Parse the response of a token endpoint.
+///
+/// # Arguments
+/// - `body` - Response body
+/// - `config` - OAuth2 configuration the token was requested with
+/// - `refresh_token` - Refresh token used for the request, kept if the endpoint does not return a new one
+/// - `now_seconds` - Current time in seconds
+///
+/// # Returns
+/// - `OAuth2Token` - Token
+pub fn parse_oauth2_token_response(
+	body: &str,
+	config: &OAuth2Config,
+	refresh_token: Option<String>,
+	now_seconds: u64
+) -> Result<OAuth2Token, ApiError> {
+	let response: Value = serde_json
+		::from_str(body)
+		.map_err(|_| ApiError::Connector(format!("OAUTH2 TOKEN ENDPOINT: INVALID RESPONSE: {body}")))?;
+
+	if let Some(error) = response.get("error") {
+		let error = error.as_str().map(String::from).unwrap_or_else(|| error.to_string());
+
+		return match response.get("error_description").and_then(|description| description.as_str()) {
+			Some(description) => Err(ApiError::Connector(format!("OAUTH2 TOKEN ENDPOINT: {error}: {description}"))),
+			None => Err(ApiError::Connector(format!("OAUTH2 TOKEN ENDPOINT: {error}"))),
+		};
+	}
+
+	let access_token = response
+		.get("access_token")
+		.and_then(|access_token| access_token.as_str())
+		.ok_or(ApiError::Connector("OAUTH2 TOKEN ENDPOINT: MISSING ACCESS TOKEN".to_string()))?;
+
+	Ok(OAuth2Token {
+		access_token: access_token.to_string(),
+		refresh_token: response
+			.get("refresh_token")
+			.and_then(|refresh_token| refresh_token.as_str())
+			.map(String::from)
+			.or(refresh_token),
+		expires_at: response
+			.get("expires_in")
+			.and_then(|expires_in| expires_in.as_u64())
+			.map(|expires_in| now_seconds + expires_in),
+		config_fingerprint: oauth2_fingerprint(config),
+		created_at: now_seconds,
+	})
+}
+
+/// Request a token from the token endpoint, through the node server so the request is only sent once.
+///
+/// # Arguments
+/// - `config` - OAuth2 configuration
+/// - `refresh_token` - Refresh token, the client credentials are used if not provided
+/// - `cycles` - Cycles attached to the token request
+///
+/// # Returns
+/// - `OAuth2Token` - Token
+pub async fn fetch_oauth2_token(
+	config: &OAuth2Config,
+	refresh_token: Option<String>,
+	cycles: u128
+) -> Result<OAuth2Token, ApiError> {
+	let body =
+		serde_json::json!({
+		"url": config.token_url,
+		"method": "POST",
+		"requestBody": oauth2_token_request_body(config, refresh_token.as_deref()),
+		"headers": {
+			"Content-Type": "application/x-www-form-urlencoded",
+			"Accept": "application/json",
+		},
+	});
+
+	let response = forward_http_request(body, cycles, URL_API_FORWARDING).await?;
+
+	parse_oauth2_token_response(&response, config, refresh_token, time() / 1_000_000_000)
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::types::connector::TokenLocation;
+	use super::*;
+
+	static NOW: u64 = 1700000000;
+
+	fn oauth2_config() -> OAuth2Config {
+		OAuth2Config {
+			token_url: "https://auth.example.com/oauth/token".to_string(),
+			client_id: "client".to_string(),
+			client_secret: "secret".to_string(),
+			scopes: vec!["read".to_string(), "write".to_string()],
+			refresh_token: None,
+			location: TokenLocation::HTTPHeader(("Authorization".to_string(), "Bearer".to_string())),
+		}
+	}
+
+	fn connector_error(result: Result<OAuth2Token, ApiError>) -> String {
+		match result {
+			Err(ApiError::Connector(error)) => error,
+			result => panic!("expected a connector error, got {result:?}"),
+		}
+	}
+
+	#[test]
+	fn parse_oauth2_token_response_reads_the_token() {
+		let body = r#"{
+			"access_token": "access-1",
+			"token_type": "Bearer",
+			"expires_in": 3600,
+			"refresh_token": "refresh-2",
+			"scope": "read write"
+		}"#;
+
+		let token = parse_oauth2_token_response(body, &oauth2_config(), Some("refresh-1".to_string()), NOW).unwrap();
+
+		assert_eq!(token.access_token, "access-1");
+		assert_eq!(token.refresh_token, Some("refresh-2".to_string()));
+		assert_eq!(token.expires_at, Some(NOW + 3600));
+		assert_eq!(token.config_fingerprint, oauth2_fingerprint(&oauth2_config()));
+		assert_eq!(token.created_at, NOW);
+		assert!(is_oauth2_token_valid(&token, &oauth2_config(), NOW + 3600 - OAUTH2_EXPIRY_MARGIN_SECONDS - 1));
+		assert!(!is_oauth2_token_valid(&token, &oauth2_config(), NOW + 3600 - OAUTH2_EXPIRY_MARGIN_SECONDS));
+	}
+
+	#[test]
+	fn parse_oauth2_token_response_keeps_the_refresh_token() {
+		let body = r#"{ "access_token": "access-1", "token_type": "Bearer" }"#;
+
+		let token = parse_oauth2_token_response(body, &oauth2_config(), Some("refresh-1".to_string()), NOW).unwrap();
+
+		assert_eq!(token.refresh_token, Some("refresh-1".to_string()));
+		assert_eq!(token.expires_at, None);
+	}
+
+	#[test]
+	fn parse_oauth2_token_response_reports_errors() {
+		let config = oauth2_config();
+
+		assert_eq!(
+			connector_error(
+				parse_oauth2_token_response(
+					r#"{ "error": "invalid_client", "error_description": "Client authentication failed" }"#,
+					&config,
+					None,
+					NOW
+				)
+			),
+			"OAUTH2 TOKEN ENDPOINT: invalid_client: Client authentication failed"
+		);
+		assert_eq!(
+			connector_error(parse_oauth2_token_response(r#"{ "error": "invalid_grant" }"#, &config, None, NOW)),
+			"OAUTH2 TOKEN ENDPOINT: invalid_grant"
+		);
+		assert_eq!(
+			connector_error(parse_oauth2_token_response(r#"{ "token_type": "Bearer" }"#, &config, None, NOW)),
+			"OAUTH2 TOKEN ENDPOINT: MISSING ACCESS TOKEN"
+		);
+		assert_eq!(
+			connector_error(parse_oauth2_token_response("Bad Gateway", &config, None, NOW)),
+			"OAUTH2 TOKEN ENDPOINT: INVALID RESPONSE: Bad Gateway"
+		);
+	}
+
+	#[test]
+	fn oauth2_token_request_body_encodes_the_grant() {
+		assert_eq!(
+			oauth2_token_request_body(&oauth2_config(), None),
+			"grant_type=client_credentials&client_id=client&client_secret=secret&scope=read%20write"
+		);
+		assert_eq!(
+			oauth2_token_request_body(&oauth2_config(), Some("refresh/1")),
+			concat!(
+				"grant_type=refresh_token&refresh_token=refresh%2F1",
+				"&client_id=client&client_secret=secret&scope=read%20write"
+			)
+		);
+	}
+}
@@ -0,0 +1,200 @@
+use std::cmp::Ordering;
+use candid::Principal;
+use serde_json::{ Map, Value };
+use crate::{
+	types::node::{
+		Condition,
+		ConditionGroup,
+		DataType,
+		FilterPin,
+		MapperPin,
+		Operator,
+		OperandType,
+		Pin,
+		PinType,
+		Rule,
+	},
+	utils::template::lookup_path,
+};
+
+/// Whether the data passes a filter. Rules are combined with the condition group, `And` if not provided,
+/// and the result is negated for the `Not` condition. A filter without rules always passes.
+///
+/// # Arguments
+/// - `filter` - Filter
+/// - `data` - Data to filter
+///
+/// # Returns
+/// - `bool` - Whether the data passes
+pub fn evaluate_filter(filter: &FilterPin, data: &Value) -> bool {
+	if filter.rules.is_empty() {
+		return true;
+	}
+
+	let mut results = filter.rules.iter().map(|rule| evaluate_rule(rule, data));
+
+	let is_match = match filter.condition_group {
+		Some(ConditionGroup::Or) => results.any(|result| result),
+		Some(ConditionGroup::And) | None => results.all(|result| result),
+	};
+
+	match filter.condition {
+		Condition::Is => is_match,
+		Condition::Not => !is_match,
+	}
+}
+
+/// Map data to a different format. Every field maps the value at an input path to an output path of the mapped data.
+/// Fields without a value are left out.
+///
+/// # Arguments
+/// - `mapper` - Mapper
+/// - `data` - Data to map
+///
+/// # Returns
+/// - `Value` - Mapped data
+pub fn apply_mapper(mapper: &MapperPin, data: &Value) -> Value {
+	let mut mapped = Value::Object(Map::new());
+
+	for (input, output) in mapper.fields.iter() {
+		if let Some(value) = lookup_path(data, input) {
+			set_path(&mut mapped, output, value.clone());
+		}
+	}
+
+	mapped
+}
+
+/// Apply the lookup pins of a node to the reply of its lookup. The reply is dropped if it does not pass the lookup
+/// filter. The lookup transform selects the part of the reply to merge and the path it is merged at, by default the
+/// whole reply is merged at `lookup_{node_id}`.
+///
+/// # Arguments
+/// - `pins` - Pins of the lookup node
+/// - `node_id` - ID of the lookup node
+/// - `reply` - Reply of the lookup
+///
+/// # Returns
+/// - `Option<(String, Value)>` - Path and value to merge into the data, if the reply passes the filter
+pub fn transform_lookup_reply(pins: &[Pin], node_id: u32, reply: &Value) -> Option<(String, Value)> {
+	let passes_filter = pins.iter().all(|pin| {
+		match &pin.pin_type {
+			PinType::LookupFilterPin(filter) => evaluate_filter(filter, reply),
+			_ => true,
+		}
+	});
+
+	if !passes_filter {
+		return None;
+	}
+
+	let transform = pins.iter().find_map(|pin| {
+		match &pin.pin_type {
+			PinType::LookupTransformPin(transform) => Some(transform),
+			_ => None,
+		}
+	});
+
+	let (value, output) = match transform {
+		Some(transform) if !transform.input.is_empty() => {
+			(lookup_path(reply, &transform.input).cloned().unwrap_or(Value::Null), transform.output.clone())
+		}
+		Some(transform) => (reply.clone(), transform.output.clone()),
+		None => (reply.clone(), String::new()),
+	};
+
+	match output.is_empty() {
+		true => Some((format!("lookup_{node_id}"), value)),
+		false => Some((output, value)),
+	}
+}
+
+/// Set a value at a dot separated path, creating the objects on the way. Values in the way that are not objects are
+/// replaced.
+///
+/// # Arguments
+/// - `data` - Data, updated in place
+/// - `path` - Dot separated path, e.g. `user.address.city`
+/// - `value` - Value to set
+pub fn set_path(data: &mut Value, path: &str, value: Value) {
+	let mut target = data;
+
+	for segment in path.split('.') {
+		if !target.is_object() {
+			*target = Value::Object(Map::new());
+		}
+
+		target = target
+			.as_object_mut()
+			.map(|object| object.entry(segment).or_insert(Value::Null))
+			.expect("target is an object");
+	}
+
+	*target = value;
+}
+
+/// Whether the data matches a rule. Rules on fields without a value never match.
+fn evaluate_rule(rule: &Rule, data: &Value) -> bool {
+	let Some(left) = lookup_path(data, &rule.field) else {
+		return false;
+	};
+
+	let right = match rule.operand.operand_type {
+		OperandType::Value => Value::String(rule.value.clone()),
+		OperandType::Field => {
+			match lookup_path(data, &rule.value) {
+				Some(value) => value.clone(),
+				None => {
+					return false;
+				}
+			}
+		}
+	};
+
+	if rule.operator == Operator::Contains {
+		return match left {
+			Value::Array(items) => items.iter().any(|item| text(item) == text(&right)),
+			_ => text(left).contains(&text(&right)),
+		};
+	}
+
+	let Some(ordering) = compare(left, &right, &rule.operand.data_type) else {
+		return false;
+	};
+
+	match rule.operator {
+		Operator::Equal => ordering == Ordering::Equal,
+		Operator::NotEqual => ordering != Ordering::Equal,
+		Operator::GreaterThan => ordering == Ordering::Greater,
+		Operator::LessThan => ordering == Ordering::Less,
+		Operator::GreaterThanOrEqual => ordering != Ordering::Less,
+		Operator::LessThanOrEqual => ordering != Ordering::Greater,
+		Operator::Contains => false,
+	}
+}
+
+/// Compare two values as the given data type, `None` if either value cannot be read as that type.
+fn compare(left: &Value, right: &Value, data_type: &DataType) -> Option<Ordering> {
+	let (left, right) = (text(left), text(right));
+
+	match data_type {
+		DataType::String => Some(left.cmp(&right)),
+		DataType::Number => left.trim().parse::<f64>().ok()?.partial_cmp(&right.trim().parse::<f64>().ok()?),
+		DataType::BigInt => Some(left.trim().parse::<i128>().ok()?.cmp(&right.trim().parse::<i128>().ok()?)),
+		DataType::Boolean => Some(left.trim().parse::<bool>().ok()?.cmp(&right.trim().parse::<bool>().ok()?)),
+		DataType::Principal => {
+			let left = Principal::from_text(left.trim()).ok()?;
+			let right = Principal::from_text(right.trim()).ok()?;
+
+			Some(left.cmp(&right))
+		}
+	}
+}
+
+/// Text form of a value. Strings are used as-is, other values as JSON.
+fn text(value: &Value) -> String {
+	match value {
+		Value::String(text) => text.clone(),
+		value => value.to_string(),
+	}
+}
@@ -0,0 +1,533 @@
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use hmac::{ Hmac, Mac };
+use percent_encoding::{ percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC };
+use serde_json::{ json, Value };
+use sha2::{ Digest, Sha256, Sha384, Sha512 };
+use crate::types::{
+	api_error::ApiError,
+	connector::{ Authentication, AwsSigV4Config, DigestEncoding, HmacAlgorithm, HmacSigningConfig, TokenLocation },
+	headers::Headers,
+	node::HttpRequestMethod,
+};
+use super::{ jwt::mint_jwt, template::render_template };
+
+/// Characters left as-is in query parameters, the unreserved characters of RFC 3986.
+static QUERY_ENCODE_SET: &AsciiSet = &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~');
+
+/// Apply the authentication of a connector to an outgoing request. Signatures are computed from the given time, so
+/// the same request signed at the same time gives the same result.
+///
+/// # Arguments
+/// - `authentication` - Authentication of the connector
+/// - `method` - Method of the request
+/// - `url` - URL of the request
+/// - `headers` - Headers of the request
+/// - `body` - Body of the request
+/// - `data` - Data used to render a JWT payload. The sample data of the JWT configuration is used if not provided
+/// - `now_seconds` - Current time in seconds
+///
+/// # Returns
+/// - `(String, Headers)` - Authenticated URL and headers
+pub fn apply_authentication(
+	authentication: &Authentication,
+	method: &HttpRequestMethod,
+	url: String,
+	mut headers: Headers,
+	body: Option<&str>,
+	data: Option<&Value>,
+	now_seconds: u64
+) -> Result<(String, Headers), ApiError> {
+	match authentication {
+		Authentication::None => Ok((url, headers)),
+		Authentication::Basic(username, password) => {
+			headers.insert("Authorization".to_string(), basic_authorization(username, password));
+
+			Ok((url, headers))
+		}
+		Authentication::Token(token_config) => {
+			Ok(apply_token(&token_config.token, &token_config.location, url, headers))
+		}
+		// Access tokens are fetched asynchronously by the caller, see `apply_token`
+		Authentication::OAuth2(_) => Err(ApiError::BadRequest("OAUTH2 ACCESS TOKEN NOT AVAILABLE".to_string())),
+		Authentication::JWT(jwt_config) => {
+			let token = match data {
+				Some(data) => mint_jwt(jwt_config, data, now_seconds)?,
+				None => {
+					let sample_data = serde_json::from_str(&jwt_config.sample_data).unwrap_or(Value::Null);
+					mint_jwt(jwt_config, &sample_data, now_seconds)?
+				}
+			};
+
+			Ok(apply_token(&token, &jwt_config.location, url, headers))
+		}
+		Authentication::AwsSigV4(aws_config) => {
+			sign_aws_sigv4(aws_config, method, &url, &mut headers, body.unwrap_or_default(), now_seconds)?;
+
+			Ok((url, headers))
+		}
+		Authentication::Hmac(hmac_config) => {
+			sign_hmac(hmac_config, method, &url, &mut headers, body.unwrap_or_default(), now_seconds)?;
+
+			Ok((url, headers))
+		}
+	}
+}
+
+/// Sign a request with AWS Signature Version 4. Adds the `Authorization` and `X-Amz-Date` headers,
+/// `X-Amz-Content-Sha256` for S3 and `X-Amz-Security-Token` for temporary credentials.
+/// The host, `Content-Type` and `X-Amz-*` headers are signed.
+///
+/// # Arguments
+/// - `config` - AWS configuration
+/// - `method` - Method of the request
+/// - `url` - URL of the request
+/// - `headers` - Headers of the request
+/// - `body` - Body of the request
+/// - `now_seconds` - Current time in seconds
+pub fn sign_aws_sigv4(
+	config: &AwsSigV4Config,
+	method: &HttpRequestMethod,
+	url: &str,
+	headers: &mut Headers,
+	body: &str,
+	now_seconds: u64
+) -> Result<(), ApiError> {
+	let parts = UrlParts::parse(url)?;
+	let amz_date = format_amz_date(now_seconds);
+	let date = &amz_date[..8];
+	let payload_hash = hex::encode(Sha256::digest(body.as_bytes()));
+
+	// Headers set by the signature replace any given in another case, the host is taken from the URL
+	headers.retain(|name, _| {
+		!["authorization", "host", "x-amz-date", "x-amz-content-sha256", "x-amz-security-token"]
+			.iter()
+			.any(|replaced| name.eq_ignore_ascii_case(replaced))
+	});
+	headers.insert("X-Amz-Date".to_string(), amz_date.clone());
+
+	// S3 requires the payload hash as a header
+	if config.service == "s3" {
+		headers.insert("X-Amz-Content-Sha256".to_string(), payload_hash.clone());
+	}
+
+	if let Some(session_token) = &config.session_token {
+		headers.insert("X-Amz-Security-Token".to_string(), session_token.clone());
+	}
+
+	let mut signed_headers = headers
+		.iter()
+		.filter(|(name, _)| is_aws_signed_header(name))
+		.map(|(name, value)| (name.to_lowercase(), value.split_whitespace().collect::<Vec<&str>>().join(" ")))
+		.collect::<Vec<(String, String)>>();
+	signed_headers.push(("host".to_string(), parts.host.clone()));
+	signed_headers.sort();
+
+	let canonical_headers = signed_headers
+		.iter()
+		.map(|(name, value)| format!("{name}:{value}\n"))
+		.collect::<String>();
+	let signed_header_names = signed_headers
+		.iter()
+		.map(|(name, _)| name.as_str())
+		.collect::<Vec<&str>>()
+		.join(";");
+
+	// Paths are encoded twice for every service except S3
+	let mut canonical_path = canonical_uri_path(parts.path);
+	if config.service != "s3" {
+		canonical_path = canonical_path
+			.split('/')
+			.map(url_encode)
+			.collect::<Vec<String>>()
+			.join("/");
+	}
+
+	let canonical_request = [
+		method.name(),
+		&canonical_path,
+		&canonical_query(parts.query),
+		&canonical_headers,
+		&signed_header_names,
+		&payload_hash,
+	].join("\n");
+
+	let scope = format!("{date}/{}/{}/aws4_request", config.region, config.service);
+	let string_to_sign = format!(
+		"AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+		hex::encode(Sha256::digest(canonical_request.as_bytes()))
+	);
+
+	let mut signing_key = format!("AWS4{}", config.secret_access_key).into_bytes();
+	for part in [date, &config.region, &config.service, "aws4_request"] {
+		signing_key = hmac_sha256(&signing_key, part.as_bytes());
+	}
+	let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+	headers.insert(
+		"Authorization".to_string(),
+		format!(
+			"AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_header_names}, Signature={signature}",
+			config.access_key_id
+		)
+	);
+
+	Ok(())
+}
+
+/// Sign a request with an HMAC of the canonical string rendered from the template of the configuration.
+///
+/// # Arguments
+/// - `config` - HMAC configuration
+/// - `method` - Method of the request
+/// - `url` - URL of the request
+/// - `headers` - Headers of the request
+/// - `body` - Body of the request
+/// - `now_seconds` - Current time in seconds
+pub fn sign_hmac(
+	config: &HmacSigningConfig,
+	method: &HttpRequestMethod,
+	url: &str,
+	headers: &mut Headers,
+	body: &str,
+	now_seconds: u64
+) -> Result<(), ApiError> {
+	let parts = UrlParts::parse(url)?;
+
+	let data =
+		json!({
+		"method": method.name(),
+		"url": url,
+		"path": parts.path,
+		"query": parts.query,
+		"body": body,
+		"body_sha256": hex::encode(Sha256::digest(body.as_bytes())),
+		"timestamp": now_seconds.to_string(),
+	});
+	let canonical_string = render_template(&config.canonical_template, &data);
+
+	let signature = hmac_digest(&config.algorithm, config.secret.as_bytes(), canonical_string.as_bytes())?;
+
+	headers.insert(
+		config.header_name.clone(),
+		format!("{}{}", config.prefix.clone().unwrap_or_default(), encode_digest(&config.encoding, &signature))
+	);
+
+	if let Some(timestamp_header) = &config.timestamp_header {
+		headers.insert(timestamp_header.clone(), now_seconds.to_string());
+	}
+
+	Ok(())
+}
+
+/// Compute an HMAC.
+///
+/// # Arguments
+/// - `algorithm` - Hash algorithm
+/// - `secret` - Secret key
+/// - `message` - Message to sign
+///
+/// # Returns
+/// - `Vec<u8>` - Signature
+pub fn hmac_digest(algorithm: &HmacAlgorithm, secret: &[u8], message: &[u8]) -> Result<Vec<u8>, ApiError> {
+	let invalid_key = |_| ApiError::BadRequest("INVALID HMAC SECRET".to_string());
+
+	Ok(match algorithm {
+		HmacAlgorithm::SHA256 => {
+			let mut mac = Hmac::<Sha256>::new_from_slice(secret).map_err(invalid_key)?;
+			mac.update(message);
+			mac.finalize().into_bytes().to_vec()
+		}
+		HmacAlgorithm::SHA384 => {
+			let mut mac = Hmac::<Sha384>::new_from_slice(secret).map_err(invalid_key)?;
+			mac.update(message);
+			mac.finalize().into_bytes().to_vec()
+		}
+		HmacAlgorithm::SHA512 => {
+			let mut mac = Hmac::<Sha512>::new_from_slice(secret).map_err(invalid_key)?;
+			mac.update(message);
+			mac.finalize().into_bytes().to_vec()
+		}
+	})
+}
+
+/// Encode a digest as text.
+///
+/// # Arguments
+/// - `encoding` - Encoding
+/// - `digest` - Digest
+///
+/// # Returns
+/// - `String` - Encoded digest
+pub fn encode_digest(encoding: &DigestEncoding, digest: &[u8]) -> String {
+	match encoding {
+		DigestEncoding::Hex => hex::encode(digest),
+		DigestEncoding::Base64 => STANDARD.encode(digest),
+	}
+}
+
+/// Place a token in the configured header or query parameter.
+///
+/// # Arguments
+/// - `token` - Token
+/// - `location` - Location of the token
+/// - `url` - URL of the request
+/// - `headers` - Headers of the request
+///
+/// # Returns
+/// - `(String, Headers)` - URL and headers containing the token
+pub fn apply_token(token: &str, location: &TokenLocation, url: String, mut headers: Headers) -> (String, Headers) {
+	match location {
+		TokenLocation::HTTPHeader((header_name, header_scheme)) => {
+			let value = match header_scheme.trim() {
+				"" => token.to_string(),
+				scheme => format!("{scheme} {token}"),
+			};
+
+			headers.insert(header_name.clone(), value);
+
+			(url, headers)
+		}
+		TokenLocation::Query(parameter) => (append_query_parameter(&url, parameter, token), headers),
+	}
+}
+
+/// Value of the `Authorization` header for basic authentication.
+///
+/// # Arguments
+/// - `username` - Username
+/// - `password` - Password
+///
+/// # Returns
+/// - `String` - Header value
+pub fn basic_authorization(username: &str, password: &str) -> String {
+	format!("Basic {}", STANDARD.encode(format!("{username}:{password}")))
+}
+
+/// Append a URL-encoded query parameter to a URL, keeping an existing query string and fragment intact.
+///
+/// # Arguments
+/// - `url` - URL
+/// - `name` - Name of the parameter
+/// - `value` - Value of the parameter
+///
+/// # Returns
+/// - `String` - URL with the parameter
+pub fn append_query_parameter(url: &str, name: &str, value: &str) -> String {
+	let (base, fragment) = match url.split_once('#') {
+		Some((base, fragment)) => (base, Some(fragment)),
+		None => (url, None),
+	};
+
+	let separator = match base.contains('?') {
+		true if base.ends_with('?') || base.ends_with('&') => "",
+		true => "&",
+		false => "?",
+	};
+
+	let name = url_encode(name);
+	let value = url_encode(value);
+
+	match fragment {
+		Some(fragment) => format!("{base}{separator}{name}={value}#{fragment}"),
+		None => format!("{base}{separator}{name}={value}"),
+	}
+}
+
+/// URL-encode a query parameter or form field, leaving only the unreserved characters of RFC 3986 as-is.
+///
+/// # Arguments
+/// - `value` - Value to encode
+///
+/// # Returns
+/// - `String` - Encoded value
+pub fn url_encode(value: &str) -> String {
+	utf8_percent_encode(value, QUERY_ENCODE_SET).to_string()
+}
+
+/// Host, path and query of a URL.
+struct UrlParts<'a> {
+	host: String,
+	path: &'a str,
+	query: &'a str,
+}
+
+impl<'a> UrlParts<'a> {
+	fn parse(url: &'a str) -> Result<Self, ApiError> {
+		let (_, rest) = url.split_once("://").ok_or(ApiError::BadRequest(format!("INVALID URL: {url}")))?;
+		let rest = rest.split('#').next().unwrap_or_default();
+
+		let authority_end = rest.find(['/', '?']).unwrap_or(rest.len());
+		let authority = &rest[..authority_end];
+		let host = authority.rsplit('@').next().unwrap_or_default().to_lowercase();
+
+		if host.is_empty() {
+			return Err(ApiError::BadRequest(format!("INVALID URL: {url}")));
+		}
+
+		let (path, query) = rest[authority_end..].split_once('?').unwrap_or((&rest[authority_end..], ""));
+
+		Ok(Self { host, path, query })
+	}
+}
+
+fn hmac_sha256(key: &[u8], message: &[u8]) -> Vec<u8> {
+	let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+	mac.update(message);
+	mac.finalize().into_bytes().to_vec()
+}
+
+fn is_aws_signed_header(name: &str) -> bool {
+	let name = name.to_lowercase();
+
+	name == "host" || name == "content-type" || name.starts_with("x-amz-")
+}
+
+fn canonical_uri_path(path: &str) -> String {
+	match path {
+		"" => "/".to_string(),
+		path => {
+			path.split('/')
+				.map(|segment| url_encode(&percent_decode_str(segment).decode_utf8_lossy()))
+				.collect::<Vec<String>>()
+				.join("/")
+		}
+	}
+}
+
+fn canonical_query(query: &str) -> String {
+	let mut parameters = query
+		.split('&')
+		.filter(|parameter| !parameter.is_empty())
+		.map(|parameter| {
+			let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+			let decode = |value: &str| percent_decode_str(&value.replace('+', " ")).decode_utf8_lossy().to_string();
+
+			(url_encode(&decode(name)), url_encode(&decode(value)))
+		})
+		.collect::<Vec<(String, String)>>();
+	parameters.sort();
+
+	parameters
+		.into_iter()
+		.map(|(name, value)| format!("{name}={value}"))
+		.collect::<Vec<String>>()
+		.join("&")
+}
+
+/// Format a time as `YYYYMMDDTHHMMSSZ`.
+fn format_amz_date(now_seconds: u64) -> String {
+	let days = (now_seconds / 86_400) as i64;
+	let seconds_of_day = now_seconds % 86_400;
+
+	// Civil date from days since the Unix epoch, see http://howardhinnant.github.io/date_algorithms.html
+	let z = days + 719_468;
+	let era = z.div_euclid(146_097);
+	let day_of_era = z.rem_euclid(146_097);
+	let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+	let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+	let month_index = (5 * day_of_year + 2) / 153;
+	let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+	let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+	let year = year_of_era + era * 400 + (if month <= 2 { 1 } else { 0 });
+
+	format!(
+		"{year:04}{month:02}{day:02}T{:02}{:02}{:02}Z",
+		seconds_of_day / 3600,
+		(seconds_of_day % 3600) / 60,
+		seconds_of_day % 60
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// Credentials and time of the AWS Signature Version 4 test suite, 20150830T123600Z
+	static AWS_TEST_SUITE_TIME: u64 = 1440938160;
+
+	fn aws_config() -> AwsSigV4Config {
+		AwsSigV4Config {
+			access_key_id: "AKIDEXAMPLE".to_string(),
+			secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+			region: "us-east-1".to_string(),
+			service: "service".to_string(),
+			session_token: None,
+		}
+	}
+
+	fn aws_signature(method: HttpRequestMethod, url: &str, mut headers: Headers, body: &str) -> String {
+		sign_aws_sigv4(&aws_config(), &method, url, &mut headers, body, AWS_TEST_SUITE_TIME).unwrap();
+
+		headers["Authorization"].rsplit("Signature=").next().unwrap().to_string()
+	}
+
+	#[test]
+	fn sign_aws_sigv4_get_vanilla() {
+		let mut headers = Headers::new();
+		sign_aws_sigv4(
+			&aws_config(),
+			&HttpRequestMethod::GET,
+			"https://example.amazonaws.com/",
+			&mut headers,
+			"",
+			AWS_TEST_SUITE_TIME
+		).unwrap();
+
+		assert_eq!(headers["X-Amz-Date"], "20150830T123600Z");
+		assert_eq!(
+			headers["Authorization"],
+			concat!(
+				"AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, ",
+				"SignedHeaders=host;x-amz-date, ",
+				"Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+			)
+		);
+	}
+
+	#[test]
+	fn sign_aws_sigv4_sorts_query_parameters() {
+		let signature = aws_signature(
+			HttpRequestMethod::GET,
+			"https://example.amazonaws.com/?Param2=value2&Param1=value1",
+			Headers::new(),
+			""
+		);
+
+		assert_eq!(signature, "b97d918cfa904a5beff61c982a1b6f458b799221646efd99d3219ec94cdf2500");
+	}
+
+	#[test]
+	fn sign_aws_sigv4_post_form() {
+		let headers = Headers::from([("Content-Type".to_string(), "application/x-www-form-urlencoded".to_string())]);
+
+		let signature = aws_signature(
+			HttpRequestMethod::POST,
+			"https://example.amazonaws.com/",
+			headers,
+			"Param1=value1"
+		);
+
+		assert_eq!(signature, "ff11897932ad3f4e8b18135d722051e5ac45fc38421b1da7b9d196a0fe09473a");
+	}
+
+	#[test]
+	fn sign_hmac_github_webhook() {
+		let config = HmacSigningConfig {
+			algorithm: HmacAlgorithm::SHA256,
+			secret: "It's a Secret to Everybody".to_string(),
+			header_name: "X-Hub-Signature-256".to_string(),
+			canonical_template: "{{body}}".to_string(),
+			encoding: DigestEncoding::Hex,
+			prefix: Some("sha256=".to_string()),
+			timestamp_header: None,
+		};
+
+		let mut headers = Headers::new();
+		sign_hmac(&config, &HttpRequestMethod::POST, "https://example.com/", &mut headers, "Hello, World!", 0).unwrap();
+
+		assert_eq!(
+			headers["X-Hub-Signature-256"],
+			"sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+		);
+	}
+}
@@ -0,0 +1,52 @@
+use ic_stable_structures::{ Memory, StableBTreeMap };
+
+/// Named counters used to allocate IDs. A counter holds the last allocated ID of its sequence.
+pub type Sequences<M> = StableBTreeMap<String, u32, M>;
+
+pub static ADMIN_AUDIT_SEQUENCE: &str = "admin_audit";
+pub static CIRCUITS_SEQUENCE: &str = "circuits";
+pub static CONNECTORS_SEQUENCE: &str = "connectors";
+pub static NODES_SEQUENCE: &str = "nodes";
+pub static PINS_SEQUENCE: &str = "pins";
+pub static TRACES_SEQUENCE: &str = "traces";
+pub static WORKSPACES_SEQUENCE: &str = "workspaces";
+
+/// Allocate the next ID of a sequence. IDs are never reused, even if the entity holding it is deleted.
+///
+/// # Arguments
+/// - `sequences` - Sequences storage
+/// - `name` - Name of the sequence
+///
+/// # Returns
+/// - `u32` - Allocated ID
+pub fn next_id<M: Memory>(sequences: &mut Sequences<M>, name: &str) -> u32 {
+	let id = current_id(sequences, name) + 1;
+	sequences.insert(name.to_string(), id);
+
+	id
+}
+
+/// Get the last allocated ID of a sequence.
+///
+/// # Arguments
+/// - `sequences` - Sequences storage
+/// - `name` - Name of the sequence
+///
+/// # Returns
+/// - `u32` - Last allocated ID, 0 if nothing has been allocated yet
+pub fn current_id<M: Memory>(sequences: &Sequences<M>, name: &str) -> u32 {
+	sequences.get(&name.to_string()).unwrap_or(0)
+}
+
+/// Move a sequence forward so it never allocates an ID lower than or equal to `id`.
+/// Used to seed counters from data that was created before sequences existed.
+///
+/// # Arguments
+/// - `sequences` - Sequences storage
+/// - `name` - Name of the sequence
+/// - `id` - Highest ID in use
+pub fn seed<M: Memory>(sequences: &mut Sequences<M>, name: &str, id: u32) {
+	if id > current_id(sequences, name) {
+		sequences.insert(name.to_string(), id);
+	}
+}
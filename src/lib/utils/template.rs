@@ -0,0 +1,88 @@
+use serde_json::Value;
+
+/// Render a template by replacing `{{path.to.field}}` placeholders with the values found in the data.
+/// Strings are inserted as-is, other values as JSON. Placeholders without a value are left untouched.
+///
+/// # Arguments
+/// - `template` - Template
+/// - `data` - Data to take the values from
+///
+/// # Returns
+/// - `String` - Rendered template
+pub fn render_template(template: &str, data: &Value) -> String {
+	let mut rendered = String::with_capacity(template.len());
+	let mut rest = template;
+
+	while let Some(start) = rest.find("{{") {
+		let Some(end) = rest[start..].find("}}") else {
+			break;
+		};
+
+		let path = rest[start + 2..start + end].trim();
+
+		rendered.push_str(&rest[..start]);
+
+		match lookup_path(data, path) {
+			Some(Value::String(value)) => rendered.push_str(value),
+			Some(value) => rendered.push_str(&value.to_string()),
+			None => rendered.push_str(&rest[start..start + end + 2]),
+		}
+
+		rest = &rest[start + end + 2..];
+	}
+
+	rendered.push_str(rest);
+	rendered
+}
+
+/// Find a value by its dot separated path. Array items are addressed by their index.
+///
+/// # Arguments
+/// - `data` - Data
+/// - `path` - Dot separated path, e.g. `user.addresses.0.city`
+///
+/// # Returns
+/// - `Option<&Value>` - Value, if found
+pub fn lookup_path<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+	if path.is_empty() {
+		return None;
+	}
+
+	path.split('.').try_fold(data, |value, segment| {
+		match value {
+			Value::Object(object) => object.get(segment),
+			Value::Array(array) => segment.parse::<usize>().ok().and_then(|index| array.get(index)),
+			_ => None,
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+	use super::*;
+
+	#[test]
+	fn render_template_replaces_placeholders() {
+		let data = json!({
+			"user": { "name": "Ada", "age": 36, "roles": ["admin", "dev"], "address": { "city": "London" } },
+		});
+
+		assert_eq!(render_template("Hello {{user.name}}!", &data), "Hello Ada!");
+		assert_eq!(render_template("{{ user.address.city }}, {{user.age}}", &data), "London, 36");
+		assert_eq!(render_template("{{user.roles.1}}", &data), "dev");
+		assert_eq!(render_template("{{user.roles}}", &data), r#"["admin","dev"]"#);
+		assert_eq!(render_template("{{user.address}}", &data), r#"{"city":"London"}"#);
+	}
+
+	#[test]
+	fn render_template_keeps_unknown_placeholders() {
+		let data = json!({ "user": { "name": "Ada" } });
+
+		assert_eq!(render_template("{{secret.API_KEY}} {{user.name}}", &data), "{{secret.API_KEY}} Ada");
+		assert_eq!(render_template("{{user.roles.0}}", &data), "{{user.roles.0}}");
+		assert_eq!(render_template("{{}}", &data), "{{}}");
+		assert_eq!(render_template("{{user.name", &data), "{{user.name");
+		assert_eq!(render_template("no placeholders", &data), "no placeholders");
+	}
+}
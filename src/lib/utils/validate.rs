@@ -1,5 +1,5 @@
 use candid::Principal;
-use crate::{ types::api_error::ApiError, whitelist::whitelist };
+use crate::types::{ api_error::ApiError, share::Role };
 
 /// Validate anonymous.
 ///
@@ -18,17 +18,18 @@ pub fn validate_anonymous(principal: &Principal) -> Result<Principal, ApiError>
 	})
 }
 
-/// Validate admin.
+/// Validate role.
 ///
 /// # Arguments
-/// - `principal` - Principal
+/// - `role` - Role of the caller
+/// - `required_role` - Minimum role required
 ///
 /// # Returns
-/// - `Result<Principal, ApiError>` - Principal or ApiError
-pub fn validate_admin(principal: &Principal) -> Result<Principal, ApiError> {
-	if !whitelist().contains(principal) {
+/// - `Result<(), ApiError>` - Unit or ApiError
+pub fn validate_role(role: &Role, required_role: Role) -> Result<(), ApiError> {
+	if *role < required_role {
 		return Err(ApiError::Unauthorized("UNAUTHORIZED".to_string()));
 	}
 
-	Ok(*principal)
+	Ok(())
 }
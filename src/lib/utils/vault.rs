@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use chacha20poly1305::{ aead::{ Aead, KeyInit, Payload }, ChaCha20Poly1305, Nonce };
+use hkdf::Hkdf;
+use serde_json::json;
+use sha2::Sha256;
+use crate::types::api_error::ApiError;
+use super::template::render_template;
+
+/// Length of the nonce of an encrypted secret.
+pub static SECRET_NONCE_LENGTH: usize = 12;
+
+/// Derive the key encrypting the secrets of an owner from the master key of the canister. The master key is stored
+/// in the canister itself, so the encryption only obfuscates the secrets in stable memory.
+///
+/// # Arguments
+/// - `master_key` - Master key of the canister
+/// - `owner` - Owner of the secrets
+///
+/// # Returns
+/// - `[u8; 32]` - Key of the owner
+pub fn derive_secret_key(master_key: &[u8], owner: &str) -> [u8; 32] {
+	let mut key = [0u8; 32];
+
+	Hkdf::<Sha256>
+		::new(None, master_key)
+		.expand(format!("secret-vault:{owner}").as_bytes(), &mut key)
+		.expect("32 bytes is a valid HKDF-SHA256 output length");
+
+	key
+}
+
+/// Encrypt a secret. The name of the secret is authenticated with it, so ciphertexts cannot be swapped.
+///
+/// # Arguments
+/// - `key` - Key of the owner
+/// - `nonce` - Unique nonce of `SECRET_NONCE_LENGTH` bytes
+/// - `name` - Name of the secret
+/// - `value` - Value of the secret
+///
+/// # Returns
+/// - `Vec<u8>` - Ciphertext
+pub fn encrypt_secret(key: &[u8; 32], nonce: &[u8], name: &str, value: &str) -> Result<Vec<u8>, ApiError> {
+	if nonce.len() != SECRET_NONCE_LENGTH {
+		return Err(ApiError::BadRequest("INVALID NONCE".to_string()));
+	}
+
+	ChaCha20Poly1305::new(key.into())
+		.encrypt(Nonce::from_slice(nonce), Payload { msg: value.as_bytes(), aad: name.as_bytes() })
+		.map_err(|_| ApiError::BadRequest("FAILED TO ENCRYPT SECRET".to_string()))
+}
+
+/// Decrypt a secret.
+///
+/// # Arguments
+/// - `key` - Key of the owner
+/// - `nonce` - Nonce the secret was encrypted with
+/// - `name` - Name of the secret
+/// - `ciphertext` - Ciphertext
+///
+/// # Returns
+/// - `String` - Value of the secret
+pub fn decrypt_secret(key: &[u8; 32], nonce: &[u8], name: &str, ciphertext: &[u8]) -> Result<String, ApiError> {
+	if nonce.len() != SECRET_NONCE_LENGTH {
+		return Err(ApiError::BadRequest("INVALID NONCE".to_string()));
+	}
+
+	let value = ChaCha20Poly1305::new(key.into())
+		.decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: name.as_bytes() })
+		.map_err(|_| ApiError::BadRequest("FAILED TO DECRYPT SECRET".to_string()))?;
+
+	String::from_utf8(value).map_err(|_| ApiError::BadRequest("FAILED TO DECRYPT SECRET".to_string()))
+}
+
+/// Whether a name can be used for a secret: letters, digits and underscores.
+pub fn is_valid_secret_name(name: &str) -> bool {
+	!name.is_empty() && name.chars().all(|char| char.is_ascii_alphanumeric() || char == '_')
+}
+
+/// Names of the secrets referenced as `{{secret.NAME}}` in a text, without duplicates.
+///
+/// # Arguments
+/// - `text` - Text
+///
+/// # Returns
+/// - `Vec<String>` - Names of the referenced secrets
+pub fn secret_references(text: &str) -> Vec<String> {
+	let mut names: Vec<String> = vec![];
+	let mut rest = text;
+
+	while let Some(start) = rest.find("{{") {
+		let Some(end) = rest[start..].find("}}") else {
+			break;
+		};
+
+		if let Some(name) = rest[start + 2..start + end].trim().strip_prefix("secret.") {
+			if is_valid_secret_name(name) && !names.iter().any(|known| known == name) {
+				names.push(name.to_string());
+			}
+		}
+
+		rest = &rest[start + end + 2..];
+	}
+
+	names
+}
+
+/// Replace the `{{secret.NAME}}` references in a text. Other placeholders are left untouched.
+///
+/// # Arguments
+/// - `text` - Text
+/// - `secrets` - Values of the secrets by name
+///
+/// # Returns
+/// - `String` - Rendered text
+pub fn render_secrets(text: &str, secrets: &HashMap<String, String>) -> String {
+	if secrets.is_empty() {
+		return text.to_string();
+	}
+
+	render_template(text, &json!({ "secret": secrets }))
+}
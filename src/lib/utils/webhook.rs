@@ -0,0 +1,227 @@
+use base64::{ engine::general_purpose::STANDARD, Engine };
+use serde_json::{ json, Value };
+use crate::types::{
+	api_error::ApiError,
+	connector::DigestEncoding,
+	headers::Headers,
+	node::{ HmacSignature, JwtVerification },
+};
+use super::{ jwt::verify_jwt, request_signing::hmac_digest, template::render_template };
+
+/// Field of the input data holding the verified authentication, e.g. `{{auth.claims.sub}}`.
+pub static AUTH_FIELD: &str = "auth";
+
+/// Verify the HMAC signature of an incoming webhook.
+///
+/// # Arguments
+/// - `config` - Signature configuration of the input node
+/// - `secret` - Signing secret, resolved from the vault
+/// - `headers` - Headers of the webhook
+/// - `body` - Raw body of the webhook
+/// - `now_seconds` - Current time in seconds, used for replay protection
+///
+/// # Returns
+/// - `()` - If the signature is valid
+pub fn verify_hmac_signature(
+	config: &HmacSignature,
+	secret: &str,
+	headers: &Headers,
+	body: &str,
+	now_seconds: u64
+) -> Result<(), ApiError> {
+	let header_value = find_header(headers, &config.header).ok_or(
+		ApiError::Unauthorized(format!("MISSING SIGNATURE HEADER {}", config.header))
+	)?;
+
+	let encoded_signature = match &config.prefix {
+		Some(prefix) => {
+			header_value
+				.trim()
+				.strip_prefix(prefix.as_str())
+				.ok_or(ApiError::Unauthorized("INVALID SIGNATURE PREFIX".to_string()))?
+		}
+		None => header_value.trim(),
+	};
+
+	let signature = match config.encoding {
+		DigestEncoding::Hex => hex::decode(encoded_signature).ok(),
+		DigestEncoding::Base64 => STANDARD.decode(encoded_signature).ok(),
+	}.ok_or(ApiError::Unauthorized("INVALID SIGNATURE ENCODING".to_string()))?;
+
+	let signed_payload = match &config.replay_protection {
+		Some(replay_protection) => {
+			let timestamp = find_header(headers, &replay_protection.timestamp_header)
+				.and_then(|timestamp| timestamp.trim().parse::<u64>().ok())
+				.ok_or(ApiError::Unauthorized("MISSING OR INVALID SIGNATURE TIMESTAMP".to_string()))?;
+
+			if now_seconds.abs_diff(timestamp) > replay_protection.tolerance_seconds {
+				return Err(ApiError::Unauthorized("SIGNATURE TIMESTAMP OUTSIDE TOLERANCE".to_string()));
+			}
+
+			render_template(
+				&replay_protection.signed_payload,
+				&json!({ "timestamp": timestamp.to_string(), "body": body })
+			)
+		}
+		None => body.to_string(),
+	};
+
+	let expected = hmac_digest(&config.algorithm, secret.as_bytes(), signed_payload.as_bytes())?;
+
+	match constant_time_eq(&expected, &signature) {
+		true => Ok(()),
+		false => Err(ApiError::Unauthorized("INVALID SIGNATURE".to_string())),
+	}
+}
+
+/// Verify the bearer JWT in the `Authorization` header of an incoming request.
+///
+/// # Arguments
+/// - `config` - JWT verification of the input node, with its secrets resolved
+/// - `headers` - Headers of the request
+/// - `now_seconds` - Current time in seconds
+///
+/// # Returns
+/// - `Value` - Authentication to expose as the `auth` field of the input data, `{ "claims": { .. } }`
+pub fn verify_bearer_jwt(config: &JwtVerification, headers: &Headers, now_seconds: u64) -> Result<Value, ApiError> {
+	let token = find_header(headers, "Authorization")
+		.and_then(|authorization| {
+			let (scheme, token) = authorization.trim().split_once(' ')?;
+
+			scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+		})
+		.ok_or(ApiError::Unauthorized("MISSING BEARER TOKEN".to_string()))?;
+
+	let claims = verify_jwt(token, config, now_seconds)?;
+
+	Ok(json!({ "claims": claims }))
+}
+
+fn find_header<'a>(headers: &'a Headers, name: &str) -> Option<&'a String> {
+	headers
+		.iter()
+		.find(|(header_name, _)| header_name.eq_ignore_ascii_case(name))
+		.map(|(_, value)| value)
+}
+
+/// Compare two byte strings in constant time. Every byte is compared, so the time taken does not reveal how much of a
+/// signature or token matched.
+///
+/// # Arguments
+/// - `a` - First byte string
+/// - `b` - Second byte string
+///
+/// # Returns
+/// - `bool` - Whether the byte strings are equal
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+	a.len() == b.len() &&
+		a
+			.iter()
+			.zip(b)
+			.fold(0, |difference, (a, b)| difference | (a ^ b)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::types::{ connector::{ HmacAlgorithm, SignatureMethod }, node::ReplayProtection };
+	use super::*;
+
+	// Example of the GitHub webhook documentation
+	static GITHUB_SECRET: &str = "It's a Secret to Everybody";
+	static GITHUB_SIGNATURE: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+	fn github_config() -> HmacSignature {
+		HmacSignature {
+			algorithm: HmacAlgorithm::SHA256,
+			secret: "{{secret.GITHUB_WEBHOOK_SECRET}}".to_string(),
+			header: "X-Hub-Signature-256".to_string(),
+			encoding: DigestEncoding::Hex,
+			prefix: Some("sha256=".to_string()),
+			replay_protection: None,
+		}
+	}
+
+	fn headers(pairs: &[(&str, &str)]) -> Headers {
+		pairs
+			.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect()
+	}
+
+	#[test]
+	fn verify_hmac_signature_github_webhook() {
+		// Header names are matched case-insensitively
+		let headers = headers(&[("x-hub-signature-256", GITHUB_SIGNATURE)]);
+
+		assert!(verify_hmac_signature(&github_config(), GITHUB_SECRET, &headers, "Hello, World!", 0).is_ok());
+	}
+
+	#[test]
+	fn verify_hmac_signature_rejects_invalid_signatures() {
+		let signed = headers(&[("X-Hub-Signature-256", GITHUB_SIGNATURE)]);
+		let unprefixed = headers(&[("X-Hub-Signature-256", GITHUB_SIGNATURE.trim_start_matches("sha256="))]);
+
+		for (secret, headers, body, message) in [
+			(GITHUB_SECRET, &signed, "Hello, World?", "INVALID SIGNATURE"),
+			("another secret", &signed, "Hello, World!", "INVALID SIGNATURE"),
+			(GITHUB_SECRET, &unprefixed, "Hello, World!", "INVALID SIGNATURE PREFIX"),
+			(GITHUB_SECRET, &Headers::new(), "Hello, World!", "MISSING SIGNATURE HEADER X-Hub-Signature-256"),
+		] {
+			match verify_hmac_signature(&github_config(), secret, headers, body, 0) {
+				Err(ApiError::Unauthorized(error)) => assert_eq!(error, message),
+				result => panic!("expected {message}, got {result:?}"),
+			}
+		}
+	}
+
+	#[test]
+	fn verify_hmac_signature_replay_protection() {
+		let mut config = github_config();
+		config.replay_protection = Some(ReplayProtection {
+			timestamp_header: "X-Timestamp".to_string(),
+			tolerance_seconds: 300,
+			signed_payload: "{{timestamp}}.{{body}}".to_string(),
+		});
+
+		let signature = hmac_digest(&HmacAlgorithm::SHA256, GITHUB_SECRET.as_bytes(), b"1700000000.{}").unwrap();
+		let headers = headers(&[
+			("X-Hub-Signature-256", &format!("sha256={}", hex::encode(signature))),
+			("X-Timestamp", "1700000000"),
+		]);
+
+		assert!(verify_hmac_signature(&config, GITHUB_SECRET, &headers, "{}", 1700000300).is_ok());
+
+		match verify_hmac_signature(&config, GITHUB_SECRET, &headers, "{}", 1700000301) {
+			Err(ApiError::Unauthorized(error)) => assert_eq!(error, "SIGNATURE TIMESTAMP OUTSIDE TOLERANCE"),
+			result => panic!("expected a replayed signature to be rejected, got {result:?}"),
+		}
+	}
+
+	#[test]
+	fn verify_bearer_jwt_exposes_claims() {
+		let config = JwtVerification {
+			algorithms: vec![SignatureMethod::HS256],
+			keys: vec!["your-256-bit-secret".to_string()],
+			issuer: None,
+			audience: None,
+			clock_skew_seconds: 0,
+		};
+
+		// Example token of jwt.io
+		let token = concat!(
+			"eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.",
+			"eyJzdWIiOiIxMjM0NTY3ODkwIiwibmFtZSI6IkpvaG4gRG9lIiwiaWF0IjoxNTE2MjM5MDIyfQ.",
+			"SflKxwRJSMeKKF2QT4fwpMeJf36POk6yJV_adQssw5c"
+		);
+		let headers = headers(&[("Authorization", &format!("Bearer {token}"))]);
+
+		let auth = verify_bearer_jwt(&config, &headers, 1516239022).unwrap();
+
+		assert_eq!(auth, json!({ "claims": { "sub": "1234567890", "name": "John Doe", "iat": 1516239022 } }));
+
+		match verify_bearer_jwt(&config, &Headers::new(), 1516239022) {
+			Err(ApiError::Unauthorized(error)) => assert_eq!(error, "MISSING BEARER TOKEN"),
+			result => panic!("expected a missing token to be rejected, got {result:?}"),
+		}
+	}
+}
@@ -1,9 +1,24 @@
-use ic_stable_structures::{ memory_manager::{ MemoryManager, MemoryId }, DefaultMemoryImpl, StableBTreeMap };
-use lib::types::node::Node;
+use ic_stable_structures::{
+	memory_manager::{ MemoryManager, MemoryId },
+	DefaultMemoryImpl,
+	StableBTreeMap,
+	StableCell,
+};
+use lib::types::{
+	circuit_revision::CircuitRevision,
+	circuit_revision_key::CircuitRevisionKey,
+	node::Node,
+	node_canister_config::NodeCanisterConfig,
+	oauth2_token::OAuth2Token,
+};
 use std::cell::RefCell;
 use ic_stable_structures::memory_manager::VirtualMemory;
 
 static NODES_MEMORY_ID: MemoryId = MemoryId::new(1);
+static REVISIONS_MEMORY_ID: MemoryId = MemoryId::new(2);
+static CONFIG_MEMORY_ID: MemoryId = MemoryId::new(3);
+static SEQUENCES_MEMORY_ID: MemoryId = MemoryId::new(4);
+static OAUTH2_TOKENS_MEMORY_ID: MemoryId = MemoryId::new(5);
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 
@@ -14,10 +29,35 @@ pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 pub type StorageRef<K, V> = RefCell<StableBTreeMap<K, V, Memory>>;
 type MemManagerStore = RefCell<MemoryManager<DefaultMemoryImpl>>;
 
+/// A reference to a `StableCell` that is wrapped in a `RefCell`.
+///# Generics
+/// * `T` - The value type of the `StableCell`.
+pub type CellRef<T> = RefCell<StableCell<T, Memory>>;
+
 thread_local! {
 	pub static MEMORY_MANAGER: MemManagerStore = RefCell::new(MemoryManager::init(DefaultMemoryImpl::default()));
 
 	pub static NODES: StorageRef<u32, Node> = RefCell::new(
 		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(NODES_MEMORY_ID)))
 	);
+
+	pub static REVISIONS: StorageRef<CircuitRevisionKey, CircuitRevision> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(REVISIONS_MEMORY_ID)))
+	);
+
+	pub static SEQUENCES: StorageRef<String, u32> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(SEQUENCES_MEMORY_ID)))
+	);
+
+	/// Cached OAuth2 access tokens by connector ID
+	pub static OAUTH2_TOKENS: StorageRef<u32, OAuth2Token> = RefCell::new(
+		StableBTreeMap::init(MEMORY_MANAGER.with(|m| m.borrow().get(OAUTH2_TOKENS_MEMORY_ID)))
+	);
+
+	pub static CONFIG: CellRef<NodeCanisterConfig> = RefCell::new(
+		StableCell::init(
+			MEMORY_MANAGER.with(|m| m.borrow().get(CONFIG_MEMORY_ID)),
+			NodeCanisterConfig::default()
+		).expect("Failed to initialize CONFIG")
+	);
 }
@@ -1,13 +1,57 @@
 use candid::Principal;
-use ic_cdk::query;
+use ic_cdk::{ init, post_upgrade, query };
+use lib::types::node_canister_config::NodeCanisterConfig;
+use modules::{ config::config_store::ConfigStore, sequences::sequences_store::SequencesStore };
 
 pub mod canister_storage;
 
 pub mod modules {
+	pub mod config {
+		pub mod config_controller;
+		pub mod config_store;
+	}
+
+	pub mod connectors {
+		pub mod connectors_store;
+	}
+
+	pub mod executions {
+		pub mod executions_controller;
+		pub mod executions_store;
+	}
+
 	pub mod nodes {
 		pub mod nodes_controller;
 		pub mod nodes_store;
 	}
+
+	pub mod oauth2 {
+		pub mod oauth2_store;
+	}
+
+	pub mod revisions {
+		pub mod revisions_controller;
+		pub mod revisions_store;
+	}
+
+	pub mod secrets {
+		pub mod secrets_store;
+	}
+
+	pub mod sequences {
+		pub mod sequences_store;
+	}
+}
+
+#[init]
+fn init(config: NodeCanisterConfig) {
+	ConfigStore::set_config(config);
+}
+
+#[post_upgrade]
+fn post_upgrade(config: Option<NodeCanisterConfig>) {
+	ConfigStore::migrate(config);
+	SequencesStore::migrate();
 }
 
 // Hacky way to expose the candid interface to the outside world
@@ -15,7 +59,11 @@ pub mod modules {
 pub fn __export_did_tmp_() -> String {
 	use candid::export_service;
 	use lib::types::api_error::*;
+	use lib::types::circuit_revision::*;
+	use lib::types::http_gateway::*;
 	use lib::types::node::*;
+	use lib::types::node_canister_config::*;
+	use lib::types::trace::*;
 	use ic_cdk::api::management_canister::http_request::{ TransformArgs, HttpResponse };
 
 	export_service!();
@@ -0,0 +1,26 @@
+use candid::Principal;
+use ic_cdk::{ caller, query, update };
+use lib::{
+	types::{ api_error::ApiError, node_canister_config::NodeCanisterConfig },
+	utils::validate::validate_anonymous,
+};
+use super::config_store::ConfigStore;
+
+#[query]
+fn get_config() -> Result<NodeCanisterConfig, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => {
+			ConfigStore::validate_owner(caller_principal)?;
+			Ok(ConfigStore::get_config())
+		}
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn transfer_ownership(new_owner: Principal) -> Result<NodeCanisterConfig, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => ConfigStore::transfer_ownership(new_owner, caller_principal),
+		Err(err) => Err(err),
+	}
+}
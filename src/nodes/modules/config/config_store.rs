@@ -0,0 +1,81 @@
+use candid::Principal;
+use lib::types::{ api_error::ApiError, node_canister_config::NodeCanisterConfig };
+use crate::canister_storage::CONFIG;
+
+pub struct ConfigStore;
+
+impl ConfigStore {
+	/// Get the configuration of the node canister.
+	///
+	/// # Returns
+	/// - `NodeCanisterConfig` - Configuration
+	pub fn get_config() -> NodeCanisterConfig {
+		CONFIG.with(|config| config.borrow().get().clone())
+	}
+
+	/// Set the configuration of the node canister.
+	///
+	/// # Arguments
+	/// - `data` - Configuration
+	pub fn set_config(data: NodeCanisterConfig) {
+		CONFIG.with(|config| {
+			config.borrow_mut().set(data).expect("Failed to set CONFIG");
+		})
+	}
+
+	/// Seed the configuration on upgrade. Node canisters installed before the configuration existed are upgraded
+	/// with it as upgrade argument by the main canister. The upgrade is rejected if the configuration is neither
+	/// stored nor provided, as nobody could call the canister afterwards.
+	///
+	/// # Arguments
+	/// - `config` - Configuration passed as upgrade argument
+	pub fn migrate(config: Option<NodeCanisterConfig>) {
+		match config {
+			Some(config) => Self::set_config(config),
+			None => {
+				if Self::get_config().main_canister == Principal::anonymous() {
+					ic_cdk::trap("NODE CANISTER CONFIG MISSING, UPGRADE WITH A NodeCanisterConfig ARGUMENT");
+				}
+			}
+		}
+	}
+
+	/// Validate that the caller is the owner of the node canister or the main canister. Other users with a role on
+	/// the circuit go through the main canister, which checks their role before calling the node canister.
+	///
+	/// # Arguments
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Result<(), ApiError>` - Unit or ApiError
+	pub fn validate_owner(caller_principal: Principal) -> Result<(), ApiError> {
+		let config = Self::get_config();
+
+		if caller_principal != config.owner && caller_principal != config.main_canister {
+			return Err(ApiError::Unauthorized("UNAUTHORIZED".to_string()));
+		}
+
+		Ok(())
+	}
+
+	/// Transfer the ownership of the node canister. Only the main canister can transfer ownership.
+	///
+	/// # Arguments
+	/// - `new_owner` - Principal of the new owner
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `NodeCanisterConfig` - Updated configuration
+	pub fn transfer_ownership(new_owner: Principal, caller_principal: Principal) -> Result<NodeCanisterConfig, ApiError> {
+		let mut config = Self::get_config();
+
+		if caller_principal != config.main_canister {
+			return Err(ApiError::Unauthorized("UNAUTHORIZED".to_string()));
+		}
+
+		config.owner = new_owner;
+		Self::set_config(config.clone());
+
+		Ok(config)
+	}
+}
@@ -0,0 +1,54 @@
+use ic_cdk::call;
+use lib::types::{ api_error::ApiError, connector::{ CanisterConnector, Connector, ConnectorType, HttpConnector } };
+use crate::modules::config::config_store::ConfigStore;
+
+pub struct ConnectorsStore;
+
+impl ConnectorsStore {
+	/// Get connector from the main canister. Connectors are not cached, so credential changes apply immediately.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	///
+	/// # Returns
+	/// - `Connector` - Connector
+	pub async fn get_connector(connector_id: u32) -> Result<Connector, ApiError> {
+		let main_canister = ConfigStore::get_config().main_canister;
+
+		let (result,): (Result<Connector, ApiError>,) = call(
+			main_canister,
+			"get_node_canister_connector",
+			(connector_id,)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Get HTTP connector from the main canister.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	///
+	/// # Returns
+	/// - `HttpConnector` - HTTP connector
+	pub async fn get_http_connector(connector_id: u32) -> Result<HttpConnector, ApiError> {
+		match Self::get_connector(connector_id).await?.connector_type {
+			ConnectorType::Http(http_connector) => Ok(http_connector),
+			_ => Err(ApiError::BadRequest("CONNECTOR IS NOT AN HTTP CONNECTOR".to_string())),
+		}
+	}
+
+	/// Get canister connector from the main canister.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	///
+	/// # Returns
+	/// - `CanisterConnector` - Canister connector
+	pub async fn get_canister_connector(connector_id: u32) -> Result<CanisterConnector, ApiError> {
+		match Self::get_connector(connector_id).await?.connector_type {
+			ConnectorType::CanisterInterface(canister_connector) => Ok(canister_connector),
+			_ => Err(ApiError::BadRequest("CONNECTOR IS NOT A CANISTER CONNECTOR".to_string())),
+		}
+	}
+}
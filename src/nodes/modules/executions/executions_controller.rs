@@ -0,0 +1,30 @@
+use ic_cdk::{ caller, query, update };
+use lib::types::{
+	api_error::ApiError,
+	headers::Headers,
+	http_gateway::{ HttpGatewayRequest, HttpGatewayResponse },
+	trace::Trace,
+};
+use super::executions_store::{ ExecutionRequest, ExecutionsStore };
+
+#[update]
+async fn trigger_circuit(circuit_id: u32, data: String) -> Result<Vec<Trace>, ApiError> {
+	// Anyone can trigger a circuit, the input node verifies the caller and the data
+	let request = ExecutionRequest {
+		caller: caller(),
+		headers: Headers::new(),
+		body: data,
+	};
+
+	ExecutionsStore::run_circuit(circuit_id, request).await
+}
+
+#[query]
+fn http_request(request: HttpGatewayRequest) -> HttpGatewayResponse {
+	ExecutionsStore::http_request(request)
+}
+
+#[update]
+async fn http_request_update(request: HttpGatewayRequest) -> HttpGatewayResponse {
+	ExecutionsStore::http_request_update(request, caller()).await
+}
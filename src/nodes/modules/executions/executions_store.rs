@@ -0,0 +1,573 @@
+use candid::{ Encode, Principal };
+use ic_cdk::{ api::{ call::call_raw128, time }, call };
+use lib::{
+	node_server::URL_API_FORWARDING,
+	types::{
+		api_error::ApiError,
+		circuit::Circuit,
+		headers::Headers,
+		http_gateway::{ HttpGatewayRequest, HttpGatewayResponse },
+		node::{ Arg, Canister, HttpRequest, LookupCanister, Node, NodeType, Output, PinType, VerificationType },
+		trace::{ Trace, TraceError, TraceStatus },
+	},
+	utils::{
+		candid_conversion::{ candid_args_from_json, candid_value_from_arg, decode_candid_reply, encode_candid_args },
+		candid_interface::method_signature,
+		http_outcall::forward_http_request,
+		pins::{ apply_mapper, evaluate_filter, set_path, transform_lookup_reply },
+		template::render_template,
+		webhook::{ constant_time_eq, verify_bearer_jwt, verify_hmac_signature, AUTH_FIELD },
+	},
+};
+use serde_json::{ json, Value };
+use crate::modules::{
+	config::config_store::ConfigStore,
+	connectors::connectors_store::ConnectorsStore,
+	nodes::nodes_store::NodesStore,
+	revisions::revisions_store::RevisionsStore,
+	secrets::secrets_store::SecretsStore,
+};
+
+/// Request triggering an execution of a circuit.
+pub struct ExecutionRequest {
+	/// Principal of the caller
+	pub caller: Principal,
+	pub headers: Headers,
+	/// Raw body, a stringified JSON object
+	pub body: String,
+}
+
+pub struct ExecutionsStore;
+
+impl ExecutionsStore {
+	/// Answer a request of the HTTP gateway. Webhooks are posted to `/circuits/{circuit_id}` and upgraded to an
+	/// update call, as executions call other canisters.
+	///
+	/// # Arguments
+	/// - `request` - Request of the HTTP gateway
+	///
+	/// # Returns
+	/// - `HttpGatewayResponse` - Response
+	pub fn http_request(request: HttpGatewayRequest) -> HttpGatewayResponse {
+		match Self::route(&request) {
+			Ok(_) => HttpGatewayResponse::upgrade(),
+			Err(response) => response,
+		}
+	}
+
+	/// Run a circuit for a webhook posted to `/circuits/{circuit_id}`. The raw body and headers are verified by the
+	/// input node, e.g. with an HMAC signature.
+	///
+	/// # Arguments
+	/// - `request` - Request of the HTTP gateway
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `HttpGatewayResponse` - Response with the IDs of the traces of the execution
+	pub async fn http_request_update(request: HttpGatewayRequest, caller_principal: Principal) -> HttpGatewayResponse {
+		let circuit_id = match Self::route(&request) {
+			Ok(circuit_id) => circuit_id,
+			Err(response) => {
+				return response;
+			}
+		};
+
+		let Ok(body) = String::from_utf8(request.body) else {
+			return Self::error_response(ApiError::BadRequest("BODY MUST BE UTF-8".to_string()));
+		};
+
+		let execution_request = ExecutionRequest {
+			caller: caller_principal,
+			headers: request.headers.into_iter().collect::<Headers>(),
+			body,
+		};
+
+		match Self::run_circuit(circuit_id, execution_request).await {
+			Ok(traces) => {
+				let trace_ids = traces
+					.iter()
+					.map(|trace| trace.id)
+					.collect::<Vec<u32>>();
+
+				HttpGatewayResponse::json(200, json!({ "trace_ids": trace_ids }))
+			}
+			Err(err) => Self::error_response(err),
+		}
+	}
+
+	/// Run a circuit. Executions run the nodes of the published revision, edits to the draft only apply once they
+	/// are published. A canister input node verifies the request, an HTTP request input node fetches the input data,
+	/// then the enabled nodes run in order and every node passes the data on to the next one. A node filtered out by
+	/// its filter pin is skipped, a failed node stops the execution. Every node that ran gets a trace recording the
+	/// revision.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `request` - Request triggering the execution
+	///
+	/// # Returns
+	/// - `Vec<Trace>` - Traces of the execution
+	pub async fn run_circuit(circuit_id: u32, request: ExecutionRequest) -> Result<Vec<Trace>, ApiError> {
+		let circuit = Self::get_circuit().await?;
+
+		if circuit.id != circuit_id {
+			return Err(ApiError::NotFound("CIRCUIT NOT FOUND".to_string()));
+		}
+
+		if !circuit.is_enabled {
+			return Err(ApiError::BadRequest("CIRCUIT IS DISABLED".to_string()));
+		}
+
+		let revision = RevisionsStore::latest_revision(circuit_id).ok_or(
+			ApiError::BadRequest("NOT PUBLISHED".to_string())
+		)?;
+
+		let mut nodes = revision.nodes
+			.into_iter()
+			.filter(|node| node.is_enabled)
+			.collect::<Vec<Node>>();
+		nodes.sort_by_key(|node| (node.order, node.id));
+
+		let mut data = match nodes.first().map(|node| &node.node_type) {
+			Some(NodeType::Canister(input)) => Self::verify_input(input, &request).await?,
+			Some(NodeType::HttpRequest(input)) => Self::fetch_input(input, &request).await?,
+			_ => {
+				return Err(ApiError::BadRequest("CIRCUIT HAS NO INPUT NODE".to_string()));
+			}
+		};
+
+		let mut traces = vec![];
+
+		for node in nodes.iter() {
+			let started_at = time();
+			let mut errors = vec![];
+
+			let status = match Self::run_node(node, &mut data, &mut errors).await {
+				Ok(true) => TraceStatus::Success,
+				Ok(false) => TraceStatus::Cancelled,
+				Err(err) => {
+					errors.push(Self::trace_error(err, Self::node_type_name(&node.node_type)));
+					TraceStatus::Failed
+				}
+			};
+
+			let completed_at = time();
+
+			traces.push(Trace {
+				id: Default::default(),
+				user_id: ConfigStore::get_config().owner,
+				node_id: node.id,
+				circuit_id,
+				revision: Some(revision.revision),
+				status: status.clone(),
+				errors,
+				data: data.to_string(),
+				duration: ((completed_at - started_at) / 1_000_000) as u32,
+				started_at,
+				completed_at,
+				created_at: completed_at,
+				updated_at: completed_at,
+			});
+
+			// Nothing runs after a failed node or a filtered input node
+			if status == TraceStatus::Failed || (status == TraceStatus::Cancelled && node.node_type.is_input()) {
+				break;
+			}
+		}
+
+		Self::save_traces(traces).await
+	}
+
+	/// Verify a request with the verification of the input node.
+	///
+	/// # Arguments
+	/// - `input` - Input node
+	/// - `request` - Request triggering the execution
+	///
+	/// # Returns
+	/// - `Value` - Input data
+	async fn verify_input(input: &Canister, request: &ExecutionRequest) -> Result<Value, ApiError> {
+		let mut data = Self::request_data(request)?;
+
+		match input.verification_type() {
+			VerificationType::None => {}
+			VerificationType::Whitelist(principals) if !principals.contains(&request.caller) => {
+				return Err(ApiError::Unauthorized("CALLER NOT WHITELISTED".to_string()));
+			}
+			VerificationType::Whitelist(_) => {}
+			VerificationType::Token(token) => {
+				// The token is removed from the data, so it does not end up in the traces
+				let provided = data.as_object_mut().and_then(|object| object.remove(&token.field));
+
+				let mut expected = token.token.clone();
+				SecretsStore::render_secrets(vec![&mut expected]).await?;
+
+				let is_valid = match provided {
+					Some(Value::String(provided)) => constant_time_eq(provided.as_bytes(), expected.as_bytes()),
+					_ => false,
+				};
+
+				if !is_valid {
+					return Err(ApiError::Unauthorized("INVALID TOKEN".to_string()));
+				}
+			}
+			VerificationType::HmacSignature(signature) => {
+				let mut secret = signature.secret.clone();
+				SecretsStore::render_secrets(vec![&mut secret]).await?;
+
+				verify_hmac_signature(signature, &secret, &request.headers, &request.body, time() / 1_000_000_000)?;
+			}
+			VerificationType::Jwt(jwt_verification) => {
+				let mut config = jwt_verification.clone();
+				SecretsStore::render_secrets(config.keys.iter_mut().collect()).await?;
+
+				let auth = verify_bearer_jwt(&config, &request.headers, time() / 1_000_000_000)?;
+				set_path(&mut data, AUTH_FIELD, auth);
+			}
+		}
+
+		Ok(data)
+	}
+
+	/// Fetch the input data with the request of an HTTP request input node. Such input nodes have no verification,
+	/// so only the owner of the circuit can trigger them. The data of the triggering request renders the
+	/// `{{path}}` placeholders of the request.
+	///
+	/// # Arguments
+	/// - `input` - Input node
+	/// - `request` - Request triggering the execution
+	///
+	/// # Returns
+	/// - `Value` - Input data, the JSON object of the response body
+	async fn fetch_input(input: &HttpRequest, request: &ExecutionRequest) -> Result<Value, ApiError> {
+		ConfigStore::validate_owner(request.caller)?;
+
+		let mut data = match Self::send_http_request(input, &Self::request_data(request)?).await? {
+			Value::Object(object) => Value::Object(object),
+			_ => {
+				return Err(ApiError::BadRequest("INPUT RESPONSE MUST BE A JSON OBJECT".to_string()));
+			}
+		};
+
+		// Only a verified JWT sets the authentication, responses cannot provide it
+		if let Some(object) = data.as_object_mut() {
+			object.remove(AUTH_FIELD);
+		}
+
+		Ok(data)
+	}
+
+	/// Data of the request triggering an execution, a JSON object. An empty body is an empty object.
+	fn request_data(request: &ExecutionRequest) -> Result<Value, ApiError> {
+		let mut data = match request.body.trim() {
+			"" => Value::Object(Default::default()),
+			body =>
+				match serde_json::from_str::<Value>(body) {
+					Ok(Value::Object(object)) => Value::Object(object),
+					_ => {
+						return Err(ApiError::BadRequest("INPUT DATA MUST BE A JSON OBJECT".to_string()));
+					}
+				}
+		};
+
+		// Only a verified JWT sets the authentication, callers cannot provide it
+		if let Some(object) = data.as_object_mut() {
+			object.remove(AUTH_FIELD);
+		}
+
+		Ok(data)
+	}
+
+	/// Run a node with its pins. Custom pin logic is not supported, such pins are recorded as errors and skipped.
+	///
+	/// # Arguments
+	/// - `node` - Node
+	/// - `data` - Data of the execution, updated in place
+	/// - `errors` - Errors of the trace of the node
+	///
+	/// # Returns
+	/// - `bool` - Whether the node ran, `false` if it was filtered out
+	async fn run_node(node: &Node, data: &mut Value, errors: &mut Vec<TraceError>) -> Result<bool, ApiError> {
+		let mut pins = node.pins.clone();
+		pins.sort_by_key(|pin| pin.order);
+
+		for pin in pins.iter() {
+			match &pin.pin_type {
+				PinType::PreMapperPin(mapper) => {
+					*data = apply_mapper(mapper, data);
+				}
+				PinType::FilterPin(filter) if !evaluate_filter(filter, data) => {
+					return Ok(false);
+				}
+				PinType::PrePin(_) => errors.push(Self::unsupported_pin_error(pin.pin_type.name())),
+				_ => {}
+			}
+		}
+
+		let reply = match &node.node_type {
+			NodeType::LookupCanister(lookup) => Some(Self::call_lookup_canister(lookup, data).await?),
+			NodeType::LookupHttpRequest(request) => Some(Self::send_http_request(request, data).await?),
+			NodeType::Output(output) => {
+				Self::call_output(output, data).await?;
+				None
+			}
+			NodeType::Canister(_) | NodeType::HttpRequest(_) => None,
+		};
+
+		if let Some(reply) = reply {
+			if let Some((path, value)) = transform_lookup_reply(&pins, node.id, &reply) {
+				set_path(data, &path, value);
+			}
+		}
+
+		for pin in pins.iter() {
+			match &pin.pin_type {
+				PinType::PostMapperPin(mapper) => {
+					*data = apply_mapper(mapper, data);
+				}
+				PinType::PostPin(_) => errors.push(Self::unsupported_pin_error(pin.pin_type.name())),
+				_ => {}
+			}
+		}
+
+		Ok(true)
+	}
+
+	/// Call the canister of a lookup canister node. `{{path}}` placeholders in the arguments are rendered with the
+	/// data of the execution. With a canister connector the arguments are converted to the types of the method and
+	/// the reply is decoded with the field names of the return types.
+	///
+	/// # Arguments
+	/// - `lookup` - Lookup canister node
+	/// - `data` - Data of the execution
+	///
+	/// # Returns
+	/// - `Value` - Reply of the canister as JSON
+	async fn call_lookup_canister(lookup: &LookupCanister, data: &Value) -> Result<Value, ApiError> {
+		let (canister, signature) = match lookup.connector_id {
+			Some(connector_id) => {
+				let canister_connector = ConnectorsStore::get_canister_connector(connector_id).await?;
+				let signature = method_signature(&canister_connector.candid, &lookup.method)?;
+
+				(canister_connector.canister_id, Some(signature))
+			}
+			None => (lookup.canister, None),
+		};
+
+		let values = lookup.args
+			.iter()
+			.enumerate()
+			.map(|(index, arg)| {
+				let parameter = signature
+					.as_ref()
+					.and_then(|(env, arg_types, _)| arg_types.get(index).map(|arg_type| (env, arg_type)));
+
+				candid_value_from_arg(&Self::render_arg(arg, data), parameter, &format!("args.{index}"))
+			})
+			.collect::<Result<Vec<_>, ApiError>>()?;
+
+		let args = encode_candid_args(
+			&values,
+			signature.as_ref().map(|(env, arg_types, _)| (env, arg_types.as_slice()))
+		)?;
+
+		let reply = call_raw128(canister, &lookup.method, args, lookup.cycles).await.map_err(|(code, message)|
+			ApiError::InterCanister(format!("{:?}: {}", code, message))
+		)?;
+
+		decode_candid_reply(
+			&reply,
+			signature.as_ref().map(|(env, _, return_types)| (env, return_types.as_slice()))
+		)
+	}
+
+	/// Send the request of an HTTP request node. Secrets of the vault are rendered first, so the data of the
+	/// execution cannot reference them, then `{{path}}` placeholders are rendered with the data.
+	///
+	/// # Arguments
+	/// - `request` - HTTP request node
+	/// - `data` - Data of the execution
+	///
+	/// # Returns
+	/// - `Value` - Response body, as JSON if it is JSON
+	async fn send_http_request(request: &HttpRequest, data: &Value) -> Result<Value, ApiError> {
+		let mut url = request.url.clone();
+		let mut headers = request.headers.clone();
+		let mut request_body = request.request_body.clone();
+
+		let mut templates = vec![&mut url];
+		templates.extend(headers.values_mut());
+		templates.extend(request_body.as_mut());
+		SecretsStore::render_secrets(templates).await?;
+
+		let url = render_template(&url, data);
+		let headers = headers
+			.into_iter()
+			.map(|(name, value)| (name, render_template(&value, data)))
+			.collect::<Headers>();
+		let request_body = request_body.map(|body| render_template(&body, data));
+		let connector = request.connector.clone().map(|mut connector| {
+			connector.path = render_template(&connector.path, data);
+			connector
+		});
+
+		let (url, headers) = NodesStore::resolve_http_request(
+			&request.method,
+			url,
+			headers,
+			request_body.as_deref(),
+			connector,
+			request.cycles
+		).await?;
+
+		let body =
+			serde_json::json!({
+			"url": url,
+			"method": request.method,
+			"requestBody": request_body,
+			"headers": headers,
+		});
+
+		let response = forward_http_request(body, request.cycles, URL_API_FORWARDING).await?;
+
+		Ok(serde_json::from_str::<Value>(&response).unwrap_or(Value::String(response)))
+	}
+
+	/// Call the canister of an output node. With a canister connector the data is converted to the argument types
+	/// of the method, otherwise the stringified JSON data is its only argument.
+	///
+	/// # Arguments
+	/// - `output` - Output node
+	/// - `data` - Data of the execution
+	async fn call_output(output: &Output, data: &Value) -> Result<(), ApiError> {
+		let (canister, args) = match output.connector_id {
+			Some(connector_id) => {
+				let canister_connector = ConnectorsStore::get_canister_connector(connector_id).await?;
+				let (env, arg_types, _) = method_signature(&canister_connector.candid, &output.method)?;
+
+				let values = candid_args_from_json(data, &env, &arg_types)?;
+
+				(canister_connector.canister_id, encode_candid_args(&values, Some((&env, arg_types.as_slice())))?)
+			}
+			None => {
+				let args = Encode!(&data.to_string()).map_err(|err|
+					ApiError::BadRequest(format!("FAILED TO ENCODE DATA: {err}"))
+				)?;
+
+				(output.canister, args)
+			}
+		};
+
+		call_raw128(canister, &output.method, args, 0).await.map_err(|(code, message)|
+			ApiError::InterCanister(format!("{:?}: {}", code, message))
+		)?;
+
+		Ok(())
+	}
+
+	/// Get the circuit run by this node canister from the main canister.
+	async fn get_circuit() -> Result<Circuit, ApiError> {
+		let main_canister = ConfigStore::get_config().main_canister;
+
+		let (result,): (Result<Circuit, ApiError>,) = call(
+			main_canister,
+			"get_node_canister_circuit",
+			()
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Store the traces of an execution in the main canister, which assigns their IDs.
+	async fn save_traces(traces: Vec<Trace>) -> Result<Vec<Trace>, ApiError> {
+		let main_canister = ConfigStore::get_config().main_canister;
+
+		let (result,): (Result<Vec<Trace>, ApiError>,) = call(
+			main_canister,
+			"add_node_canister_traces",
+			(traces,)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Get the circuit ID of a webhook, or the response rejecting the request.
+	fn route(request: &HttpGatewayRequest) -> Result<u32, HttpGatewayResponse> {
+		let path = request.url.split('?').next().unwrap_or_default();
+
+		let circuit_id = path
+			.strip_prefix("/circuits/")
+			.and_then(|circuit_id| circuit_id.trim_end_matches('/').parse::<u32>().ok())
+			.ok_or(Self::error_response(ApiError::NotFound("NOT FOUND".to_string())))?;
+
+		if !request.method.eq_ignore_ascii_case("POST") {
+			return Err(HttpGatewayResponse::json(405, json!({ "error": "METHOD NOT ALLOWED" })));
+		}
+
+		Ok(circuit_id)
+	}
+
+	/// Response of the HTTP gateway for an error.
+	fn error_response(err: ApiError) -> HttpGatewayResponse {
+		let (status_code, message) = match err {
+			ApiError::Unauthorized(message) => (401, message),
+			ApiError::NotFound(message) => (404, message),
+			ApiError::BadRequest(message) => (400, message),
+			ApiError::AlreadyExists(message) => (409, message),
+			ApiError::InterCanister(message) | ApiError::Connector(message) => (502, message),
+		};
+
+		HttpGatewayResponse::json(status_code, json!({ "error": message }))
+	}
+
+	/// Render the `{{path}}` placeholders of an argument with the data of the execution.
+	fn render_arg(arg: &Arg, data: &Value) -> Arg {
+		match arg {
+			Arg::String(text) => Arg::String(render_template(text, data)),
+			Arg::Number(text) => Arg::Number(render_template(text, data)),
+			Arg::Principal(text) => Arg::Principal(render_template(text, data)),
+			Arg::BigInt(text) => Arg::BigInt(render_template(text, data)),
+			Arg::Boolean(text) => Arg::Boolean(render_template(text, data)),
+			Arg::Array(text) => Arg::Array(render_template(text, data)),
+			Arg::Object(text) => Arg::Object(render_template(text, data)),
+			Arg::Candid(value) => Arg::Candid(value.clone()),
+		}
+	}
+
+	/// Name of a node type, used as the source of trace errors.
+	fn node_type_name(node_type: &NodeType) -> &'static str {
+		match node_type {
+			NodeType::Canister(_) => "Canister",
+			NodeType::HttpRequest(_) => "HttpRequest",
+			NodeType::Output(_) => "Output",
+			NodeType::LookupCanister(_) => "LookupCanister",
+			NodeType::LookupHttpRequest(_) => "LookupHttpRequest",
+		}
+	}
+
+	/// Trace error of a failed node.
+	fn trace_error(err: ApiError, source: &str) -> TraceError {
+		let (code, message) = match err {
+			ApiError::Unauthorized(message) => ("UNAUTHORIZED", message),
+			ApiError::NotFound(message) => ("NOT_FOUND", message),
+			ApiError::AlreadyExists(message) => ("ALREADY_EXISTS", message),
+			ApiError::InterCanister(message) => ("INTER_CANISTER", message),
+			ApiError::BadRequest(message) => ("BAD_REQUEST", message),
+			ApiError::Connector(message) => ("CONNECTOR", message),
+		};
+
+		TraceError {
+			code: code.to_string(),
+			message,
+			source: source.to_string(),
+			resolved_at: None,
+			created_at: time(),
+			updated_at: time(),
+		}
+	}
+
+	/// Trace error of a pin with custom logic, which executions do not support.
+	fn unsupported_pin_error(source: &str) -> TraceError {
+		Self::trace_error(ApiError::BadRequest("CUSTOM PIN LOGIC IS NOT SUPPORTED".to_string()), source)
+	}
+}
@@ -6,7 +6,10 @@ use ic_cdk::{
 	update,
 };
 use lib::{
-	types::{ api_error::ApiError, node::{ LookupCanisterPreview, LookupHttpRequestPreview, Node, NodeType, Pin } },
+	types::{
+		api_error::ApiError,
+		node::{ LookupCanisterPreview, LookupHttpRequestPreview, Node, NodeType, PostNode, PostPin },
+	},
 	utils::validate::validate_anonymous,
 };
 use super::nodes_store::NodesStore;
@@ -88,6 +91,14 @@ fn add_node(circuit_id: u32, data: NodeType) -> Result<Node, ApiError> {
 	}
 }
 
+#[update]
+fn add_nodes(circuit_id: u32, position: Option<u32>, data: Vec<PostNode>) -> Result<Vec<Node>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::add_nodes(circuit_id, position, data, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
 #[update]
 fn edit_node(node_id: u32, data: NodeType) -> Result<Node, ApiError> {
 	match validate_anonymous(&caller()) {
@@ -105,7 +116,15 @@ fn edit_order(node_id: u32, order: u32) -> Result<Node, ApiError> {
 }
 
 #[update]
-fn add_pin(node_id: u32, data: Pin) -> Result<Node, ApiError> {
+fn reorder_nodes(circuit_id: u32, node_ids: Vec<u32>) -> Result<Vec<Node>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::reorder_nodes(circuit_id, node_ids, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn add_pin(node_id: u32, data: PostPin) -> Result<Node, ApiError> {
 	match validate_anonymous(&caller()) {
 		Ok(caller_principal) => NodesStore::add_pin(node_id, data, caller_principal),
 		Err(err) => Err(err),
@@ -113,17 +132,25 @@ fn add_pin(node_id: u32, data: Pin) -> Result<Node, ApiError> {
 }
 
 #[update]
-fn edit_pin(node_id: u32, data: Pin) -> Result<Node, ApiError> {
+fn edit_pin(node_id: u32, pin_id: u32, data: PostPin) -> Result<Node, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => NodesStore::edit_pin(node_id, pin_id, data, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn delete_pin(node_id: u32, pin_id: u32) -> Result<Node, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => NodesStore::edit_pin(node_id, data, caller_principal),
+		Ok(caller_principal) => NodesStore::delete_pin(node_id, pin_id, caller_principal),
 		Err(err) => Err(err),
 	}
 }
 
 #[update]
-fn delete_pin(node_id: u32, data: Pin) -> Result<Node, ApiError> {
+fn reorder_pins(node_id: u32, pin_ids: Vec<u32>) -> Result<Node, ApiError> {
 	match validate_anonymous(&caller()) {
-		Ok(caller_principal) => NodesStore::delete_pin(node_id, data, caller_principal),
+		Ok(caller_principal) => NodesStore::reorder_pins(node_id, pin_ids, caller_principal),
 		Err(err) => Err(err),
 	}
 }
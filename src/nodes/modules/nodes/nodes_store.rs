@@ -1,43 +1,47 @@
 use candid::Principal;
-use ic_cdk::{
-	api::{
-		management_canister::http_request::{
-			http_request,
-			CanisterHttpRequestArgument,
-			HttpHeader,
-			HttpMethod,
-			TransformContext,
-			TransformFunc,
-		},
-		time,
-	},
-	id,
-};
+use ic_cdk::{ api::{ call::call_raw128, time }, id };
+use ic_stable_structures::StableBTreeMap;
 use lib::{
-	node_server::{ HOST, URL_API_FORWARDING, URL_ICC },
+	node_server::{ URL_API_FORWARDING, URL_ICC },
 	types::{
 		api_error::ApiError,
-		node::{ LookupCanisterPreview, LookupHttpRequestPreview, Node, NodeType, Pin, PinType },
+		connector::Authentication,
+		headers::Headers,
+		node::{
+			ConnectorReference,
+			HttpRequestMethod,
+			LookupCanisterPreview,
+			LookupHttpRequestPreview,
+			Node,
+			PreviewArg,
+			NodeType,
+			Pin,
+			PostNode,
+			PostPin,
+		},
+	},
+	utils::{
+		candid_conversion::{ candid_value_from_preview_arg, decode_candid_reply, encode_candid_args },
+		candid_interface::method_signature,
+		http_outcall::forward_http_request,
+		pins::{ set_path, transform_lookup_reply },
+		request_signing::{ apply_authentication, apply_token },
+		sequence::{ NODES_SEQUENCE, PINS_SEQUENCE },
+		vault::secret_references,
+	},
+};
+use serde_json::{ Map, Value };
+
+use crate::{
+	canister_storage::{ Memory, NODES },
+	modules::{
+		config::config_store::ConfigStore,
+		connectors::connectors_store::ConnectorsStore,
+		oauth2::oauth2_store::OAuth2Store,
+		secrets::secrets_store::SecretsStore,
+		sequences::sequences_store::SequencesStore,
 	},
-	utils::idempotency::generate_idempotency_key,
 };
-use serde_json::Value;
-
-use crate::canister_storage::NODES;
-
-// TODO: add node owner check to all calls
-// thread_local! {
-// 	pub static CANISTER_OWNER: RefCell<StableCell<String, Memory>> = RefCell::new(
-// 		StableCell::init(
-// 			MEMORY_MANAGER.with(|m| m.borrow().get(MemoryId::new(1))),
-// 			String::default()
-// 		).expect("Failed to initialize CANISTER_OWNER")
-// 	);
-// }
-// if caller_principal.to_string() != canister_owner {
-// 	// If the caller is not the canister owner, return an error
-// 	return Err(ApiError::NotFound("UNAUTHORIZED".to_string()));
-// }
 
 // Handlebars with json helper
 // https://docs.rs/handlebars/latest/handlebars/#string-or-case-helpers
@@ -53,8 +57,8 @@ impl NodesStore {
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn get_circuit_node(node_id: u32, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn get_circuit_node(node_id: u32, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let nodes = nodes.borrow();
@@ -80,9 +84,9 @@ impl NodesStore {
 	/// - `Vec<Node>` - Nodes
 	pub fn get_circuit_nodes(
 		circuit_id: u32,
-		_caller_principal: Principal
+		caller_principal: Principal
 	) -> Result<(Principal, Vec<Node>), ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let nodes = nodes.borrow();
@@ -106,8 +110,8 @@ impl NodesStore {
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn delete_node(node_id: u32, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn delete_node(node_id: u32, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
@@ -119,8 +123,9 @@ impl NodesStore {
 
 			let node = node.unwrap().clone();
 
-			// Remove node
+			// Remove node and close the gap in the order
 			nodes.remove(&node.id);
+			Self::renumber_nodes(&mut nodes, node.circuit_id);
 
 			Ok(node)
 		})
@@ -136,22 +141,34 @@ impl NodesStore {
 	/// # Returns
 	/// - `Node` - Node
 	pub fn add_node(circuit_id: u32, data: NodeType, caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+		ConfigStore::validate_owner(caller_principal)?;
+
+		// Nodes added through the main canister belong to the owner
+		let user_id = ConfigStore::get_config().owner;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
 
-			let node_id = (nodes.len() as u32) + 1;
+			let node_id = SequencesStore::next_id(NODES_SEQUENCE);
+
+			// Append the node to the end of the circuit
+			let order =
+				nodes
+					.iter()
+					.filter(|(_, node)| node.circuit_id == circuit_id)
+					.map(|(_, node)| node.order)
+					.max()
+					.unwrap_or(0) + 1;
 
 			let new_node = Node {
 				id: node_id,
 				circuit_id,
-				user_id: caller_principal,
+				user_id,
 				is_enabled: true,
 				is_error: false,
 				is_running: false,
 				node_type: data,
-				order: node_id, // node_id is the order
+				order,
 				pins: vec![],
 				created_at: time(),
 				updated_at: time(),
@@ -164,6 +181,91 @@ impl NodesStore {
 		})
 	}
 
+	/// Add multiple nodes to a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `position` - Order of the first added node, later nodes move back. Appended if not provided
+	/// - `data` - Nodes data, in execution order
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Added nodes
+	pub fn add_nodes(
+		circuit_id: u32,
+		position: Option<u32>,
+		data: Vec<PostNode>,
+		caller_principal: Principal
+	) -> Result<Vec<Node>, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		// Nodes added by the main canister (e.g. imports) belong to the owner
+		let user_id = ConfigStore::get_config().owner;
+
+		NODES.with(|nodes| {
+			let mut nodes = nodes.borrow_mut();
+
+			let circuit_nodes = Self::sorted_circuit_nodes(&nodes, circuit_id);
+
+			let last_order = circuit_nodes
+				.iter()
+				.map(|node| node.order)
+				.max()
+				.unwrap_or(0);
+
+			let first_order = match position {
+				Some(position) if position > 0 && position <= last_order => position,
+				_ => last_order + 1,
+			};
+
+			// Copied or imported nodes can be inserted before the input node
+			let (nodes_before, nodes_after): (Vec<&Node>, Vec<&Node>) = circuit_nodes
+				.iter()
+				.partition(|node| node.order < first_order);
+			let node_types = nodes_before
+				.iter()
+				.map(|node| &node.node_type)
+				.chain(data.iter().map(|post_node| &post_node.node_type))
+				.chain(nodes_after.iter().map(|node| &node.node_type))
+				.collect::<Vec<&NodeType>>();
+			Self::validate_input_placement(&node_types)?;
+
+			// Make room for the added nodes
+			let shift = data.len() as u32;
+			for mut node in circuit_nodes.into_iter().filter(|node| node.order >= first_order) {
+				node.order += shift;
+				node.updated_at = time();
+				nodes.insert(node.id, node);
+			}
+
+			let mut added_nodes = vec![];
+
+			for (index, post_node) in data.into_iter().enumerate() {
+				let node_id = SequencesStore::next_id(NODES_SEQUENCE);
+
+				let new_node = Node {
+					id: node_id,
+					circuit_id,
+					user_id,
+					is_enabled: post_node.is_enabled,
+					is_error: false,
+					is_running: false,
+					node_type: post_node.node_type,
+					order: first_order + (index as u32),
+					pins: Self::assign_pin_ids(post_node.pins),
+					created_at: time(),
+					updated_at: time(),
+				};
+
+				// Add new node
+				nodes.insert(node_id, new_node.clone());
+				added_nodes.push(new_node);
+			}
+
+			Ok(added_nodes)
+		})
+	}
+
 	/// Edit a node.
 	///
 	/// # Arguments
@@ -173,8 +275,8 @@ impl NodesStore {
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn edit_node(node_id: u32, data: NodeType, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn edit_node(node_id: u32, data: NodeType, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
@@ -197,36 +299,105 @@ impl NodesStore {
 		})
 	}
 
-	/// Edit a node's order.
+	/// Edit a node's order. The node moves to the given position and the other nodes of the circuit shift,
+	/// so the order stays dense (1..n).
 	///
 	/// # Arguments
 	/// - `node_id` - Node ID
-	/// - `order` - Node order
+	/// - `order` - Node order, clamped to the number of nodes in the circuit
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn edit_order(node_id: u32, order: u32, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn edit_order(node_id: u32, order: u32, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		let (circuit_id, node_ids) = NODES.with(|nodes| {
+			let nodes = nodes.borrow();
+
+			let node = nodes.get(&node_id).ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
+
+			let mut node_ids = Self::sorted_circuit_nodes(&nodes, node.circuit_id)
+				.into_iter()
+				.map(|node| node.id)
+				.filter(|id| *id != node_id)
+				.collect::<Vec<u32>>();
+
+			let index = (order.max(1) as usize - 1).min(node_ids.len());
+			node_ids.insert(index, node_id);
+
+			Ok((node.circuit_id, node_ids))
+		})?;
+
+		let reordered_nodes = Self::reorder_nodes(circuit_id, node_ids, caller_principal)?;
+
+		reordered_nodes
+			.into_iter()
+			.find(|node| node.id == node_id)
+			.ok_or(ApiError::NotFound("NOT FOUND".to_string()))
+	}
+
+	/// Reorder all nodes of a circuit at once. The nodes get a dense order (1..n) following the given node IDs.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `node_ids` - IDs of all the circuit's nodes, in the new execution order
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<Node>` - Reordered nodes
+	pub fn reorder_nodes(
+		circuit_id: u32,
+		node_ids: Vec<u32>,
+		caller_principal: Principal
+	) -> Result<Vec<Node>, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
 
-			let node = nodes.get(&node_id);
-			if node.is_none() {
-				return Err(ApiError::NotFound("NOT FOUND".to_string()));
+			let mut circuit_node_ids = nodes
+				.iter()
+				.filter(|(_, node)| node.circuit_id == circuit_id)
+				.map(|(node_id, _)| node_id)
+				.collect::<Vec<u32>>();
+			circuit_node_ids.sort();
+
+			let mut sorted_node_ids = node_ids.clone();
+			sorted_node_ids.sort();
+
+			if sorted_node_ids != circuit_node_ids {
+				return Err(ApiError::BadRequest("NODE IDS DO NOT MATCH THE CIRCUIT NODES".to_string()));
 			}
 
-			let mut node = node.unwrap().clone();
+			let reordered_nodes = node_ids
+				.iter()
+				.filter_map(|node_id| nodes.get(node_id))
+				.collect::<Vec<Node>>();
 
-			// Mutate values
-			node.order = order;
-			node.updated_at = time();
+			Self::validate_input_placement(
+				&reordered_nodes
+					.iter()
+					.map(|node| &node.node_type)
+					.collect::<Vec<&NodeType>>()
+			)?;
 
-			// Add new node or overwrite existing one
-			nodes.insert(node_id, node.clone());
+			let mut updated_nodes = vec![];
 
-			Ok(node)
+			for (index, mut node) in reordered_nodes.into_iter().enumerate() {
+				let order = (index as u32) + 1;
+
+				if node.order != order {
+					node.order = order;
+					node.updated_at = time();
+
+					nodes.insert(node.id, node.clone());
+				}
+
+				updated_nodes.push(node);
+			}
+
+			Ok(updated_nodes)
 		})
 	}
 
@@ -239,8 +410,8 @@ impl NodesStore {
 	///
 	/// # Returns
 	/// - `Node` - Enabled node
-	pub fn toggle_node(node_id: u32, enabled: bool, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn toggle_node(node_id: u32, enabled: bool, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
@@ -263,174 +434,288 @@ impl NodesStore {
 		})
 	}
 
-	/// Add pin to node.
+	/// Add pin to node. The pin is appended to the pins of the node.
 	///
 	/// # Arguments
-	/// - `node_id` - Circuit ID
+	/// - `node_id` - Node ID
 	/// - `data` - Pin data
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn add_pin(node_id: u32, data: Pin, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn add_pin(node_id: u32, data: PostPin, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
 
-			let node = nodes.get(&node_id);
-			if node.is_none() {
-				return Err(ApiError::NotFound("NOT FOUND".to_string()));
+			let mut node = nodes.get(&node_id).ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
+
+			// Lookup pins can only be used once per node
+			if data.pin_type.is_unique() && node.pins.iter().any(|pin| pin.pin_type.name() == data.pin_type.name()) {
+				return Err(ApiError::BadRequest("ALREADY EXISTS".to_string()));
 			}
 
-			let mut node = node.unwrap().clone();
+			let order =
+				node.pins
+					.iter()
+					.map(|pin| pin.order)
+					.max()
+					.unwrap_or(0) + 1;
 
-			// Check if the pin already exists
-			let pin_index_opt = node.pins.iter().position(|pin| pin.pin_type == data.pin_type);
-			match pin_index_opt {
-				Some(_) => Err(ApiError::NotFound("ALREADY EXISTS".to_string())),
-				None => {
-					// Mutate values
-					node.pins.push(data);
+			// Mutate values
+			node.pins.push(Pin {
+				id: Some(SequencesStore::next_id(PINS_SEQUENCE)),
+				pin_type: data.pin_type,
+				order,
+			});
+			node.updated_at = time();
 
-					// Add new node or overwrite existing one
-					nodes.insert(node_id, node.clone());
+			// Add new node or overwrite existing one
+			nodes.insert(node_id, node.clone());
 
-					Ok(node)
-				}
-			}
+			Ok(node)
 		})
 	}
 
 	/// Edit pin of node.
 	///
 	/// # Arguments
-	/// - `node_id` - Circuit ID
+	/// - `node_id` - Node ID
+	/// - `pin_id` - Pin ID
 	/// - `data` - Pin data
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn edit_pin(node_id: u32, data: Pin, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn edit_pin(node_id: u32, pin_id: u32, data: PostPin, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
 
-			// Ensure the node exists
-			let node_opt = nodes.get(&node_id);
-			let mut node = match node_opt {
-				Some(n) => n,
-				None => {
-					return Err(ApiError::NotFound("NOT FOUND".to_string()));
-				}
-			};
+			let mut node = nodes.get(&node_id).ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
 
-			// Find the index of the pin to edit based on PinType
-			let pin_index_opt = node.pins.iter().position(|pin| {
-				match (&pin.pin_type, &data.pin_type) {
-					(PinType::PrePin(_), PinType::PrePin(_)) => true,
-					(PinType::PostPin(_), PinType::PostPin(_)) => true,
-					(PinType::PreMapperPin(_), PinType::PreMapperPin(_)) => true,
-					(PinType::PostMapperPin(_), PinType::PostMapperPin(_)) => true,
-					(PinType::FilterPin(_), PinType::FilterPin(_)) => true,
-					(PinType::LookupTransformPin(_), PinType::LookupTransformPin(_)) => true,
-					(PinType::LookupFilterPin(_), PinType::LookupFilterPin(_)) => true,
-					_ => false,
-				}
-			});
+			let pin_index = Self::find_pin_index(&node, pin_id)?;
 
-			let pin_index = match pin_index_opt {
-				Some(idx) => idx,
-				None => {
-					return Err(ApiError::NotFound("NODE INDEX NOT FOUND".to_string()));
-				}
-			};
+			// Lookup pins can only be used once per node
+			let is_duplicate = node.pins
+				.iter()
+				.any(|pin| pin.id != Some(pin_id) && pin.pin_type.name() == data.pin_type.name());
+
+			if data.pin_type.is_unique() && is_duplicate {
+				return Err(ApiError::BadRequest("ALREADY EXISTS".to_string()));
+			}
 
 			// Update the pin
-			node.pins[pin_index] = data;
+			node.pins[pin_index].pin_type = data.pin_type;
+			node.updated_at = time();
+
 			nodes.insert(node_id, node.clone());
 
 			Ok(node)
 		})
 	}
 
-	/// Delete pin from node.
+	/// Delete pin from node. The remaining pins keep their relative order.
 	///
 	/// # Arguments
-	/// - `node_id` - Circuit ID
+	/// - `node_id` - Node ID
 	/// - `pin_id` - Pin ID
 	/// - `caller_principal` - Principal of the caller
 	///
 	/// # Returns
 	/// - `Node` - Node
-	pub fn delete_pin(node_id: u32, data: Pin, _caller_principal: Principal) -> Result<Node, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+	pub fn delete_pin(node_id: u32, pin_id: u32, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
 
 		NODES.with(|nodes| {
 			let mut nodes = nodes.borrow_mut();
 
-			// Ensure the node exists
-			let node_opt = nodes.get(&node_id);
-			let mut node = match node_opt {
-				Some(n) => n,
-				None => {
-					return Err(ApiError::NotFound("NOT FOUND".to_string()));
-				}
-			};
+			let mut node = nodes.get(&node_id).ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
 
-			// Find the index of the pin to delete
-			let pin_index_opt = node.pins.iter().position(|pin| {
-				match (&pin.pin_type, &data.pin_type) {
-					(PinType::PrePin(_), PinType::PrePin(_)) => true,
-					(PinType::PostPin(_), PinType::PostPin(_)) => true,
-					(PinType::PreMapperPin(_), PinType::PreMapperPin(_)) => true,
-					(PinType::PostMapperPin(_), PinType::PostMapperPin(_)) => true,
-					(PinType::FilterPin(_), PinType::FilterPin(_)) => true,
-					(PinType::LookupTransformPin(_), PinType::LookupTransformPin(_)) => true,
-					(PinType::LookupFilterPin(_), PinType::LookupFilterPin(_)) => true,
-					_ => false,
-				}
-			});
+			let pin_index = Self::find_pin_index(&node, pin_id)?;
+
+			// Delete the pin and close the gap in the order
+			node.pins.remove(pin_index);
+			node.pins.sort_by_key(|pin| pin.order);
+			for (index, pin) in node.pins.iter_mut().enumerate() {
+				pin.order = (index as u32) + 1;
+			}
+			node.updated_at = time();
+
+			nodes.insert(node_id, node.clone());
+
+			Ok(node)
+		})
+	}
+
+	/// Reorder all pins of a node at once. The pins get a dense order (1..n) following the given pin IDs.
+	///
+	/// # Arguments
+	/// - `node_id` - Node ID
+	/// - `pin_ids` - IDs of all the node's pins, in the new execution order
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Node` - Node
+	pub fn reorder_pins(node_id: u32, pin_ids: Vec<u32>, caller_principal: Principal) -> Result<Node, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		NODES.with(|nodes| {
+			let mut nodes = nodes.borrow_mut();
+
+			let mut node = nodes.get(&node_id).ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
+
+			let mut node_pin_ids = node.pins
+				.iter()
+				.filter_map(|pin| pin.id)
+				.collect::<Vec<u32>>();
+			node_pin_ids.sort();
+
+			let mut sorted_pin_ids = pin_ids.clone();
+			sorted_pin_ids.sort();
+
+			if node_pin_ids.len() != node.pins.len() || sorted_pin_ids != node_pin_ids {
+				return Err(ApiError::BadRequest("PIN IDS DO NOT MATCH THE NODE PINS".to_string()));
+			}
 
-			let pin_index = match pin_index_opt {
-				Some(idx) => idx,
-				None => {
-					return Err(ApiError::NotFound("NODE INDEX NOT FOUND".to_string()));
+			// Mutate values
+			for pin in node.pins.iter_mut() {
+				if let Some(index) = pin_ids.iter().position(|pin_id| pin.id == Some(*pin_id)) {
+					pin.order = (index as u32) + 1;
 				}
-			};
+			}
+			node.pins.sort_by_key(|pin| pin.order);
+			node.updated_at = time();
 
-			// Delete the pin
-			node.pins.remove(pin_index);
 			nodes.insert(node_id, node.clone());
 
 			Ok(node)
 		})
 	}
 
-	/// Preview lookup canister request
+	/// Preview lookup canister request. Previews with a canister connector or Candid arguments call the canister
+	/// directly and return the reply as JSON, other previews go through the proxy. When previewing a node, the
+	/// reply goes through the lookup pins of the node, like in an execution, and the result is stored as the sample
+	/// data of the node.
 	///
 	/// # Arguments
 	/// - `data` - LookupCanisterPreview
 	///
 	/// # Returns
-	/// - `Unknown` - Unknown data from the canister
+	/// - `String` - Stringified JSON reply, or the data the node merges when previewing a node
 	pub async fn preview_lookup_canister(
 		data: LookupCanisterPreview,
-		_caller_principal: Principal
+		caller_principal: Principal
 	) -> Result<String, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+		ConfigStore::validate_owner(caller_principal)?;
+
+		let reply = if data.connector_id.is_some() || data.args.iter().any(|arg| matches!(arg, PreviewArg::Candid(_))) {
+			let reply = Self::call_canister(&data).await?;
+
+			serde_json
+				::to_string(&reply)
+				.map_err(|err| ApiError::BadRequest(format!("FAILED TO SERIALIZE REPLY: {err}")))?
+		} else {
+			let body = serde_json::json!({
+				"canisterId": data.canister,
+				"methodName": data.method,
+				"args": data.args,
+			});
 
-		let body = serde_json::json!({
-			"canisterId": data.canister,
-			"methodName": data.method,
-			"args": data.args,
-		});
+			forward_http_request(body, data.cycles, URL_ICC).await?
+		};
+
+		match data.node_id {
+			Some(node_id) => Self::set_lookup_sample_data(node_id, &reply),
+			None => Ok(reply),
+		}
+	}
+
+	/// Apply the lookup pins of a lookup canister node to a reply and store the result as the sample data of the node.
+	///
+	/// # Arguments
+	/// - `node_id` - Node ID
+	/// - `reply` - Stringified reply of the lookup
+	///
+	/// # Returns
+	/// - `String` - Stringified JSON data the node merges, `{}` if the reply is filtered out
+	fn set_lookup_sample_data(node_id: u32, reply: &str) -> Result<String, ApiError> {
+		NODES.with(|nodes| {
+			let mut nodes = nodes.borrow_mut();
+
+			let mut node = nodes.get(&node_id).ok_or(ApiError::NotFound("NOT FOUND".to_string()))?;
+
+			let NodeType::LookupCanister(lookup_canister) = &mut node.node_type else {
+				return Err(ApiError::BadRequest("NODE IS NOT A LOOKUP CANISTER NODE".to_string()));
+			};
+
+			let reply = serde_json::from_str::<Value>(reply).unwrap_or(Value::String(reply.to_string()));
+
+			let mut merged = Value::Object(Map::new());
+			if let Some((path, value)) = transform_lookup_reply(&node.pins, node_id, &reply) {
+				set_path(&mut merged, &path, value);
+			}
+
+			lookup_canister.sample_data = merged.to_string();
+			node.updated_at = time();
+
+			nodes.insert(node_id, node.clone());
+
+			Ok(merged.to_string())
+		})
+	}
+
+	/// Call a canister directly with Candid arguments. With a canister connector the arguments are converted to the
+	/// argument types of the method and the reply is decoded with its return types.
+	///
+	/// # Arguments
+	/// - `data` - LookupCanisterPreview
+	///
+	/// # Returns
+	/// - `Value` - Reply of the canister as JSON
+	async fn call_canister(data: &LookupCanisterPreview) -> Result<Value, ApiError> {
+		let (canister, signature) = match data.connector_id {
+			Some(connector_id) => {
+				let canister_connector = ConnectorsStore::get_canister_connector(connector_id).await?;
+				let signature = method_signature(&canister_connector.candid, &data.method)?;
+
+				(canister_connector.canister_id, Some(signature))
+			}
+			None => (data.canister, None),
+		};
 
-		Self::http_request_call(body, data.cycles, URL_ICC).await
+		let values = data.args
+			.iter()
+			.enumerate()
+			.map(|(index, arg)| {
+				let parameter = signature
+					.as_ref()
+					.and_then(|(env, arg_types, _)| arg_types.get(index).map(|arg_type| (env, arg_type)));
+
+				candid_value_from_preview_arg(arg, parameter, &format!("args.{index}"))
+			})
+			.collect::<Result<Vec<_>, ApiError>>()?;
+
+		let args = encode_candid_args(
+			&values,
+			signature.as_ref().map(|(env, arg_types, _)| (env, arg_types.as_slice()))
+		)?;
+
+		let reply = call_raw128(canister, &data.method, args, data.cycles).await.map_err(|(code, message)|
+			ApiError::InterCanister(format!("{:?}: {}", code, message))
+		)?;
+
+		decode_candid_reply(
+			&reply,
+			signature.as_ref().map(|(env, _, return_types)| (env, return_types.as_slice()))
+		)
 	}
 
-	/// Preview lookup HTTP Request
+	/// Preview lookup HTTP Request. Secrets of the vault and the authentication of connectors are only used when
+	/// previewing a stored node, ad-hoc previews cannot reference them, so they cannot send the secrets of the
+	/// owner to any URL.
 	///
 	/// # Arguments
 	/// - `data` - LookupHttpRequestPreview
@@ -439,105 +724,191 @@ impl NodesStore {
 	/// - `Unknown` - Unknown data from the API
 	pub async fn preview_lookup_http_request(
 		data: LookupHttpRequestPreview,
-		_caller_principal: Principal
+		caller_principal: Principal
 	) -> Result<String, ApiError> {
-		// let canister_owner = CANISTER_OWNER.with(|canister_owner| canister_owner.borrow().get().clone());
+		ConfigStore::validate_owner(caller_principal)?;
+
+		let (mut url, method, mut headers, mut request_body, connector, cycles) = match data.node_id {
+			Some(node_id) => {
+				let node = Self::get_circuit_node(node_id, caller_principal)?;
+
+				let NodeType::LookupHttpRequest(request) = node.node_type else {
+					return Err(ApiError::BadRequest("NODE IS NOT A LOOKUP HTTP REQUEST NODE".to_string()));
+				};
+
+				(request.url, request.method, request.headers, request.request_body, request.connector, request.cycles)
+			}
+			None => {
+				let mut templates = vec![&data.url];
+				templates.extend(data.headers.values());
+				templates.extend(data.request_body.as_ref());
+
+				if templates.iter().any(|template| !secret_references(template).is_empty()) {
+					return Err(ApiError::BadRequest("SECRETS CAN ONLY BE USED WHEN PREVIEWING A NODE".to_string()));
+				}
+
+				if data.connector.is_some() {
+					return Err(ApiError::BadRequest("CONNECTORS CAN ONLY BE USED WHEN PREVIEWING A NODE".to_string()));
+				}
+
+				(data.url, data.method, data.headers, data.request_body, None, data.cycles)
+			}
+		};
+
+		// Secrets of the vault are referenced as `{{secret.NAME}}` in the URL, headers and body
+		let mut templates = vec![&mut url];
+		templates.extend(headers.values_mut());
+		templates.extend(request_body.as_mut());
+		SecretsStore::render_secrets(templates).await?;
+
+		let (url, headers) = Self::resolve_http_request(
+			&method,
+			url,
+			headers,
+			request_body.as_deref(),
+			connector,
+			cycles
+		).await?;
 
 		let body =
 			serde_json::json!({
-			"url": data.url,
-			"method": data.method,
-			"requestBody": data.request_body,
-			"headers": data.headers,
+			"url": url,
+			"method": method,
+			"requestBody": request_body,
+			"headers": headers,
 		});
 
-		Self::http_request_call(body, data.cycles, URL_API_FORWARDING).await
+		forward_http_request(body, cycles, URL_API_FORWARDING).await
 	}
 
-	/// Canister HTTP Request call
+	/// Resolve the URL and headers of an HTTP request. If the request references a connector, the URL is built from
+	/// the base URL of the connector and the headers of the connector apply, overridden by the request headers.
+	/// The authentication of the connector is applied last, so it cannot be overridden by the request. Signatures are
+	/// computed from the current time, so the request should be sent right away.
 	///
 	/// # Arguments
-	/// - `body` - Value
-	/// - `cycles` - u128
-	async fn http_request_call(body: Value, cycles: u128, url: &str) -> Result<String, ApiError> {
-		// Prepare headers for the system http_request call
-		let request_headers = vec![
-			HttpHeader {
-				name: "Host".to_string(),
-				value: format!("{HOST}:443"),
-			},
-			HttpHeader {
-				name: "User-Agent".to_string(),
-				value: "demo_HTTP_POST_canister".to_string(),
-			},
-			HttpHeader {
-				name: "Idempotency-Key".to_string(),
-				value: generate_idempotency_key().await.unwrap(),
-			},
-			HttpHeader {
-				name: "Content-Type".to_string(),
-				value: "application/json".to_string(),
+	/// - `method` - Method of the request
+	/// - `url` - URL of the request
+	/// - `headers` - Headers of the request
+	/// - `body` - Body of the request, signed by some authentication methods
+	/// - `connector` - Connector referenced by the request
+	/// - `cycles` - Cycles attached to requests made to authenticate, e.g. fetching an OAuth2 access token
+	///
+	/// # Returns
+	/// - `(String, Headers)` - URL and headers to send
+	pub async fn resolve_http_request(
+		method: &HttpRequestMethod,
+		url: String,
+		headers: Headers,
+		body: Option<&str>,
+		connector: Option<ConnectorReference>,
+		cycles: u128
+	) -> Result<(String, Headers), ApiError> {
+		let connector = match connector {
+			Some(connector) => connector,
+			None => {
+				return Ok((url, headers));
 			}
-		];
-
-		let request_body: Option<Vec<u8>> = Some(body.to_string().into_bytes());
-
-		let request = CanisterHttpRequestArgument {
-			url: url.to_string(),
-			max_response_bytes: None, //optional for request
-			method: HttpMethod::POST,
-			headers: request_headers,
-			body: request_body,
-			transform: Some(TransformContext {
-				function: TransformFunc(candid::Func {
-					method: "transform".into(),
-					principal: ic_cdk::id(),
-				}),
-				context: vec![],
-			}),
 		};
 
-		match http_request(request, cycles).await {
-			Ok((response,)) => {
-				// if successful, `HttpResponse` has this structure:
-				// pub struct HttpResponse {
-				//     pub status: Nat,
-				//     pub headers: Vec<HttpHeader>,
-				//     pub body: Vec<u8>,
-				// }
-
-				// You need to decode that Vec<u8> that is the body into readable text.
-				// To do this:
-				//  1. Call `String::from_utf8()` on response.body
-				//  2. You use a switch to explicitly call out both cases of decoding the Blob into ?Text
-
-				// The API response will looks like this:
-
-				// ("[[1682978460,5.714,5.718,5.714,5.714,243.5678]]")
-
-				// Which can be formatted as this
-				//  [
-				//     [
-				//         1682978460, <-- start/timestamp
-				//         5.714, <-- low
-				//         5.718, <-- high
-				//         5.714, <-- open
-				//         5.714, <-- close
-				//         243.5678 <-- volume
-				//     ],
-				//  ]
-
-				// Return the body as a string and end the method
-				let str_body = String::from_utf8(response.body).expect("Transformed response is not UTF-8 encoded.");
-
-				Ok(str_body)
+		let http_connector = ConnectorsStore::get_http_connector(connector.connector_id).await?;
+
+		let mut request_headers = http_connector.headers.clone();
+		request_headers.extend(headers);
+
+		if let Authentication::OAuth2(oauth2_config) = &http_connector.authentication {
+			let access_token = OAuth2Store::get_access_token(connector.connector_id, oauth2_config, cycles).await?;
+
+			let url = http_connector.url(&connector.path)?;
+
+			return Ok(apply_token(&access_token, &oauth2_config.location, url, request_headers));
+		}
+
+		apply_authentication(
+			&http_connector.authentication,
+			method,
+			http_connector.url(&connector.path)?,
+			request_headers,
+			body,
+			None,
+			time() / 1_000_000_000
+		)
+	}
+
+	/// Give pins new IDs and a dense order (1..n), keeping their relative order.
+	///
+	/// # Arguments
+	/// - `pins` - Pins
+	///
+	/// # Returns
+	/// - `Vec<Pin>` - Pins with IDs
+	pub fn assign_pin_ids(mut pins: Vec<Pin>) -> Vec<Pin> {
+		pins.sort_by_key(|pin| pin.order);
+
+		pins.into_iter()
+			.enumerate()
+			.map(|(index, pin)| Pin {
+				id: Some(SequencesStore::next_id(PINS_SEQUENCE)),
+				pin_type: pin.pin_type,
+				order: (index as u32) + 1,
+			})
+			.collect()
+	}
+
+	/// Get the nodes of a circuit in execution order. Nodes with the same order are sorted by ID.
+	fn sorted_circuit_nodes(nodes: &StableBTreeMap<u32, Node, Memory>, circuit_id: u32) -> Vec<Node> {
+		let mut circuit_nodes = nodes
+			.iter()
+			.filter(|(_, node)| node.circuit_id == circuit_id)
+			.map(|(_, node)| node)
+			.collect::<Vec<Node>>();
+
+		circuit_nodes.sort_by_key(|node| (node.order, node.id));
+		circuit_nodes
+	}
+
+	/// Give the nodes of a circuit a dense order (1..n), keeping their relative order.
+	fn renumber_nodes(nodes: &mut StableBTreeMap<u32, Node, Memory>, circuit_id: u32) {
+		for (index, mut node) in Self::sorted_circuit_nodes(nodes, circuit_id).into_iter().enumerate() {
+			let order = (index as u32) + 1;
+
+			if node.order != order {
+				node.order = order;
+				node.updated_at = time();
+
+				nodes.insert(node.id, node);
 			}
-			Err((r, m)) => {
-				let message = format!("The http_request resulted into error. RejectionCode: {r:?}, Error: {m}");
+		}
+	}
 
-				//Return the error as a string and end the method
-				Err(ApiError::InterCanister(message))
+	/// Check that no node is placed before the input node, as it would run without data.
+	///
+	/// # Arguments
+	/// - `node_types` - Node types of a circuit, in execution order
+	///
+	/// # Returns
+	/// - `Result<(), ApiError>` - Unit, or an error if a node is placed before the input node
+	fn validate_input_placement(node_types: &[&NodeType]) -> Result<(), ApiError> {
+		if let Some(input_index) = node_types.iter().position(|node_type| node_type.is_input()) {
+			let nodes_before_input = &node_types[..input_index];
+
+			if nodes_before_input.iter().any(|node_type| matches!(node_type, NodeType::Output(_))) {
+				return Err(ApiError::BadRequest("OUTPUT NODE BEFORE INPUT NODE".to_string()));
+			}
+
+			if !nodes_before_input.is_empty() {
+				return Err(ApiError::BadRequest("NODE BEFORE INPUT NODE".to_string()));
 			}
 		}
+
+		Ok(())
+	}
+
+	/// Find the index of a pin within a node.
+	fn find_pin_index(node: &Node, pin_id: u32) -> Result<usize, ApiError> {
+		node.pins
+			.iter()
+			.position(|pin| pin.id == Some(pin_id))
+			.ok_or(ApiError::NotFound("PIN NOT FOUND".to_string()))
 	}
 }
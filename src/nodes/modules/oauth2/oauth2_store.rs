@@ -0,0 +1,62 @@
+use ic_cdk::api::time;
+use lib::{
+	types::{ api_error::ApiError, connector::OAuth2Config },
+	utils::oauth2::{ fetch_oauth2_token, is_oauth2_token_valid },
+};
+use crate::canister_storage::OAUTH2_TOKENS;
+
+pub struct OAuth2Store;
+
+impl OAuth2Store {
+	/// Get an access token for an OAuth2 connector. Cached tokens are used until they expire, after which they are
+	/// refreshed with the refresh token if there is one, or fetched again with the client credentials.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	/// - `config` - OAuth2 configuration of the connector
+	/// - `cycles` - Cycles attached to the token request
+	///
+	/// # Returns
+	/// - `String` - Access token
+	pub async fn get_access_token(connector_id: u32, config: &OAuth2Config, cycles: u128) -> Result<String, ApiError> {
+		let now_seconds = time() / 1_000_000_000;
+		let cached_token = OAUTH2_TOKENS.with(|tokens| tokens.borrow().get(&connector_id));
+
+		if let Some(cached_token) = &cached_token {
+			if is_oauth2_token_valid(cached_token, config, now_seconds) {
+				return Ok(cached_token.access_token.clone());
+			}
+		}
+
+		// A refresh token returned by the token endpoint replaces the configured one, unless the configuration changed
+		let refresh_token = cached_token
+			.filter(|cached_token| is_oauth2_token_valid(cached_token, config, 0))
+			.and_then(|cached_token| cached_token.refresh_token)
+			.or(config.refresh_token.clone());
+
+		// A rejected refresh token is dropped, so the next request starts over from the configuration
+		let token = match fetch_oauth2_token(config, refresh_token, cycles).await {
+			Ok(token) => token,
+			Err(err) => {
+				Self::clear_token(connector_id);
+				return Err(err);
+			}
+		};
+
+		OAUTH2_TOKENS.with(|tokens| {
+			tokens.borrow_mut().insert(connector_id, token.clone());
+		});
+
+		Ok(token.access_token)
+	}
+
+	/// Remove the cached token of a connector.
+	///
+	/// # Arguments
+	/// - `connector_id` - Connector ID
+	pub fn clear_token(connector_id: u32) {
+		OAUTH2_TOKENS.with(|tokens| {
+			tokens.borrow_mut().remove(&connector_id);
+		})
+	}
+}
@@ -0,0 +1,55 @@
+use ic_cdk::{ caller, query, update };
+use lib::{
+	types::{ api_error::ApiError, circuit_revision::{ CircuitRevision, NodeChange } },
+	utils::validate::validate_anonymous,
+};
+use super::revisions_store::RevisionsStore;
+
+#[query]
+fn get_circuit_revisions(circuit_id: u32) -> Result<Vec<CircuitRevision>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => RevisionsStore::get_circuit_revisions(circuit_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn get_circuit_revision(circuit_id: u32, revision: u32) -> Result<CircuitRevision, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => RevisionsStore::get_circuit_revision(circuit_id, revision, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn get_published_revision(circuit_id: u32) -> Result<CircuitRevision, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => RevisionsStore::get_published_revision(circuit_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[query]
+fn diff_revisions(circuit_id: u32, from_revision: u32, to_revision: Option<u32>) -> Result<Vec<NodeChange>, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) =>
+			RevisionsStore::diff_revisions(circuit_id, from_revision, to_revision, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn publish_circuit(circuit_id: u32) -> Result<CircuitRevision, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => RevisionsStore::publish_circuit(circuit_id, caller_principal),
+		Err(err) => Err(err),
+	}
+}
+
+#[update]
+fn rollback_to(circuit_id: u32, revision: u32) -> Result<CircuitRevision, ApiError> {
+	match validate_anonymous(&caller()) {
+		Ok(caller_principal) => RevisionsStore::rollback_to(circuit_id, revision, caller_principal),
+		Err(err) => Err(err),
+	}
+}
@@ -0,0 +1,237 @@
+use candid::Principal;
+use ic_cdk::api::time;
+use lib::types::{
+	api_error::ApiError,
+	circuit_revision::{ CircuitRevision, NodeChange },
+	circuit_revision_key::CircuitRevisionKey,
+	circuit_validation::CircuitValidation,
+	node::Node,
+};
+use crate::{ canister_storage::{ NODES, REVISIONS }, modules::config::config_store::ConfigStore };
+
+pub struct RevisionsStore;
+
+impl RevisionsStore {
+	/// Get all published revisions of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<CircuitRevision>` - Revisions, oldest first
+	pub fn get_circuit_revisions(
+		circuit_id: u32,
+		caller_principal: Principal
+	) -> Result<Vec<CircuitRevision>, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		REVISIONS.with(|revisions| {
+			let revisions = revisions.borrow();
+
+			let circuit_revisions = revisions
+				.range(Self::first_key(circuit_id)..=Self::last_key(circuit_id))
+				.map(|(_, revision)| revision.clone())
+				.collect::<Vec<CircuitRevision>>();
+
+			Ok(circuit_revisions)
+		})
+	}
+
+	/// Get a published revision of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `revision` - Revision number
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Revision
+	pub fn get_circuit_revision(
+		circuit_id: u32,
+		revision: u32,
+		caller_principal: Principal
+	) -> Result<CircuitRevision, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		REVISIONS.with(|revisions| {
+			let revisions = revisions.borrow();
+
+			revisions
+				.get(&(CircuitRevisionKey { circuit_id, revision }))
+				.ok_or(ApiError::NotFound("REVISION NOT FOUND".to_string()))
+		})
+	}
+
+	/// Get the revision executions should run, which is the latest published revision.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Published revision
+	pub fn get_published_revision(circuit_id: u32, caller_principal: Principal) -> Result<CircuitRevision, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		Self::latest_revision(circuit_id).ok_or(ApiError::NotFound("NOT PUBLISHED".to_string()))
+	}
+
+	/// Publish the draft (the current nodes) of a circuit as a new immutable revision. Drafts with blocking
+	/// validation errors cannot be published, as executions run the published revision.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Published revision
+	pub fn publish_circuit(circuit_id: u32, caller_principal: Principal) -> Result<CircuitRevision, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		let draft_nodes = Self::draft_nodes(circuit_id);
+
+		if draft_nodes.is_empty() {
+			return Err(ApiError::BadRequest("CIRCUIT HAS NO NODES".to_string()));
+		}
+
+		CircuitValidation::validate(circuit_id, &draft_nodes).ensure_valid()?;
+
+		if let Some(latest_revision) = Self::latest_revision(circuit_id) {
+			if CircuitRevision::diff(&latest_revision.nodes, &draft_nodes).is_empty() {
+				return Err(ApiError::BadRequest("NO CHANGES TO PUBLISH".to_string()));
+			}
+		}
+
+		Ok(Self::insert_revision(circuit_id, draft_nodes, caller_principal))
+	}
+
+	/// Diff two revisions of a circuit.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `from_revision` - Revision to compare from
+	/// - `to_revision` - Revision to compare to. The draft is used if not provided
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `Vec<NodeChange>` - Changes between the revisions
+	pub fn diff_revisions(
+		circuit_id: u32,
+		from_revision: u32,
+		to_revision: Option<u32>,
+		caller_principal: Principal
+	) -> Result<Vec<NodeChange>, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		let from_nodes = Self::get_circuit_revision(circuit_id, from_revision, caller_principal)?.nodes;
+
+		let to_nodes = match to_revision {
+			Some(revision) => Self::get_circuit_revision(circuit_id, revision, caller_principal)?.nodes,
+			None => Self::draft_nodes(circuit_id),
+		};
+
+		Ok(CircuitRevision::diff(&from_nodes, &to_nodes))
+	}
+
+	/// Roll a circuit back to a published revision. The draft is replaced by the revision's nodes
+	/// and those nodes are published again as a new revision, so the history stays append-only.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	/// - `revision` - Revision to roll back to
+	/// - `caller_principal` - Principal of the caller
+	///
+	/// # Returns
+	/// - `CircuitRevision` - Newly published revision
+	pub fn rollback_to(circuit_id: u32, revision: u32, caller_principal: Principal) -> Result<CircuitRevision, ApiError> {
+		ConfigStore::validate_owner(caller_principal)?;
+
+		let target_revision = Self::get_circuit_revision(circuit_id, revision, caller_principal)?;
+
+		// Revisions published before validation was enforced may not be valid
+		CircuitValidation::validate(circuit_id, &target_revision.nodes).ensure_valid()?;
+
+		NODES.with(|nodes| {
+			let mut nodes = nodes.borrow_mut();
+
+			// Replace the draft nodes with the nodes of the revision
+			let draft_node_ids = nodes
+				.iter()
+				.filter(|(_, node)| node.circuit_id == circuit_id)
+				.map(|(node_id, _)| node_id)
+				.collect::<Vec<u32>>();
+
+			for node_id in draft_node_ids {
+				nodes.remove(&node_id);
+			}
+
+			for node in target_revision.nodes.iter() {
+				nodes.insert(node.id, node.clone());
+			}
+		});
+
+		Ok(Self::insert_revision(circuit_id, target_revision.nodes, caller_principal))
+	}
+
+	fn insert_revision(circuit_id: u32, nodes: Vec<Node>, caller_principal: Principal) -> CircuitRevision {
+		let revision = Self::latest_revision(circuit_id)
+			.map(|revision| revision.revision)
+			.unwrap_or(0) + 1;
+
+		let new_revision = CircuitRevision {
+			revision,
+			circuit_id,
+			user_id: caller_principal,
+			nodes,
+			published_at: time(),
+		};
+
+		REVISIONS.with(|revisions| {
+			revisions.borrow_mut().insert(CircuitRevisionKey { circuit_id, revision }, new_revision.clone());
+		});
+
+		new_revision
+	}
+
+	/// Get the latest published revision of a circuit, the revision executions run.
+	///
+	/// # Arguments
+	/// - `circuit_id` - Circuit ID
+	///
+	/// # Returns
+	/// - `Option<CircuitRevision>` - Revision, if the circuit was published
+	pub fn latest_revision(circuit_id: u32) -> Option<CircuitRevision> {
+		REVISIONS.with(|revisions| {
+			let revisions = revisions.borrow();
+
+			revisions
+				.range(Self::first_key(circuit_id)..=Self::last_key(circuit_id))
+				.last()
+				.map(|(_, revision)| revision)
+		})
+	}
+
+	fn draft_nodes(circuit_id: u32) -> Vec<Node> {
+		NODES.with(|nodes| {
+			let nodes = nodes.borrow();
+
+			let mut draft_nodes = nodes
+				.iter()
+				.filter(|(_, node)| node.circuit_id == circuit_id)
+				.map(|(_, node)| node.clone())
+				.collect::<Vec<Node>>();
+
+			draft_nodes.sort_by_key(|node| node.order);
+			draft_nodes
+		})
+	}
+
+	fn first_key(circuit_id: u32) -> CircuitRevisionKey {
+		CircuitRevisionKey { circuit_id, revision: u32::MIN }
+	}
+
+	fn last_key(circuit_id: u32) -> CircuitRevisionKey {
+		CircuitRevisionKey { circuit_id, revision: u32::MAX }
+	}
+}
@@ -0,0 +1,52 @@
+use std::collections::HashMap;
+use ic_cdk::call;
+use lib::{ types::api_error::ApiError, utils::vault::{ render_secrets, secret_references } };
+use crate::modules::config::config_store::ConfigStore;
+
+pub struct SecretsStore;
+
+impl SecretsStore {
+	/// Get secrets of the circuit owner from the main canister. Secrets are not cached, so rotations apply immediately.
+	///
+	/// # Arguments
+	/// - `names` - Names of the secrets
+	///
+	/// # Returns
+	/// - `HashMap<String, String>` - Values of the secrets by name
+	pub async fn get_secrets(names: Vec<String>) -> Result<HashMap<String, String>, ApiError> {
+		if names.is_empty() {
+			return Ok(HashMap::new());
+		}
+
+		let main_canister = ConfigStore::get_config().main_canister;
+
+		let (result,): (Result<HashMap<String, String>, ApiError>,) = call(
+			main_canister,
+			"get_node_canister_secrets",
+			(names,)
+		).await.map_err(|(_, message)| ApiError::InterCanister(message))?;
+
+		result
+	}
+
+	/// Render the `{{secret.NAME}}` references in templates with the secrets of the circuit owner.
+	///
+	/// # Arguments
+	/// - `templates` - Templates, rendered in place
+	pub async fn render_secrets(mut templates: Vec<&mut String>) -> Result<(), ApiError> {
+		let mut names = templates
+			.iter()
+			.flat_map(|template| secret_references(template))
+			.collect::<Vec<String>>();
+		names.sort();
+		names.dedup();
+
+		let secrets = Self::get_secrets(names).await?;
+
+		for template in templates.iter_mut() {
+			**template = render_secrets(template, &secrets);
+		}
+
+		Ok(())
+	}
+}
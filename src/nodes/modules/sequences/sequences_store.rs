@@ -0,0 +1,91 @@
+use lib::{ types::node::{ Node, Pin }, utils::sequence::{ next_id, seed, NODES_SEQUENCE, PINS_SEQUENCE } };
+use crate::canister_storage::{ NODES, REVISIONS, SEQUENCES };
+
+pub struct SequencesStore;
+
+impl SequencesStore {
+	/// Allocate the next ID of a sequence.
+	///
+	/// # Arguments
+	/// - `name` - Name of the sequence
+	///
+	/// # Returns
+	/// - `u32` - Allocated ID
+	pub fn next_id(name: &str) -> u32 {
+		SEQUENCES.with(|sequences| next_id(&mut sequences.borrow_mut(), name))
+	}
+
+	/// Seed the sequences from the existing data and give pins created before pins had IDs an ID.
+	/// Nodes are keyed by ID, so a reused node ID replaced the previous node instead of colliding with it.
+	/// Safe to run on every upgrade, sequences are only moved forward.
+	pub fn migrate() {
+		let max_node_id = NODES.with(|nodes| nodes.borrow().last_key_value().map(|(key, _)| key));
+		let max_pin_id = NODES.with(|nodes|
+			nodes
+				.borrow()
+				.iter()
+				.flat_map(|(_, node)| node.pins.into_iter().filter_map(|pin| pin.id))
+				.max()
+		);
+
+		SEQUENCES.with(|sequences| {
+			let mut sequences = sequences.borrow_mut();
+
+			seed(&mut sequences, NODES_SEQUENCE, max_node_id.unwrap_or(0));
+			seed(&mut sequences, PINS_SEQUENCE, max_pin_id.unwrap_or(0));
+		});
+
+		NODES.with(|nodes| {
+			let mut nodes = nodes.borrow_mut();
+
+			let unmigrated_nodes = nodes
+				.iter()
+				.filter(|(_, node)| node.pins.iter().any(|pin| pin.id.is_none()))
+				.collect::<Vec<_>>();
+
+			for (node_id, mut node) in unmigrated_nodes {
+				node.pins = Self::migrate_pins(node.pins, None);
+				nodes.insert(node_id, node);
+			}
+		});
+
+		// Pins of published revisions get the ID of the matching draft pin, so diffs stay meaningful
+		REVISIONS.with(|revisions| {
+			let mut revisions = revisions.borrow_mut();
+
+			let unmigrated_revisions = revisions
+				.iter()
+				.filter(|(_, revision)| revision.nodes.iter().any(|node| node.pins.iter().any(|pin| pin.id.is_none())))
+				.collect::<Vec<_>>();
+
+			for (key, mut revision) in unmigrated_revisions {
+				for node in revision.nodes.iter_mut() {
+					let draft_node = NODES.with(|nodes| nodes.borrow().get(&node.id));
+					node.pins = Self::migrate_pins(node.pins.clone(), draft_node.as_ref());
+				}
+
+				revisions.insert(key, revision);
+			}
+		})
+	}
+
+	/// Give pins without an ID an ID, reusing the ID of the matching pin of the draft node if there is one.
+	fn migrate_pins(pins: Vec<Pin>, draft_node: Option<&Node>) -> Vec<Pin> {
+		pins.into_iter()
+			.map(|mut pin| {
+				if pin.id.is_none() {
+					let draft_pin_id = draft_node.and_then(|draft_node| {
+						draft_node.pins
+							.iter()
+							.find(|draft_pin| draft_pin.pin_type == pin.pin_type && draft_pin.order == pin.order)
+							.and_then(|draft_pin| draft_pin.id)
+					});
+
+					pin.id = Some(draft_pin_id.unwrap_or_else(|| Self::next_id(PINS_SEQUENCE)));
+				}
+
+				pin
+			})
+			.collect()
+	}
+}